@@ -0,0 +1,22 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Generates the C header into `OUT_DIR`, and also into `include/` if
+/// `RUST_BCF_FFI_UPDATE_HEADER` is set, which updates the committed header.
+fn main() {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_root_or_default(&crate_dir);
+    let bindings = cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("failed to generate the C header");
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    bindings.write_to_file(out_dir.join("include/rust_bcf.h"));
+    if env::var_os("RUST_BCF_FFI_UPDATE_HEADER").is_some() {
+        bindings.write_to_file(PathBuf::from(&crate_dir).join("include/rust_bcf.h"));
+    }
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=RUST_BCF_FFI_UPDATE_HEADER");
+}
@@ -0,0 +1,242 @@
+#ifndef RUST_BCF_H
+#define RUST_BCF_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The value of missing integers.
+#define BCF_INT32_MISSING INT32_MIN
+
+// The padding of integer FORMAT values of samples with fewer values than others.
+#define BCF_INT32_VECTOR_END (INT32_MIN + 1)
+
+// The bits of missing floats, a NaN.
+#define BCF_FLOAT_MISSING_BITS 2139095041
+
+// The bits of the padding of float FORMAT values, a NaN.
+#define BCF_FLOAT_VECTOR_END_BITS 2139095042
+
+// The value of missing alleles, see `bcf_record_genotypes`.
+#define BCF_ALLELE_MISSING -1
+
+// The padding of alleles of calls with a lower ploidy than others.
+#define BCF_ALLELE_VECTOR_END -2
+
+typedef enum BcfStatus {
+  BCF_STATUS_OK = 0,
+  // There are no more records
+  BCF_STATUS_END = 1,
+  BCF_STATUS_NULL_POINTER = -1,
+  BCF_STATUS_IO = -2,
+  // Invalid input, such as a malformed file or a non-UTF-8 tag
+  BCF_STATUS_PARSE = -3,
+  // The field is not present in the record
+  BCF_STATUS_NOT_FOUND = -4,
+  // The field has a different type than requested
+  BCF_STATUS_WRONG_TYPE = -5,
+  // An index is out of range
+  BCF_STATUS_OUT_OF_RANGE = -6,
+} BcfStatus;
+
+// The header of a BCF file, owned by its reader.
+typedef struct BcfHeader BcfHeader;
+
+// A reader of BCF files.
+typedef struct BcfReader BcfReader;
+
+// A BCF record, which stays valid independently of its reader.
+typedef struct BcfRecord BcfRecord;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the message of the last error of this thread, or NULL. The message is valid until
+// the next error.
+const char *bcf_last_error(void);
+
+// Opens a (possibly gzip-compressed) BCF file, or returns NULL on error.
+//
+// # Safety
+// `path` must be a NUL-terminated string.
+struct BcfReader *bcf_reader_open(const char *path);
+
+// Closes a reader; NULL is ignored.
+//
+// # Safety
+// `reader` must be NULL or returned by `bcf_reader_open`, and not be used afterwards.
+void bcf_reader_free(struct BcfReader *reader);
+
+// Returns the header of a reader, which is valid as long as the reader.
+//
+// # Safety
+// `reader` must be a valid reader.
+const struct BcfHeader *bcf_reader_header(const struct BcfReader *reader);
+
+// Reads the next record into `*record`, which is allocated if it is NULL and reused otherwise.
+// Returns `BCF_STATUS_END` after the last record.
+//
+// # Safety
+// `reader` must be a valid reader, and `record` point to NULL or a valid record.
+enum BcfStatus bcf_reader_next(struct BcfReader *reader, struct BcfRecord **record);
+
+// Frees a record; NULL is ignored.
+//
+// # Safety
+// `record` must be NULL or returned by `bcf_reader_next`, and not be used afterwards.
+void bcf_record_free(struct BcfRecord *record);
+
+// Returns the number of samples.
+//
+// # Safety
+// `header` must be a valid header.
+int64_t bcf_header_n_samples(const struct BcfHeader *header);
+
+// Returns the name of the sample with the given index, or NULL if out of range. The name is
+// valid as long as the header.
+//
+// # Safety
+// `header` must be a valid header.
+const char *bcf_header_sample(const struct BcfHeader *header, size_t index);
+
+// Returns the number of contigs.
+//
+// # Safety
+// `header` must be a valid header.
+int64_t bcf_header_n_contigs(const struct BcfHeader *header);
+
+// Returns the ID of the contig with the given index, or NULL if out of range. The ID is valid
+// as long as the header.
+//
+// # Safety
+// `header` must be a valid header.
+const char *bcf_header_contig(const struct BcfHeader *header, size_t index);
+
+// Copies the contig name into `buffer` and returns its length, like `snprintf`.
+//
+// # Safety
+// `record` must be a valid record, and `buffer` have room for `size` bytes.
+int64_t bcf_record_chrom(const struct BcfRecord *record, char *buffer, size_t size);
+
+// Returns the 0-based position.
+//
+// # Safety
+// `record` must be a valid record.
+int64_t bcf_record_pos(const struct BcfRecord *record);
+
+// Copies the ID, which is empty if missing.
+//
+// # Safety
+// `record` must be a valid record, and `buffer` have room for `size` bytes.
+int64_t bcf_record_id(const struct BcfRecord *record, char *buffer, size_t size);
+
+// Returns the number of alleles, including the reference allele.
+//
+// # Safety
+// `record` must be a valid record.
+int64_t bcf_record_n_alleles(const struct BcfRecord *record);
+
+// Copies the allele with the given index, where 0 is the reference allele.
+//
+// # Safety
+// `record` must be a valid record, and `buffer` have room for `size` bytes.
+int64_t bcf_record_allele(const struct BcfRecord *record, size_t index, char *buffer, size_t size);
+
+// Stores the quality in `*qual`, or returns `BCF_STATUS_NOT_FOUND` if it is missing.
+//
+// # Safety
+// `record` must be a valid record, and `qual` point to a float.
+enum BcfStatus bcf_record_qual(const struct BcfRecord *record, float *qual);
+
+// Copies the values of an integer INFO field.
+//
+// # Safety
+// `record` must be a valid record, `tag` a NUL-terminated string and `values` have room for
+// `size` values.
+int64_t bcf_record_info_int(const struct BcfRecord *record,
+                            const char *tag,
+                            int32_t *values,
+                            size_t size);
+
+// Copies the values of a float INFO field.
+//
+// # Safety
+// `record` must be a valid record, `tag` a NUL-terminated string and `values` have room for
+// `size` values.
+int64_t bcf_record_info_float(const struct BcfRecord *record,
+                              const char *tag,
+                              float *values,
+                              size_t size);
+
+// Copies the value of a string INFO field, with multiple values separated by commas.
+//
+// # Safety
+// `record` must be a valid record, `tag` a NUL-terminated string and `buffer` have room for
+// `size` bytes.
+int64_t bcf_record_info_string(const struct BcfRecord *record,
+                               const char *tag,
+                               char *buffer,
+                               size_t size);
+
+// Returns 1 if the INFO flag is set, and 0 otherwise.
+//
+// # Safety
+// `record` must be a valid record and `tag` a NUL-terminated string.
+int64_t bcf_record_info_flag(const struct BcfRecord *record, const char *tag);
+
+// Copies the values of an integer FORMAT field as flat samples × `*per_sample` array, padded
+// with `BCF_INT32_VECTOR_END`. `GT` values are BCF-encoded, see `bcf_record_genotypes`.
+//
+// # Safety
+// `record` must be a valid record, `tag` a NUL-terminated string, `values` have room for
+// `size` values and `per_sample` point to a size_t.
+int64_t bcf_record_format_int(const struct BcfRecord *record,
+                              const char *tag,
+                              int32_t *values,
+                              size_t size,
+                              size_t *per_sample);
+
+// Copies the values of a float FORMAT field as flat samples × `*per_sample` array, padded with
+// the NaN of `BCF_FLOAT_VECTOR_END_BITS`.
+//
+// # Safety
+// `record` must be a valid record, `tag` a NUL-terminated string, `values` have room for
+// `size` values and `per_sample` point to a size_t.
+int64_t bcf_record_format_float(const struct BcfRecord *record,
+                                const char *tag,
+                                float *values,
+                                size_t size,
+                                size_t *per_sample);
+
+// Copies the value of a string FORMAT field of the sample with the given index.
+//
+// # Safety
+// `record` must be a valid record, `tag` a NUL-terminated string and `buffer` have room for
+// `size` bytes.
+int64_t bcf_record_format_string(const struct BcfRecord *record,
+                                 const char *tag,
+                                 size_t sample,
+                                 char *buffer,
+                                 size_t size);
+
+// Copies the allele indices of all calls as flat samples × `*ploidy` array, with
+// `BCF_ALLELE_MISSING` for missing alleles and `BCF_ALLELE_VECTOR_END` as padding.
+//
+// # Safety
+// `record` must be a valid record, `alleles` have room for `size` values and `ploidy` point to
+// a size_t.
+int64_t bcf_record_genotypes(const struct BcfRecord *record,
+                             int32_t *alleles,
+                             size_t size,
+                             size_t *ploidy);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RUST_BCF_H */
@@ -0,0 +1,631 @@
+//! C API of `rust-bcf`; the header `include/rust_bcf.h` is generated by cbindgen, run the build
+//! with `RUST_BCF_FFI_UPDATE_HEADER=1` to update it.
+//!
+//! Readers, headers and records are opaque handles. Functions which copy values into caller
+//! buffers return the number of available values (or the length of a text without the
+//! terminating NUL, like `snprintf`), of which at most `size` are copied, so a call with
+//! `size` 0 queries the required buffer size. Negative return values are `BcfStatus` errors,
+//! which are described by `bcf_last_error`.
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io::Read;
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use bcf::record::BcfRecord as InnerRecord;
+use bcf::types::TypedVec;
+use bcf::{BcfRecords, Record};
+
+/// The value of missing integers.
+pub const BCF_INT32_MISSING: i32 = i32::MIN;
+/// The padding of integer FORMAT values of samples with fewer values than others.
+pub const BCF_INT32_VECTOR_END: i32 = i32::MIN + 1;
+/// The bits of missing floats, a NaN.
+pub const BCF_FLOAT_MISSING_BITS: u32 = 0x7F80_0001;
+/// The bits of the padding of float FORMAT values, a NaN.
+pub const BCF_FLOAT_VECTOR_END_BITS: u32 = 0x7F80_0002;
+/// The value of missing alleles, see `bcf_record_genotypes`.
+pub const BCF_ALLELE_MISSING: i32 = -1;
+/// The padding of alleles of calls with a lower ploidy than others.
+pub const BCF_ALLELE_VECTOR_END: i32 = -2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BcfStatus {
+    Ok = 0,
+    /// There are no more records
+    End = 1,
+    NullPointer = -1,
+    Io = -2,
+    /// Invalid input, such as a malformed file or a non-UTF-8 tag
+    Parse = -3,
+    /// The field is not present in the record
+    NotFound = -4,
+    /// The field has a different type than requested
+    WrongType = -5,
+    /// An index is out of range
+    OutOfRange = -6,
+}
+
+/// A reader of BCF files.
+pub struct BcfReader {
+    records: BcfRecords<Box<dyn Read>>,
+    header: BcfHeader,
+}
+
+/// The header of a BCF file, owned by its reader.
+pub struct BcfHeader {
+    samples: Vec<CString>,
+    contigs: Vec<CString>,
+}
+
+/// A BCF record, which stays valid independently of its reader.
+pub struct BcfRecord(InnerRecord);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+type Error = (BcfStatus, String);
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', "")).unwrap();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, turning panics into `BcfStatus::Parse` errors, and records any error message.
+fn guard<T, F: FnOnce() -> Result<T, Error>>(f: F) -> Result<T, BcfStatus> {
+    let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown error".into());
+        Err((BcfStatus::Parse, message))
+    });
+    result.map_err(|(status, message)| {
+        set_last_error(&message);
+        status
+    })
+}
+
+/// Converts a result into a count, or a negative `BcfStatus`.
+fn count<F: FnOnce() -> Result<usize, Error>>(f: F) -> i64 {
+    guard(f).map_or_else(|status| status as i64, |n| n as i64)
+}
+
+fn status<F: FnOnce() -> Result<(), Error>>(f: F) -> BcfStatus {
+    guard(f).err().unwrap_or(BcfStatus::Ok)
+}
+
+unsafe fn reference<'a, T>(pointer: *const T, name: &str) -> Result<&'a T, Error> {
+    pointer
+        .as_ref()
+        .ok_or_else(|| (BcfStatus::NullPointer, format!("{} is NULL", name)))
+}
+
+unsafe fn text<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, Error> {
+    if pointer.is_null() {
+        return Err((BcfStatus::NullPointer, format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(pointer)
+        .to_str()
+        .map_err(|e| (BcfStatus::Parse, format!("{} is not UTF-8: {}", name, e)))
+}
+
+/// Copies `text` into `buffer` like `snprintf`, and returns its length.
+unsafe fn copy_text(text: &[u8], buffer: *mut c_char, size: usize) -> Result<usize, Error> {
+    if size > 0 {
+        if buffer.is_null() {
+            return Err((BcfStatus::NullPointer, "buffer is NULL".into()));
+        }
+        let n = text.len().min(size - 1);
+        ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, buffer, n);
+        *buffer.add(n) = 0;
+    }
+    Ok(text.len())
+}
+
+/// Copies at most `size` of `values` into `buffer`, and returns the number of values.
+unsafe fn copy_values<T: Copy>(values: &[T], buffer: *mut T, size: usize) -> Result<usize, Error> {
+    let n = values.len().min(size);
+    if n > 0 {
+        if buffer.is_null() {
+            return Err((BcfStatus::NullPointer, "buffer is NULL".into()));
+        }
+        ptr::copy_nonoverlapping(values.as_ptr(), buffer, n);
+    }
+    Ok(values.len())
+}
+
+fn not_found(kind: &str, tag: &str) -> Error {
+    (
+        BcfStatus::NotFound,
+        format!("{} field {} is not present", kind, tag),
+    )
+}
+
+fn wrong_type(kind: &str, tag: &str, requested: &str) -> Error {
+    (
+        BcfStatus::WrongType,
+        format!("{} field {} is not of type {}", kind, tag, requested),
+    )
+}
+
+/// Returns the message of the last error of this thread, or NULL. The message is valid until
+/// the next error.
+#[no_mangle]
+pub extern "C" fn bcf_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Opens a (possibly gzip-compressed) BCF file, or returns NULL on error.
+///
+/// # Safety
+/// `path` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_reader_open(path: *const c_char) -> *mut BcfReader {
+    guard(|| {
+        let path = text(path, "path")?;
+        let records = BcfRecords::from_path(path)
+            .map_err(|e| (BcfStatus::Io, format!("failed to open {}: {}", path, e)))?;
+        let header = records.header();
+        let c_string = |s: &str| CString::new(s.replace('\0', "")).unwrap();
+        let header = BcfHeader {
+            samples: header.samples().iter().map(|s| c_string(s)).collect(),
+            contigs: header.contigs().iter().map(|c| c_string(c.id())).collect(),
+        };
+        Ok(Box::into_raw(Box::new(BcfReader { records, header })))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Closes a reader; NULL is ignored.
+///
+/// # Safety
+/// `reader` must be NULL or returned by `bcf_reader_open`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_reader_free(reader: *mut BcfReader) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
+
+/// Returns the header of a reader, which is valid as long as the reader.
+///
+/// # Safety
+/// `reader` must be a valid reader.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_reader_header(reader: *const BcfReader) -> *const BcfHeader {
+    reader
+        .as_ref()
+        .map_or(ptr::null(), |reader| &reader.header as *const _)
+}
+
+/// Reads the next record into `*record`, which is allocated if it is NULL and reused otherwise.
+/// Returns `BCF_STATUS_END` after the last record.
+///
+/// # Safety
+/// `reader` must be a valid reader, and `record` point to NULL or a valid record.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_reader_next(
+    reader: *mut BcfReader,
+    record: *mut *mut BcfRecord,
+) -> BcfStatus {
+    if record.is_null() {
+        set_last_error("record is NULL");
+        return BcfStatus::NullPointer;
+    }
+    let reader = match reader.as_mut() {
+        Some(reader) => reader,
+        None => {
+            set_last_error("reader is NULL");
+            return BcfStatus::NullPointer;
+        }
+    };
+    let next = match guard(|| Ok(reader.records.next())) {
+        Ok(next) => next,
+        Err(status) => return status,
+    };
+    match next {
+        None => BcfStatus::End,
+        Some(next) => {
+            match (*record).as_mut() {
+                Some(record) => record.0 = next,
+                None => *record = Box::into_raw(Box::new(BcfRecord(next))),
+            }
+            BcfStatus::Ok
+        }
+    }
+}
+
+/// Frees a record; NULL is ignored.
+///
+/// # Safety
+/// `record` must be NULL or returned by `bcf_reader_next`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_free(record: *mut BcfRecord) {
+    if !record.is_null() {
+        drop(Box::from_raw(record));
+    }
+}
+
+/// Returns the number of samples.
+///
+/// # Safety
+/// `header` must be a valid header.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_header_n_samples(header: *const BcfHeader) -> i64 {
+    count(|| Ok(reference(header, "header")?.samples.len()))
+}
+
+/// Returns the name of the sample with the given index, or NULL if out of range. The name is
+/// valid as long as the header.
+///
+/// # Safety
+/// `header` must be a valid header.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_header_sample(
+    header: *const BcfHeader,
+    index: usize,
+) -> *const c_char {
+    header
+        .as_ref()
+        .and_then(|header| header.samples.get(index))
+        .map_or(ptr::null(), |sample| sample.as_ptr())
+}
+
+/// Returns the number of contigs.
+///
+/// # Safety
+/// `header` must be a valid header.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_header_n_contigs(header: *const BcfHeader) -> i64 {
+    count(|| Ok(reference(header, "header")?.contigs.len()))
+}
+
+/// Returns the ID of the contig with the given index, or NULL if out of range. The ID is valid
+/// as long as the header.
+///
+/// # Safety
+/// `header` must be a valid header.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_header_contig(
+    header: *const BcfHeader,
+    index: usize,
+) -> *const c_char {
+    header
+        .as_ref()
+        .and_then(|header| header.contigs.get(index))
+        .map_or(ptr::null(), |contig| contig.as_ptr())
+}
+
+/// Copies the contig name into `buffer` and returns its length, like `snprintf`.
+///
+/// # Safety
+/// `record` must be a valid record, and `buffer` have room for `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_chrom(
+    record: *const BcfRecord,
+    buffer: *mut c_char,
+    size: usize,
+) -> i64 {
+    count(|| {
+        let chrom = reference(record, "record")?
+            .0
+            .try_chrom()
+            .map_err(|e| (BcfStatus::Parse, e.to_string()))?;
+        copy_text(chrom.as_bytes(), buffer, size)
+    })
+}
+
+/// Returns the 0-based position.
+///
+/// # Safety
+/// `record` must be a valid record.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_pos(record: *const BcfRecord) -> i64 {
+    count(|| Ok(reference(record, "record")?.0.pos() as usize))
+}
+
+/// Copies the ID, which is empty if missing.
+///
+/// # Safety
+/// `record` must be a valid record, and `buffer` have room for `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_id(
+    record: *const BcfRecord,
+    buffer: *mut c_char,
+    size: usize,
+) -> i64 {
+    count(|| copy_text(&reference(record, "record")?.0.id(), buffer, size))
+}
+
+/// Returns the number of alleles, including the reference allele.
+///
+/// # Safety
+/// `record` must be a valid record.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_n_alleles(record: *const BcfRecord) -> i64 {
+    count(|| Ok(1 + reference(record, "record")?.0.alt_alleles().len()))
+}
+
+/// Copies the allele with the given index, where 0 is the reference allele.
+///
+/// # Safety
+/// `record` must be a valid record, and `buffer` have room for `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_allele(
+    record: *const BcfRecord,
+    index: usize,
+    buffer: *mut c_char,
+    size: usize,
+) -> i64 {
+    count(|| {
+        let record = &reference(record, "record")?.0;
+        let allele = if index == 0 {
+            record.ref_allele()
+        } else {
+            record
+                .alt_alleles()
+                .into_iter()
+                .nth(index - 1)
+                .ok_or_else(|| {
+                    (
+                        BcfStatus::OutOfRange,
+                        format!("there is no allele {}", index),
+                    )
+                })?
+        };
+        copy_text(&allele, buffer, size)
+    })
+}
+
+/// Stores the quality in `*qual`, or returns `BCF_STATUS_NOT_FOUND` if it is missing.
+///
+/// # Safety
+/// `record` must be a valid record, and `qual` point to a float.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_qual(record: *const BcfRecord, qual: *mut f32) -> BcfStatus {
+    status(|| {
+        let record = &reference(record, "record")?.0;
+        if qual.is_null() {
+            return Err((BcfStatus::NullPointer, "qual is NULL".into()));
+        }
+        *qual = record
+            .qual()
+            .ok_or_else(|| (BcfStatus::NotFound, "QUAL is missing".into()))?;
+        Ok(())
+    })
+}
+
+/// Returns the value of an INFO field and its tag.
+unsafe fn info_value(
+    record: *const BcfRecord,
+    tag: *const c_char,
+) -> Result<(TypedVec, String), Error> {
+    let record = &reference(record, "record")?.0;
+    let tag = text(tag, "tag")?;
+    let value = record
+        .info(tag.as_bytes())
+        .ok_or_else(|| not_found("INFO", tag))?;
+    Ok((value, tag.into()))
+}
+
+/// Copies the values of an integer INFO field.
+///
+/// # Safety
+/// `record` must be a valid record, `tag` a NUL-terminated string and `values` have room for
+/// `size` values.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_info_int(
+    record: *const BcfRecord,
+    tag: *const c_char,
+    values: *mut i32,
+    size: usize,
+) -> i64 {
+    count(|| match info_value(record, tag)? {
+        (TypedVec::Int32(v), _) => copy_values(&v, values, size),
+        (TypedVec::Missing, _) => Ok(0),
+        (_, tag) => Err(wrong_type("INFO", &tag, "Integer")),
+    })
+}
+
+/// Copies the values of a float INFO field.
+///
+/// # Safety
+/// `record` must be a valid record, `tag` a NUL-terminated string and `values` have room for
+/// `size` values.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_info_float(
+    record: *const BcfRecord,
+    tag: *const c_char,
+    values: *mut f32,
+    size: usize,
+) -> i64 {
+    count(|| match info_value(record, tag)? {
+        (TypedVec::Float32(v), _) => copy_values(&v, values, size),
+        (TypedVec::Missing, _) => Ok(0),
+        (_, tag) => Err(wrong_type("INFO", &tag, "Float")),
+    })
+}
+
+/// Copies the value of a string INFO field, with multiple values separated by commas.
+///
+/// # Safety
+/// `record` must be a valid record, `tag` a NUL-terminated string and `buffer` have room for
+/// `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_info_string(
+    record: *const BcfRecord,
+    tag: *const c_char,
+    buffer: *mut c_char,
+    size: usize,
+) -> i64 {
+    count(|| match info_value(record, tag)? {
+        (TypedVec::UString(v), _) => copy_text(&v, buffer, size),
+        (TypedVec::Missing, _) => copy_text(b"", buffer, size),
+        (_, tag) => Err(wrong_type("INFO", &tag, "String")),
+    })
+}
+
+/// Returns 1 if the INFO flag is set, and 0 otherwise.
+///
+/// # Safety
+/// `record` must be a valid record and `tag` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_info_flag(record: *const BcfRecord, tag: *const c_char) -> i64 {
+    count(|| {
+        let record = &reference(record, "record")?.0;
+        Ok(record.has_flag(text(tag, "tag")?.as_bytes()) as usize)
+    })
+}
+
+/// Copies rows of values as flat array padded with `pad`, and stores the maximum row length in
+/// `*width`.
+unsafe fn copy_rows<T: Copy>(
+    rows: &[Vec<T>],
+    pad: T,
+    buffer: *mut T,
+    size: usize,
+    width: *mut usize,
+) -> Result<usize, Error> {
+    if width.is_null() {
+        return Err((BcfStatus::NullPointer, "row length is NULL".into()));
+    }
+    let max = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut flat = Vec::with_capacity(rows.len() * max);
+    for row in rows {
+        flat.extend_from_slice(row);
+        flat.extend(std::iter::repeat_n(pad, max - row.len()));
+    }
+    *width = max;
+    copy_values(&flat, buffer, size)
+}
+
+/// Returns the values of a numeric FORMAT field per sample, converted by `f`.
+unsafe fn format_values<T: Copy, F: Fn(TypedVec) -> Option<Vec<T>>>(
+    record: *const BcfRecord,
+    tag: *const c_char,
+    requested: &str,
+    f: F,
+) -> Result<Vec<Vec<T>>, Error> {
+    let record = &reference(record, "record")?.0;
+    let tag = text(tag, "tag")?;
+    record
+        .format(tag.as_bytes())
+        .ok_or_else(|| not_found("FORMAT", tag))?
+        .into_iter()
+        .map(|value| match value {
+            TypedVec::Missing => Ok(vec![]),
+            value => f(value).ok_or_else(|| wrong_type("FORMAT", tag, requested)),
+        })
+        .collect()
+}
+
+/// Copies the values of an integer FORMAT field as flat samples × `*per_sample` array, padded
+/// with `BCF_INT32_VECTOR_END`. `GT` values are BCF-encoded, see `bcf_record_genotypes`.
+///
+/// # Safety
+/// `record` must be a valid record, `tag` a NUL-terminated string, `values` have room for
+/// `size` values and `per_sample` point to a size_t.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_format_int(
+    record: *const BcfRecord,
+    tag: *const c_char,
+    values: *mut i32,
+    size: usize,
+    per_sample: *mut usize,
+) -> i64 {
+    count(|| {
+        let rows = format_values(record, tag, "Integer", |value| match value {
+            TypedVec::Int32(v) => Some(v),
+            _ => None,
+        })?;
+        copy_rows(&rows, BCF_INT32_VECTOR_END, values, size, per_sample)
+    })
+}
+
+/// Copies the values of a float FORMAT field as flat samples × `*per_sample` array, padded with
+/// the NaN of `BCF_FLOAT_VECTOR_END_BITS`.
+///
+/// # Safety
+/// `record` must be a valid record, `tag` a NUL-terminated string, `values` have room for
+/// `size` values and `per_sample` point to a size_t.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_format_float(
+    record: *const BcfRecord,
+    tag: *const c_char,
+    values: *mut f32,
+    size: usize,
+    per_sample: *mut usize,
+) -> i64 {
+    count(|| {
+        let rows = format_values(record, tag, "Float", |value| match value {
+            TypedVec::Float32(v) => Some(v),
+            _ => None,
+        })?;
+        let pad = f32::from_bits(BCF_FLOAT_VECTOR_END_BITS);
+        copy_rows(&rows, pad, values, size, per_sample)
+    })
+}
+
+/// Copies the value of a string FORMAT field of the sample with the given index.
+///
+/// # Safety
+/// `record` must be a valid record, `tag` a NUL-terminated string and `buffer` have room for
+/// `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_format_string(
+    record: *const BcfRecord,
+    tag: *const c_char,
+    sample: usize,
+    buffer: *mut c_char,
+    size: usize,
+) -> i64 {
+    count(|| {
+        let record = &reference(record, "record")?.0;
+        let tag = text(tag, "tag")?;
+        let values = record
+            .format(tag.as_bytes())
+            .ok_or_else(|| not_found("FORMAT", tag))?;
+        match values.get(sample) {
+            Some(TypedVec::UString(v)) => copy_text(v, buffer, size),
+            Some(TypedVec::Missing) => copy_text(b"", buffer, size),
+            Some(_) => Err(wrong_type("FORMAT", tag, "String")),
+            None => Err((
+                BcfStatus::OutOfRange,
+                format!("there is no sample {}", sample),
+            )),
+        }
+    })
+}
+
+/// Copies the allele indices of all calls as flat samples × `*ploidy` array, with
+/// `BCF_ALLELE_MISSING` for missing alleles and `BCF_ALLELE_VECTOR_END` as padding.
+///
+/// # Safety
+/// `record` must be a valid record, `alleles` have room for `size` values and `ploidy` point to
+/// a size_t.
+#[no_mangle]
+pub unsafe extern "C" fn bcf_record_genotypes(
+    record: *const BcfRecord,
+    alleles: *mut i32,
+    size: usize,
+    ploidy: *mut usize,
+) -> i64 {
+    count(|| {
+        let rows = reference(record, "record")?
+            .0
+            .genotypes()
+            .iter()
+            .map(|call| {
+                call.iter()
+                    .map(|allele| allele.index().map_or(BCF_ALLELE_MISSING, |i| i as i32))
+                    .collect()
+            })
+            .collect::<Vec<_>>();
+        copy_rows(&rows, BCF_ALLELE_VECTOR_END, alleles, size, ploidy)
+    })
+}
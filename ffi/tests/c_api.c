@@ -0,0 +1,73 @@
+/* Exercises the C API on an example file, see c_api.rs. */
+#include <assert.h>
+#include <math.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "rust_bcf.h"
+
+int main(int argc, char **argv) {
+    assert(argc == 2);
+    assert(bcf_reader_open("does/not/exist.bcf") == NULL);
+    assert(strstr(bcf_last_error(), "does/not/exist.bcf") != NULL);
+
+    BcfReader *reader = bcf_reader_open(argv[1]);
+    assert(reader != NULL);
+    const BcfHeader *header = bcf_reader_header(reader);
+    assert(bcf_header_n_samples(header) == 3);
+    assert(strcmp(bcf_header_sample(header, 0), "HG001") == 0);
+    assert(bcf_header_sample(header, 3) == NULL);
+    assert(bcf_header_n_contigs(header) > 0);
+    assert(strcmp(bcf_header_contig(header, 0), "chr1") == 0);
+
+    BcfRecord *record = NULL;
+    assert(bcf_reader_next(reader, &record) == BCF_STATUS_OK);
+
+    char text[16];
+    assert(bcf_record_chrom(record, text, sizeof(text)) == 4);
+    assert(strcmp(text, "chr1") == 0);
+    assert(bcf_record_chrom(record, text, 3) == 4);
+    assert(strcmp(text, "ch") == 0);
+    assert(bcf_record_pos(record) == 817185);
+    assert(bcf_record_id(record, text, sizeof(text)) == 0);
+    assert(bcf_record_n_alleles(record) == 2);
+    assert(bcf_record_allele(record, 0, text, sizeof(text)) == 1 && strcmp(text, "G") == 0);
+    assert(bcf_record_allele(record, 1, text, sizeof(text)) == 1 && strcmp(text, "A") == 0);
+    assert(bcf_record_allele(record, 2, text, sizeof(text)) == BCF_STATUS_OUT_OF_RANGE);
+    float qual;
+    assert(bcf_record_qual(record, &qual) == BCF_STATUS_OK && qual == 50.0f);
+
+    int32_t values[16];
+    assert(bcf_record_info_int(record, "platforms", values, 16) == 1 && values[0] > 0);
+    assert(bcf_record_info_float(record, "platforms", NULL, 0) == BCF_STATUS_WRONG_TYPE);
+    assert(strstr(bcf_last_error(), "platforms") != NULL);
+    assert(bcf_record_info_int(record, "DPSum", values, 16) == BCF_STATUS_NOT_FOUND);
+    int64_t length = bcf_record_info_string(record, "platformnames", NULL, 0);
+    assert(length > 0);
+    char names[256];
+    assert(bcf_record_info_string(record, "platformnames", names, sizeof(names)) == length);
+    assert((int64_t)strlen(names) == length);
+    assert(bcf_record_info_flag(record, "difficultregion") == 0
+           || bcf_record_info_flag(record, "difficultregion") == 1);
+
+    size_t per_sample;
+    assert(bcf_record_format_int(record, "DP", values, 16, &per_sample) == 3);
+    assert(per_sample == 1);
+    assert(bcf_record_format_int(record, "AD", NULL, 0, &per_sample) == 3 * (int64_t)per_sample);
+    size_t ploidy;
+    assert(bcf_record_genotypes(record, values, 16, &ploidy) == 6);
+    assert(ploidy == 2 && values[0] == 1 && values[1] == 1);
+
+    int64_t n_records = 1;
+    BcfStatus status;
+    while ((status = bcf_reader_next(reader, &record)) == BCF_STATUS_OK) {
+        n_records++;
+    }
+    assert(status == BCF_STATUS_END);
+    assert(n_records == 905);
+
+    bcf_record_free(record);
+    bcf_reader_free(reader);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,39 @@
+//! Compiles `c_api.c` against the shared library and the generated header and runs it on an
+//! example file.
+use std::path::PathBuf;
+use std::process::Command;
+
+#[test]
+fn test_c_api() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // the test executable is in target/<profile>/deps, as is the library built for it
+    let library_dir = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+    let executable = library_dir.join("c_api_test");
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+    let status = Command::new(compiler)
+        .arg(manifest_dir.join("tests/c_api.c"))
+        .arg("-I")
+        .arg(PathBuf::from(env!("OUT_DIR")).join("include"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .args(["-lrust_bcf_ffi", "-o"])
+        .arg(&executable)
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling c_api.c failed");
+    let output = Command::new(&executable)
+        .arg(manifest_dir.join("../resources/example.uncompressed.bcf"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "c_api.c failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(output.stdout, b"ok\n");
+}
@@ -0,0 +1,415 @@
+//! Python bindings of `rust-bcf`, built with maturin:
+//!
+//! ```python
+//! import rust_bcf
+//!
+//! records = rust_bcf.BcfRecords("example.bcf")
+//! print(records.header.samples)
+//! for record in records.fetch("chr1", 10000, 20000):
+//!     print(record.chrom, record.pos, record.alleles, record.info("DP"), record.format("GT"))
+//! ```
+use std::io::Read;
+use std::path::PathBuf;
+
+use bcf::record::GenotypeAllele;
+use bcf::region::Interval;
+use bcf::types::{InfoType, TypedVec, MISSING_INTEGER};
+use bcf::{BcfRecord, BcfRecords, Record};
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// The value of missing alleles in genotype arrays.
+const MISSING_ALLELE: i32 = -1;
+/// The value of non-existent alleles in genotype arrays, i.e. beyond the ploidy of a call.
+const FILL_ALLELE: i32 = -2;
+
+/// Iterates over the records of a (possibly gzip-compressed) BCF file.
+#[pyclass(name = "BcfRecords", module = "rust_bcf", unsendable)]
+struct PyBcfRecords {
+    records: BcfRecords<Box<dyn Read>>,
+    /// Only records overlapping this region (contig index and interval), see `fetch`
+    region: Option<(usize, Interval)>,
+    /// Whether the region has been seen, i.e. whether the first record beyond it ends iteration
+    in_region: bool,
+    done: bool,
+}
+
+#[pymethods]
+impl PyBcfRecords {
+    #[new]
+    fn new(path: PathBuf) -> anyhow::Result<Self> {
+        Ok(PyBcfRecords {
+            records: BcfRecords::from_path(path)?,
+            region: None,
+            in_region: false,
+            done: false,
+        })
+    }
+
+    #[getter]
+    fn header(&self) -> PyHeader {
+        PyHeader(self.records.header().clone())
+    }
+
+    /// Restricts iteration to the records overlapping the region `start..end` (0-based,
+    /// half-open) on `contig`. As BCF files are not indexed, this scans the file from the
+    /// current record, and stops at the first record beyond the region, which requires the
+    /// records to be sorted. The contig may also be given by an alias of its name, e.g. `1`
+    /// for `chr1`.
+    #[pyo3(signature = (contig, start = None, end = None))]
+    fn fetch(
+        mut slf: PyRefMut<'_, Self>,
+        contig: String,
+        start: Option<u32>,
+        end: Option<u32>,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        let (start, end) = (start.unwrap_or(0), end.unwrap_or(u32::MAX));
+        if end < start {
+            return Err(PyValueError::new_err(format!(
+                "region end {} is before start {}",
+                end, start
+            )));
+        }
+        let contig = slf.records.header().resolve_contig(&contig);
+        slf.region = contig.map(|contig| (contig, Interval::from_zero_based(start, end)));
+        slf.in_region = false;
+        // no record is on an undeclared contig
+        slf.done = contig.is_none();
+        Ok(slf)
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self) -> Option<PyRecord> {
+        while !self.done {
+            let record = match self.records.next() {
+                Some(record) => record,
+                None => break,
+            };
+            let (contig, interval) = match &self.region {
+                None => return Some(PyRecord(record)),
+                Some(region) => region,
+            };
+            if record.contig_index() != *contig || record.position() >= interval.end() {
+                self.done = self.in_region;
+                continue;
+            }
+            self.in_region = true;
+            if record.interval().overlaps(interval) {
+                return Some(PyRecord(record));
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+/// The metadata of a BCF file.
+#[pyclass(name = "Header", module = "rust_bcf")]
+struct PyHeader(bcf::types::Header);
+
+#[pymethods]
+impl PyHeader {
+    #[getter]
+    fn samples(&self) -> Vec<String> {
+        self.0.samples().clone()
+    }
+
+    /// The contigs as `(ID, length)` tuples, where the length may be `None`.
+    #[getter]
+    fn contigs(&self) -> Vec<(String, Option<usize>)> {
+        self.0
+            .contigs()
+            .iter()
+            .map(|contig| (contig.id().clone(), *contig.length()))
+            .collect()
+    }
+
+    /// The FILTER descriptions by ID.
+    #[getter]
+    fn filters<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let filters = PyDict::new(py);
+        for filter in self.0.filter().values() {
+            filters.set_item(filter.id(), filter.description())?;
+        }
+        Ok(filters)
+    }
+
+    /// The INFO definitions by ID, as dicts with the keys `number`, `type` and `description`.
+    #[getter]
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let info = PyDict::new(py);
+        for field in self.0.info().values() {
+            let definition = definition(
+                py,
+                field.number().to_string(),
+                field.kind(),
+                field.description(),
+            )?;
+            info.set_item(field.id(), definition)?;
+        }
+        Ok(info)
+    }
+
+    /// The FORMAT definitions by ID, like `info`.
+    #[getter]
+    fn format<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let format = PyDict::new(py);
+        for field in self.0.format().values() {
+            let definition = definition(
+                py,
+                field.number().to_string(),
+                field.kind(),
+                field.description(),
+            )?;
+            format.set_item(field.id(), definition)?;
+        }
+        Ok(format)
+    }
+
+    /// The header as VCF text.
+    fn __str__(&self) -> String {
+        self.0.to_vcf_text()
+    }
+}
+
+fn definition<'py>(
+    py: Python<'py>,
+    number: String,
+    kind: &InfoType,
+    description: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let definition = PyDict::new(py);
+    definition.set_item("number", number)?;
+    definition.set_item("type", kind.to_string())?;
+    definition.set_item("description", description)?;
+    Ok(definition)
+}
+
+/// A BCF record. Positions are 0-based as in BCF, not 1-based as in VCF.
+#[pyclass(name = "Record", module = "rust_bcf")]
+struct PyRecord(BcfRecord);
+
+#[pymethods]
+impl PyRecord {
+    #[getter]
+    fn chrom(&self) -> PyResult<String> {
+        let chrom = self.0.try_chrom();
+        chrom
+            .map(String::from)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[getter]
+    fn pos(&self) -> u32 {
+        self.0.pos()
+    }
+
+    #[getter]
+    fn id(&self) -> Option<String> {
+        text(&self.0.id())
+    }
+
+    #[getter(r#ref)]
+    fn ref_allele(&self) -> String {
+        String::from_utf8_lossy(&self.0.ref_allele()).into()
+    }
+
+    #[getter]
+    fn alts(&self) -> Vec<String> {
+        self.0
+            .alt_alleles()
+            .iter()
+            .map(|alt| String::from_utf8_lossy(alt).into())
+            .collect()
+    }
+
+    #[getter]
+    fn alleles(&self) -> Vec<String> {
+        let mut alleles = vec![self.ref_allele()];
+        alleles.extend(self.alts());
+        alleles
+    }
+
+    #[getter]
+    fn qual(&self) -> Option<f32> {
+        self.0.qual()
+    }
+
+    #[getter]
+    fn filters(&self) -> PyResult<Vec<String>> {
+        let filters = self.0.try_filters();
+        filters
+            .map(|filters| filters.into_iter().map(Into::into).collect())
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[getter]
+    fn info_tags(&self) -> Vec<String> {
+        self.0.info_tags().into_iter().map(Into::into).collect()
+    }
+
+    #[getter]
+    fn format_tags(&self) -> Vec<String> {
+        self.0.format_tags().into_iter().map(Into::into).collect()
+    }
+
+    /// Returns the value of an INFO field: a numpy array for numbers (with missing integers as
+    /// `MISSING_INTEGER` and missing floats as NaN), a str for text, a bool for flags, or
+    /// `None` if the field is not present.
+    fn info<'py>(&self, py: Python<'py>, tag: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let header = self.0.header();
+        let is_flag = header
+            .info_tag_to_offset()
+            .get(tag)
+            .is_some_and(|offset| *header.info()[offset].kind() == InfoType::Flag);
+        if is_flag {
+            let present = self.0.has_flag(tag.as_bytes());
+            return Ok(Some(present.into_pyobject(py)?.to_owned().into_any()));
+        }
+        self.0
+            .info(tag.as_bytes())
+            .map(|value| typed_value(py, value))
+            .transpose()
+    }
+
+    /// Returns the values of a FORMAT field as a samples × values numpy array, padded with
+    /// `END_OF_VECTOR_INTEGER` resp. NaN, or a list of str for text, or `None` if the field
+    /// is not present. `GT` is returned as samples × ploidy array of allele indices, with -1
+    /// for missing alleles and -2 for padding.
+    fn format<'py>(&self, py: Python<'py>, tag: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+        if tag == "GT" {
+            return self.genotypes(py).map(Some);
+        }
+        let values = match self.0.format(tag.as_bytes()) {
+            Some(values) => values,
+            None => return Ok(None),
+        };
+        let width = values.iter().map(value_len).max().unwrap_or(0);
+        let array = match values.iter().find(|v| !matches!(v, TypedVec::Missing)) {
+            Some(TypedVec::Int32(_)) => {
+                padded(&values, width, END_OF_VECTOR_INTEGER, |v| match v {
+                    TypedVec::Int32(v) => v.clone(),
+                    _ => vec![],
+                })
+                .into_pyarray(py)
+                .into_any()
+            }
+            Some(TypedVec::Float32(_)) => padded(&values, width, f32::NAN, |v| match v {
+                TypedVec::Float32(v) => v.clone(),
+                _ => vec![],
+            })
+            .into_pyarray(py)
+            .into_any(),
+            _ => values
+                .iter()
+                .map(|v| match v {
+                    TypedVec::UString(v) => text(v),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .into_pyobject(py)?
+                .into_any(),
+        };
+        Ok(Some(array))
+    }
+
+    /// Returns the allele indices of all samples, see `format`.
+    fn genotypes<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let genotypes = self.0.genotypes();
+        let ploidy = genotypes.iter().map(Vec::len).max().unwrap_or(0);
+        let mut array = Array2::from_elem((genotypes.len(), ploidy), FILL_ALLELE);
+        for (i, call) in genotypes.iter().enumerate() {
+            for (j, allele) in call.iter().enumerate() {
+                array[[i, j]] = allele.index().map_or(MISSING_ALLELE, |index| index as i32);
+            }
+        }
+        Ok(array.into_pyarray(py).into_any())
+    }
+
+    /// Returns whether each sample's call is phased, i.e. all alleles but the first are phased.
+    fn phased(&self) -> Vec<bool> {
+        self.0
+            .genotypes()
+            .iter()
+            .map(|call| {
+                call.len() > 1
+                    && call[1..].iter().all(|allele| {
+                        matches!(
+                            allele,
+                            GenotypeAllele::Phased(_) | GenotypeAllele::PhasedMissing
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Record({}:{} {}>{})",
+            self.0.try_chrom().unwrap_or("?"),
+            self.pos() + 1,
+            self.ref_allele(),
+            self.alts().join(",")
+        )
+    }
+}
+
+/// The padding of integer FORMAT values, i.e. the BCF end-of-vector value.
+const END_OF_VECTOR_INTEGER: i32 = MISSING_INTEGER + 1;
+
+fn text(text: &[u8]) -> Option<String> {
+    if text.is_empty() || text == b"." {
+        None
+    } else {
+        Some(String::from_utf8_lossy(text).into())
+    }
+}
+
+fn value_len(value: &TypedVec) -> usize {
+    match value {
+        TypedVec::Int32(v) => v.len(),
+        TypedVec::Float32(v) => v.len(),
+        _ => 0,
+    }
+}
+
+/// Builds a samples × `width` array from the per-sample values, padded with `fill`.
+fn padded<T: Copy, F: Fn(&TypedVec) -> Vec<T>>(
+    values: &[TypedVec],
+    width: usize,
+    fill: T,
+    f: F,
+) -> Array2<T> {
+    let mut array = Array2::from_elem((values.len(), width), fill);
+    for (i, value) in values.iter().enumerate() {
+        for (j, v) in f(value).into_iter().enumerate() {
+            array[[i, j]] = v;
+        }
+    }
+    array
+}
+
+fn typed_value(py: Python<'_>, value: TypedVec) -> PyResult<Bound<'_, PyAny>> {
+    Ok(match value {
+        TypedVec::Missing => py.None().into_bound(py),
+        TypedVec::Int32(v) => PyArray1::from_vec(py, v).into_any(),
+        TypedVec::Float32(v) => PyArray1::from_vec(py, v).into_any(),
+        TypedVec::UString(v) => String::from_utf8_lossy(&v).into_pyobject(py)?.into_any(),
+    })
+}
+
+#[pymodule]
+fn rust_bcf(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyBcfRecords>()?;
+    m.add_class::<PyHeader>()?;
+    m.add_class::<PyRecord>()?;
+    m.add("MISSING_INTEGER", MISSING_INTEGER)?;
+    m.add("END_OF_VECTOR_INTEGER", END_OF_VECTOR_INTEGER)?;
+    Ok(())
+}
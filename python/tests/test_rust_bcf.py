@@ -0,0 +1,47 @@
+import os
+
+import numpy as np
+import pytest
+import rust_bcf
+
+EXAMPLE = os.path.join(
+    os.path.dirname(__file__), "..", "..", "resources", "example.uncompressed.bcf"
+)
+
+
+def test_header():
+    header = rust_bcf.BcfRecords(EXAMPLE).header
+    assert header.samples == ["HG001", "INTEGRATION", "HG003"]
+    assert header.contigs[0] == ("chr1", 248956422)
+    assert header.format["GT"]["type"] == "String"
+    assert str(header).startswith("##fileformat=VCF")
+
+
+def test_records():
+    records = list(rust_bcf.BcfRecords(EXAMPLE))
+    assert len(records) == 905
+    record = records[0]
+    assert (record.chrom, record.pos, record.alleles) == ("chr1", 817185, ["G", "A"])
+    assert record.filters == ["PASS"]
+    assert record.info("platforms").dtype == np.int32
+    assert record.info("undefined") is None
+    gt = record.format("GT")
+    assert gt.shape == (3, 2)
+    assert gt[0].tolist() == [1, 1]
+    assert record.format("DP").shape[0] == 3
+
+
+def test_fetch():
+    records = rust_bcf.BcfRecords(EXAMPLE).fetch("chr1", 817185, 817340)
+    assert [(r.chrom, r.pos) for r in records] == [("chr1", 817185)]
+
+
+def test_fetch_alias():
+    records = rust_bcf.BcfRecords(EXAMPLE).fetch("1", 817185, 817340)
+    assert [(r.chrom, r.pos) for r in records] == [("chr1", 817185)]
+    assert list(rust_bcf.BcfRecords(EXAMPLE).fetch("chrUn")) == []
+
+
+def test_fetch_invalid_region():
+    with pytest.raises(ValueError):
+        rust_bcf.BcfRecords(EXAMPLE).fetch("chr1", 10, 5)
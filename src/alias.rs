@@ -0,0 +1,191 @@
+//! Aliases of contig names, such as `chr1` (UCSC), `1` (Ensembl) and `NC_000001.11` (RefSeq).
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// The RefSeq accessions of the GRCh38 chromosomes, by Ensembl name.
+const GRCH38_REFSEQ: [(&str, &str); 25] = [
+    ("1", "NC_000001.11"),
+    ("2", "NC_000002.12"),
+    ("3", "NC_000003.12"),
+    ("4", "NC_000004.12"),
+    ("5", "NC_000005.10"),
+    ("6", "NC_000006.12"),
+    ("7", "NC_000007.14"),
+    ("8", "NC_000008.11"),
+    ("9", "NC_000009.12"),
+    ("10", "NC_000010.11"),
+    ("11", "NC_000011.10"),
+    ("12", "NC_000012.12"),
+    ("13", "NC_000013.11"),
+    ("14", "NC_000014.9"),
+    ("15", "NC_000015.10"),
+    ("16", "NC_000016.10"),
+    ("17", "NC_000017.11"),
+    ("18", "NC_000018.10"),
+    ("19", "NC_000019.10"),
+    ("20", "NC_000020.11"),
+    ("21", "NC_000021.9"),
+    ("22", "NC_000022.11"),
+    ("X", "NC_000023.11"),
+    ("Y", "NC_000024.10"),
+    ("MT", "NC_012920.1"),
+];
+
+/// Groups of names which refer to the same contig.
+///
+/// The default aliases treat names with and without `chr` prefix as aliases (e.g. `chr1` and
+/// `1`), `chrM` and `MT` as aliases, and the RefSeq accessions of the GRCh38 chromosomes as
+/// aliases of their UCSC and Ensembl names. Further aliases can be added, e.g. from a UCSC
+/// `chromAlias.txt` file.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::alias::ContigAliases;
+///
+/// let mut aliases = ContigAliases::default();
+/// assert!(aliases.is_alias("chr1", "1"));
+/// assert!(aliases.is_alias("chrM", "MT"));
+/// assert!(aliases.is_alias("NC_000023.11", "chrX"));
+/// assert!(!aliases.is_alias("chr1", "chr10"));
+///
+/// aliases.add(["chr1_KI270706v1_random", "KI270706.1"]);
+/// assert!(aliases.is_alias("KI270706.1", "chr1_KI270706v1_random"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContigAliases {
+    /// The group of each name with explicitly added aliases
+    groups: HashMap<String, usize>,
+    n_groups: usize,
+    /// Whether names with and without `chr` prefix are aliases
+    chr_prefix: bool,
+}
+
+impl Default for ContigAliases {
+    fn default() -> Self {
+        let mut aliases = ContigAliases {
+            chr_prefix: true,
+            ..Self::empty()
+        };
+        aliases.add(["chrM", "MT"]);
+        for (name, refseq) in GRCH38_REFSEQ.iter() {
+            aliases.add([*name, *refseq]);
+        }
+        aliases
+    }
+}
+
+impl ContigAliases {
+    /// Creates aliases where each name is only an alias of itself, i.e. without the default
+    /// aliases.
+    pub fn empty() -> Self {
+        ContigAliases {
+            groups: HashMap::new(),
+            n_groups: 0,
+            chr_prefix: false,
+        }
+    }
+
+    /// Declares all given names as aliases of each other (and of their existing aliases).
+    pub fn add<I, S>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names = names
+            .into_iter()
+            .map(|name| name.as_ref().to_string())
+            .collect::<Vec<_>>();
+        let merged = names
+            .iter()
+            .filter_map(|name| self.groups.get(name).copied())
+            .collect::<Vec<_>>();
+        let group = merged.first().copied().unwrap_or_else(|| {
+            self.n_groups += 1;
+            self.n_groups - 1
+        });
+        for g in self.groups.values_mut() {
+            if merged.contains(g) {
+                *g = group;
+            }
+        }
+        for name in names {
+            self.groups.insert(name, group);
+        }
+    }
+
+    /// Adds aliases from a text file with the names of one contig per line, separated by tabs,
+    /// such as the `chromAlias.txt` files of UCSC. Lines starting with `#` are ignored.
+    pub fn add_from_path<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        self.add_from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Adds aliases from text, see `add_from_path`.
+    pub fn add_from_reader<R: BufRead>(&mut self, reader: R) -> anyhow::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.starts_with('#') {
+                continue;
+            }
+            self.add(
+                line.split('\t')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty()),
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if both names refer to the same contig.
+    pub fn is_alias(&self, name: &str, other: &str) -> bool {
+        if name == other {
+            return true;
+        }
+        let (names, others) = (self.spellings(name), self.spellings(other));
+        if names.iter().any(|name| others.contains(name)) {
+            return true;
+        }
+        let groups = |names: &[String]| {
+            names
+                .iter()
+                .filter_map(|name| self.groups.get(name).copied())
+                .collect::<Vec<_>>()
+        };
+        let other_groups = groups(&others);
+        groups(&names)
+            .iter()
+            .any(|group| other_groups.contains(group))
+    }
+
+    /// Returns the first of the given names which is equal to the given name or else the first
+    /// which is an alias of it.
+    pub fn find<'a, I>(&self, name: &str, names: I) -> Option<&'a str>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let names = names.into_iter().collect::<Vec<_>>();
+        names
+            .iter()
+            .find(|candidate| **candidate == name)
+            .or_else(|| {
+                names
+                    .iter()
+                    .find(|candidate| self.is_alias(name, candidate))
+            })
+            .copied()
+    }
+
+    /// Returns the name itself and, with `chr_prefix`, the name with (or without) `chr` prefix.
+    fn spellings(&self, name: &str) -> Vec<String> {
+        let mut spellings = vec![name.to_string()];
+        if self.chr_prefix {
+            spellings.push(match name.strip_prefix("chr") {
+                Some(stripped) => stripped.into(),
+                None => format!("chr{}", name),
+            });
+        }
+        spellings
+    }
+}
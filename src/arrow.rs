@@ -0,0 +1,463 @@
+//! Conversion of records into Apache Arrow `RecordBatch`es (behind the `arrow` feature).
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+use anyhow::bail;
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::types::{Float32Type, Int32Type};
+use arrow_array::{
+    ArrayRef, BooleanArray, Float32Array, Int32Array, ListArray, RecordBatch, StringArray,
+    StructArray, UInt32Array,
+};
+use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef};
+use arrow_select::take::take;
+
+use crate::record::Record;
+use crate::types::{
+    Header, HeaderFormat, HeaderInfo, InfoNumber, InfoType, TypedVec, MISSING_FLOAT,
+    MISSING_INTEGER,
+};
+use crate::writer::vcf_genotype;
+
+/// How samples are represented in a table.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TableLayout {
+    /// One row per record, with one struct column per sample
+    Wide,
+    /// One row per record and sample, with a `SAMPLE` column and one column per FORMAT field
+    Long,
+}
+
+/// Which fields to convert, and how many records to put into each batch.
+#[derive(Debug, Clone)]
+pub struct ArrowOptions {
+    /// The maximum number of records per `RecordBatch`
+    pub batch_size: usize,
+    /// The INFO fields to convert (in this order), or `None` for all fields of the header
+    pub info: Option<Vec<String>>,
+    /// The FORMAT fields to convert (in this order), or `None` for all fields of the header;
+    /// without any FORMAT fields, no sample columns are created.
+    pub format: Option<Vec<String>>,
+    pub layout: TableLayout,
+}
+
+impl Default for ArrowOptions {
+    fn default() -> Self {
+        ArrowOptions {
+            batch_size: 8192,
+            info: None,
+            format: None,
+            layout: TableLayout::Wide,
+        }
+    }
+}
+
+/// Converts records into Arrow `RecordBatch`es with a schema derived from a `Header`.
+///
+/// The schema consists of
+/// - the fixed columns `CHROM`, `POS` (1-based, as in VCF), `ID`, `REF`, `ALT`, `QUAL` and
+///   `FILTER`, where `ALT` and `FILTER` are lists and missing values are null,
+/// - one column `INFO_<ID>` per INFO field, typed according to its definition, which is a list
+///   unless `Number=1` (flags are non-null booleans),
+/// - one struct column per sample, named after the sample, with one field per FORMAT field,
+///   typed like INFO columns; `GT` is a string such as `0|1`.
+///
+/// In `TableLayout::Long`, the sample columns are replaced by the columns `SAMPLE` and one
+/// column per FORMAT field, and the columns of each record are repeated for each sample.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::arrow::{ArrowConverter, ArrowOptions};
+/// use rust_bcf::BcfRecords;
+///
+/// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+/// let options = ArrowOptions {
+///     batch_size: 2,
+///     info: Some(vec!["DPSum".into()]),
+///     format: Some(vec!["GT".into(), "DP".into()]),
+///     ..Default::default()
+/// };
+/// let converter = ArrowConverter::new(records.header(), options).unwrap();
+/// let batch = converter.batches(records).next().unwrap().unwrap();
+/// assert_eq!(batch.num_rows(), 2);
+/// assert_eq!(batch.schema().field(7).name(), "INFO_DPSum");
+/// assert_eq!(batch.schema().field(8).name(), "HG001");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ArrowConverter {
+    schema: SchemaRef,
+    batch_size: usize,
+    info: Vec<HeaderInfo>,
+    format: Vec<HeaderFormat>,
+    samples: Vec<String>,
+    layout: TableLayout,
+}
+
+impl ArrowConverter {
+    /// Derives the schema from the header; fails if a selected field is not defined in the header.
+    pub fn new(header: &Header, options: ArrowOptions) -> anyhow::Result<Self> {
+        if options.batch_size == 0 {
+            bail!("batch size must be positive");
+        }
+        let info = match options.info {
+            None => header.info.values().cloned().collect(),
+            Some(ids) => ids
+                .iter()
+                .map(|id| {
+                    header
+                        .info_tag_to_offset
+                        .get(id)
+                        .map(|offset| header.info[offset].clone())
+                        .ok_or_else(|| anyhow::anyhow!("INFO {} is not defined in the header", id))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        };
+        let format = match options.format {
+            None => header.format.values().cloned().collect(),
+            Some(ids) => ids
+                .iter()
+                .map(|id| {
+                    header
+                        .format_tag_to_offset
+                        .get(id)
+                        .map(|offset| header.format[offset].clone())
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("FORMAT {} is not defined in the header", id)
+                        })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        };
+
+        let mut fields = vec![
+            Field::new("CHROM", DataType::Utf8, false),
+            Field::new("POS", DataType::UInt32, false),
+            Field::new("ID", DataType::Utf8, true),
+            Field::new("REF", DataType::Utf8, false),
+            Field::new("ALT", list_type(DataType::Utf8), false),
+            Field::new("QUAL", DataType::Float32, true),
+            Field::new("FILTER", list_type(DataType::Utf8), false),
+        ];
+        for info in &info {
+            let data_type = column_type(info.kind(), info.number());
+            let nullable = *info.kind() != InfoType::Flag;
+            fields.push(Field::new(format!("INFO_{}", info.id), data_type, nullable));
+        }
+        match options.layout {
+            TableLayout::Wide if !format.is_empty() => {
+                let sample_fields = format_fields(&format);
+                for sample in &header.samples {
+                    fields.push(Field::new(
+                        sample,
+                        DataType::Struct(sample_fields.clone()),
+                        false,
+                    ));
+                }
+            }
+            TableLayout::Wide => {}
+            TableLayout::Long => {
+                fields.push(Field::new("SAMPLE", DataType::Utf8, false));
+                fields.extend(format_fields(&format).iter().map(|f| f.as_ref().clone()));
+            }
+        }
+        Ok(ArrowConverter {
+            schema: Arc::new(Schema::new(fields)),
+            batch_size: options.batch_size,
+            info,
+            format,
+            samples: header.samples.clone(),
+            layout: options.layout,
+        })
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Converts the given records into a single batch, regardless of the batch size.
+    pub fn batch<R: Record>(&self, records: &[R]) -> anyhow::Result<RecordBatch> {
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(
+                records
+                    .iter()
+                    .map(|r| r.try_chrom())
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            )),
+            Arc::new(UInt32Array::from_iter_values(
+                records.iter().map(|r| r.pos() + 1),
+            )),
+            Arc::new(StringArray::from_iter(
+                records.iter().map(|r| text(&r.id())),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                records
+                    .iter()
+                    .map(|r| String::from_utf8_lossy(&r.ref_allele()).into_owned()),
+            )),
+            string_list(records.iter().map(|r| {
+                Some(
+                    r.alt_alleles()
+                        .iter()
+                        .map(|allele| Some(String::from_utf8_lossy(allele).into_owned()))
+                        .collect(),
+                )
+            })),
+            Arc::new(Float32Array::from_iter(records.iter().map(|r| r.qual()))),
+            string_list(
+                records
+                    .iter()
+                    .map(|r| {
+                        let filters = r.try_filters()?;
+                        Ok(Some(
+                            filters.into_iter().map(|f| Some(f.to_string())).collect(),
+                        ))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?
+                    .into_iter(),
+            ),
+        ];
+        for info in &self.info {
+            let values = records
+                .iter()
+                .map(|r| r.info(info.id.as_bytes()))
+                .collect::<Vec<_>>();
+            columns.push(column(info.kind(), info.number(), &values));
+        }
+        let values = self
+            .format
+            .iter()
+            .map(|format| {
+                records
+                    .iter()
+                    .map(|r| r.format(format.id.as_bytes()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let sample_values = |values: &[Option<Vec<TypedVec>>], sample: usize| {
+            values
+                .iter()
+                .map(|v| v.as_ref().and_then(|v| v.get(sample).cloned()))
+                .collect::<Vec<_>>()
+        };
+        match self.layout {
+            TableLayout::Wide if !self.format.is_empty() => {
+                let fields = format_fields(&self.format);
+                for sample in 0..self.samples.len() {
+                    let arrays = self
+                        .format
+                        .iter()
+                        .zip(&values)
+                        .map(|(format, values)| {
+                            format_column(format, &sample_values(values, sample))
+                        })
+                        .collect::<Vec<_>>();
+                    columns.push(Arc::new(StructArray::new(fields.clone(), arrays, None)));
+                }
+            }
+            TableLayout::Wide => {}
+            TableLayout::Long => {
+                // rows are ordered by record, then by sample
+                let n_samples = self.samples.len();
+                let indices = UInt32Array::from_iter_values(
+                    (0..records.len() as u32).flat_map(|i| std::iter::repeat_n(i, n_samples)),
+                );
+                for column in columns.iter_mut() {
+                    *column = take(column.as_ref(), &indices, None)?;
+                }
+                let samples = (0..records.len())
+                    .flat_map(|_| self.samples.iter().map(String::as_str))
+                    .collect::<Vec<_>>();
+                columns.push(Arc::new(StringArray::from(samples)));
+                for (format, values) in self.format.iter().zip(&values) {
+                    let values = values
+                        .iter()
+                        .flat_map(|v| {
+                            (0..n_samples)
+                                .map(move |sample| v.as_ref().and_then(|v| v.get(sample).cloned()))
+                        })
+                        .collect::<Vec<_>>();
+                    columns.push(format_column(format, &values));
+                }
+            }
+        }
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+
+    /// Returns an iterator over batches of at most `batch_size` records each.
+    pub fn batches<R: Record, I: IntoIterator<Item = R>>(
+        &self,
+        records: I,
+    ) -> RecordBatches<'_, I::IntoIter> {
+        RecordBatches {
+            converter: self,
+            records: records.into_iter(),
+        }
+    }
+}
+
+/// An iterator over `RecordBatch`es, see `ArrowConverter::batches`.
+pub struct RecordBatches<'a, I> {
+    converter: &'a ArrowConverter,
+    records: I,
+}
+
+impl<'a, R: Record, I: Iterator<Item = R>> Iterator for RecordBatches<'a, I> {
+    type Item = anyhow::Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let records = self
+            .records
+            .by_ref()
+            .take(self.converter.batch_size)
+            .collect::<Vec<_>>();
+        if records.is_empty() {
+            None
+        } else {
+            Some(self.converter.batch(&records))
+        }
+    }
+}
+
+fn list_type(item: DataType) -> DataType {
+    DataType::List(Arc::new(Field::new("item", item, true)))
+}
+
+/// The type of an INFO or FORMAT column, a list unless there is exactly one value.
+fn column_type(kind: &InfoType, number: &InfoNumber) -> DataType {
+    let item = match kind {
+        InfoType::Integer => DataType::Int32,
+        InfoType::Float => DataType::Float32,
+        InfoType::Flag => return DataType::Boolean,
+        InfoType::Character | InfoType::String => DataType::Utf8,
+    };
+    if *number == InfoNumber::Count(1) {
+        item
+    } else {
+        list_type(item)
+    }
+}
+
+/// The fields of the per-sample struct columns.
+fn format_fields(format: &[HeaderFormat]) -> Fields {
+    format
+        .iter()
+        .map(|format| {
+            let data_type = if format.id == "GT" {
+                DataType::Utf8
+            } else {
+                column_type(format.kind(), format.number())
+            };
+            Field::new(&format.id, data_type, true)
+        })
+        .collect()
+}
+
+/// Builds the column of a FORMAT field, see `column`; `GT` is converted to text.
+fn format_column(format: &HeaderFormat, values: &[Option<TypedVec>]) -> ArrayRef {
+    if format.id == "GT" {
+        Arc::new(StringArray::from_iter(
+            values.iter().map(|v| v.as_ref().and_then(genotype)),
+        ))
+    } else {
+        column(format.kind(), format.number(), values)
+    }
+}
+
+fn text(text: &[u8]) -> Option<String> {
+    if text.is_empty() || text == b"." {
+        None
+    } else {
+        Some(String::from_utf8_lossy(text).into_owned())
+    }
+}
+
+fn genotype(value: &TypedVec) -> Option<String> {
+    match value {
+        TypedVec::Int32(alleles) if !alleles.is_empty() && alleles[0] != MISSING_INTEGER => {
+            Some(vcf_genotype(value))
+        }
+        _ => None,
+    }
+}
+
+/// The values of an INFO or FORMAT field, or `None` if the whole field is missing
+/// (or, in case of an invalid file, of another type).
+fn integers(value: &Option<TypedVec>) -> Option<Vec<Option<i32>>> {
+    match value {
+        Some(TypedVec::Int32(v)) if !v.is_empty() => Some(
+            v.iter()
+                .map(|&v| Some(v).filter(|&v| v != MISSING_INTEGER))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn floats(value: &Option<TypedVec>) -> Option<Vec<Option<f32>>> {
+    match value {
+        Some(TypedVec::Float32(v)) if !v.is_empty() => Some(
+            v.iter()
+                .map(|&v| Some(v).filter(|v| v.to_bits() != MISSING_FLOAT))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn strings(value: &Option<TypedVec>) -> Option<Vec<Option<String>>> {
+    match value {
+        Some(v @ TypedVec::UString(s)) if !s.is_empty() && s != b"." => {
+            Some(v.string().into_iter().map(text).collect())
+        }
+        _ => None,
+    }
+}
+
+fn string_list<I: Iterator<Item = Option<Vec<Option<String>>>>>(values: I) -> ArrayRef {
+    let mut builder = ListBuilder::new(StringBuilder::new());
+    for value in values {
+        match value {
+            Some(strings) => {
+                for s in strings {
+                    builder.values().append_option(s);
+                }
+                builder.append(true);
+            }
+            None => builder.append(false),
+        }
+    }
+    Arc::new(builder.finish())
+}
+
+/// Builds the column of an INFO or FORMAT field with the type given by `column_type`.
+fn column(kind: &InfoType, number: &InfoNumber, values: &[Option<TypedVec>]) -> ArrayRef {
+    let scalar = *number == InfoNumber::Count(1);
+    match kind {
+        InfoType::Flag => Arc::new(BooleanArray::from(
+            values.iter().map(|v| v.is_some()).collect::<Vec<_>>(),
+        )),
+        InfoType::Integer if scalar => Arc::new(Int32Array::from_iter(
+            values.iter().map(|v| integers(v).and_then(|v| v[0])),
+        )),
+        InfoType::Integer => Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(
+            values.iter().map(integers),
+        )),
+        InfoType::Float if scalar => Arc::new(Float32Array::from_iter(
+            values.iter().map(|v| floats(v).and_then(|v| v[0])),
+        )),
+        InfoType::Float => Arc::new(ListArray::from_iter_primitive::<Float32Type, _, _>(
+            values.iter().map(floats),
+        )),
+        InfoType::Character | InfoType::String if scalar => {
+            Arc::new(StringArray::from_iter(values.iter().map(|v| match v {
+                Some(TypedVec::UString(s)) => text(s),
+                _ => None,
+            })))
+        }
+        InfoType::Character | InfoType::String => string_list(values.iter().map(strings)),
+    }
+}
@@ -0,0 +1,384 @@
+//! Export of genotype probabilities into BGEN v1.2 files (behind the `bgen` feature).
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::anyhow;
+use flate2::write::ZlibEncoder;
+
+use crate::reader::BcfRecords;
+use crate::record::{GenotypeAllele, Record};
+use crate::types::{Header, TypedVec};
+
+/// The compression of genotype data blocks.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BgenCompression {
+    Zlib,
+    /// Zstandard, which readers only support since the BGEN v1.3 spec
+    Zstd,
+}
+
+#[derive(Debug, Clone)]
+pub struct BgenOptions {
+    pub compression: BgenCompression,
+    /// The number of bits per probability, between 1 and 32
+    pub bits: u8,
+}
+
+impl Default for BgenOptions {
+    fn default() -> Self {
+        BgenOptions {
+            compression: BgenCompression::Zlib,
+            bits: 8,
+        }
+    }
+}
+
+/// The offset of the number of variants in the header block, which is only known in the end.
+const N_VARIANTS_OFFSET: u64 = 8;
+
+/// Writes records into a BGEN v1.2 file with layout 2 and a sample identifier block.
+///
+/// Genotype probabilities are taken from the `GP` field of a sample if it has one value per
+/// genotype (which is normalised to sum up to 1), and otherwise derived from the `GT` call,
+/// i.e. probability 1 for the called genotype. Calls without either are missing. The ploidy
+/// is that of the `GT` call, or 2 without one. All probabilities are written unphased.
+///
+/// The variant ID is used as both BGEN variant ID and RSID, or `CHROM:POS:REF:ALT` without one.
+/// Call `finish` when done, which writes the number of variants into the header.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use rust_bcf::bgen::{BgenOptions, BgenWriter};
+/// use rust_bcf::BcfRecords;
+///
+/// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+/// let mut writer =
+///     BgenWriter::new(Cursor::new(vec![]), records.header(), BgenOptions::default()).unwrap();
+/// for record in records {
+///     writer.write(&record).unwrap();
+/// }
+/// let bgen = writer.finish().unwrap().into_inner();
+/// assert_eq!(&bgen[16..20], b"bgen");
+/// ```
+pub struct BgenWriter<W: Write + Seek> {
+    inner: W,
+    options: BgenOptions,
+    /// The position of the header, to which the number of variants is relative
+    start: u64,
+    n_samples: usize,
+    n_variants: u32,
+}
+
+impl BgenWriter<BufWriter<File>> {
+    pub fn to_path<P: AsRef<Path>>(
+        path: P,
+        header: &Header,
+        options: BgenOptions,
+    ) -> anyhow::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Self::new(file, header, options)
+    }
+}
+
+impl<W: Write + Seek> BgenWriter<W> {
+    /// Creates a new writer and writes the header block and the sample identifiers.
+    pub fn new(mut inner: W, header: &Header, options: BgenOptions) -> anyhow::Result<Self> {
+        if !(1..=32).contains(&options.bits) {
+            return Err(anyhow!(
+                "BGEN probabilities need 1 to 32 bits, not {}",
+                options.bits
+            ));
+        }
+        let start = inner.stream_position()?;
+        let samples = header.samples();
+        let header_length = 20u32;
+        let samples_length = 8 + samples.iter().map(|s| 2 + s.len() as u32).sum::<u32>();
+        let compression = match options.compression {
+            BgenCompression::Zlib => 1,
+            BgenCompression::Zstd => 2,
+        };
+        // compression, layout 2 and sample identifiers present
+        let flags: u32 = compression | 2 << 2 | 1 << 31;
+        inner.write_all(&(header_length + samples_length).to_le_bytes())?;
+        inner.write_all(&header_length.to_le_bytes())?;
+        inner.write_all(&0u32.to_le_bytes())?;
+        inner.write_all(&(samples.len() as u32).to_le_bytes())?;
+        inner.write_all(b"bgen")?;
+        inner.write_all(&flags.to_le_bytes())?;
+        inner.write_all(&samples_length.to_le_bytes())?;
+        inner.write_all(&(samples.len() as u32).to_le_bytes())?;
+        for sample in samples {
+            write_u16_text(&mut inner, sample.as_bytes())?;
+        }
+        Ok(BgenWriter {
+            inner,
+            options,
+            start,
+            n_samples: samples.len(),
+            n_variants: 0,
+        })
+    }
+
+    pub fn write<R: Record>(&mut self, record: &R) -> anyhow::Result<()> {
+        let chrom = record.try_chrom()?;
+        let ref_allele = record.ref_allele();
+        let alt_alleles = record.alt_alleles();
+        let n_alleles = 1 + alt_alleles.len();
+        if n_alleles > u16::MAX as usize {
+            return Err(anyhow!(
+                "{}:{} has too many alleles for BGEN",
+                chrom,
+                record.pos() + 1
+            ));
+        }
+        let id = match record.id() {
+            id if id.is_empty() || id == b"." => format!(
+                "{}:{}:{}:{}",
+                chrom,
+                record.pos() + 1,
+                String::from_utf8_lossy(&ref_allele),
+                alt_alleles
+                    .iter()
+                    .map(|alt| String::from_utf8_lossy(alt))
+                    .collect::<Vec<_>>()
+                    .join(":")
+            )
+            .into_bytes(),
+            id => id,
+        };
+        write_u16_text(&mut self.inner, &id)?;
+        write_u16_text(&mut self.inner, &id)?;
+        write_u16_text(&mut self.inner, chrom.as_bytes())?;
+        self.inner.write_all(&(record.pos() + 1).to_le_bytes())?;
+        self.inner.write_all(&(n_alleles as u16).to_le_bytes())?;
+        for allele in std::iter::once(&ref_allele).chain(&alt_alleles) {
+            self.inner.write_all(&(allele.len() as u32).to_le_bytes())?;
+            self.inner.write_all(allele)?;
+        }
+
+        let data = self.probability_data(record, n_alleles)?;
+        let compressed = match self.options.compression {
+            BgenCompression::Zlib => {
+                let mut encoder = ZlibEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+            BgenCompression::Zstd => zstd::encode_all(&data[..], 0)?,
+        };
+        self.inner
+            .write_all(&(compressed.len() as u32 + 4).to_le_bytes())?;
+        self.inner.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&compressed)?;
+        self.n_variants += 1;
+        Ok(())
+    }
+
+    /// Returns the uncompressed genotype data block of layout 2.
+    fn probability_data<R: Record>(&self, record: &R, n_alleles: usize) -> anyhow::Result<Vec<u8>> {
+        let genotypes = record.genotypes();
+        let probabilities = record.format(b"GP");
+        let samples = (0..self.n_samples)
+            .map(|sample| {
+                sample_probabilities(
+                    genotypes.get(sample).map(Vec::as_slice),
+                    probabilities.as_ref().and_then(|gp| gp.get(sample)),
+                    n_alleles,
+                )
+            })
+            .collect::<Vec<_>>();
+        if let Some((ploidy, _)) = samples.iter().find(|(ploidy, _)| *ploidy > 63) {
+            return Err(anyhow!(
+                "{}:{} has a call of ploidy {}, BGEN supports at most 63",
+                record.try_chrom()?,
+                record.pos() + 1,
+                ploidy
+            ));
+        }
+        let min_ploidy = samples.iter().map(|(p, _)| *p).min().unwrap_or(2);
+        let max_ploidy = samples.iter().map(|(p, _)| *p).max().unwrap_or(2);
+
+        let mut data = vec![];
+        data.extend_from_slice(&(self.n_samples as u32).to_le_bytes());
+        data.extend_from_slice(&(n_alleles as u16).to_le_bytes());
+        data.push(min_ploidy as u8);
+        data.push(max_ploidy as u8);
+        for (ploidy, probabilities) in &samples {
+            let missing = if probabilities.is_none() { 0x80 } else { 0 };
+            data.push(*ploidy as u8 | missing);
+        }
+        // unphased
+        data.push(0);
+        data.push(self.options.bits);
+        let mut bits = BitWriter::new(data);
+        for (ploidy, probabilities) in &samples {
+            let n_genotypes = n_genotypes(*ploidy, n_alleles);
+            match probabilities {
+                Some(probabilities) => {
+                    let values = quantize(probabilities, self.options.bits);
+                    for &value in &values[..values.len() - 1] {
+                        bits.push(value, self.options.bits);
+                    }
+                }
+                None => {
+                    for _ in 1..n_genotypes {
+                        bits.push(0, self.options.bits);
+                    }
+                }
+            }
+        }
+        Ok(bits.finish())
+    }
+
+    /// Writes the number of variants into the header and returns the inner writer.
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        let end = self.inner.stream_position()?;
+        self.inner
+            .seek(SeekFrom::Start(self.start + N_VARIANTS_OFFSET))?;
+        self.inner.write_all(&self.n_variants.to_le_bytes())?;
+        self.inner.seek(SeekFrom::Start(end))?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+fn write_u16_text<W: Write>(writer: &mut W, text: &[u8]) -> anyhow::Result<()> {
+    if text.len() > u16::MAX as usize {
+        return Err(anyhow!("identifier too long for BGEN"));
+    }
+    writer.write_all(&(text.len() as u16).to_le_bytes())?;
+    writer.write_all(text)?;
+    Ok(())
+}
+
+/// Returns the ploidy and the genotype probabilities of a sample, or `None` if missing.
+fn sample_probabilities(
+    call: Option<&[GenotypeAllele]>,
+    probabilities: Option<&TypedVec>,
+    n_alleles: usize,
+) -> (usize, Option<Vec<f64>>) {
+    let call = call.unwrap_or_default();
+    let ploidy = if call.is_empty() { 2 } else { call.len() };
+    let n_genotypes = n_genotypes(ploidy, n_alleles);
+    if let Some(TypedVec::Float32(values)) = probabilities {
+        let sum = values.iter().map(|&p| p as f64).sum::<f64>();
+        if values.len() == n_genotypes && sum > 0.0 && values.iter().all(|p| *p >= 0.0) {
+            return (
+                ploidy,
+                Some(values.iter().map(|&p| p as f64 / sum).collect()),
+            );
+        }
+    }
+    let alleles = call
+        .iter()
+        .map(|allele| allele.index().filter(|&i| (i as usize) < n_alleles))
+        .collect::<Option<Vec<_>>>();
+    match alleles {
+        Some(mut alleles) if !alleles.is_empty() => {
+            let mut probabilities = vec![0.0; n_genotypes];
+            alleles.sort_unstable();
+            probabilities[genotype_index(&alleles)] = 1.0;
+            (ploidy, Some(probabilities))
+        }
+        _ => (ploidy, None),
+    }
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+}
+
+/// The number of unphased genotypes of the given ploidy and number of alleles.
+fn n_genotypes(ploidy: usize, n_alleles: usize) -> usize {
+    binomial(ploidy + n_alleles - 1, n_alleles - 1)
+}
+
+/// The index of a genotype of sorted allele indices in colex order, which is both the order of
+/// BGEN and of VCF fields with `Number=G`.
+fn genotype_index(alleles: &[u32]) -> usize {
+    alleles
+        .iter()
+        .enumerate()
+        .map(|(i, &allele)| binomial(allele as usize + i, i + 1))
+        .sum()
+}
+
+/// Scales probabilities to integers of the given number of bits which sum up to the maximum,
+/// rounding up those with the largest remainders as recommended by the BGEN spec.
+fn quantize(probabilities: &[f64], bits: u8) -> Vec<u64> {
+    let max = ((1u64 << bits) - 1) as f64;
+    let scaled = probabilities.iter().map(|p| p * max).collect::<Vec<_>>();
+    let mut values = scaled.iter().map(|v| v.floor() as u64).collect::<Vec<_>>();
+    let remainder = (max as u64).saturating_sub(values.iter().sum());
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        (scaled[b] - scaled[b].floor()).total_cmp(&(scaled[a] - scaled[a].floor()))
+    });
+    for &i in order.iter().take(remainder as usize) {
+        values[i] += 1;
+    }
+    values
+}
+
+/// Packs values of up to 32 bits, least significant bit first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    n_bits: u8,
+}
+
+impl BitWriter {
+    fn new(bytes: Vec<u8>) -> Self {
+        BitWriter {
+            bytes,
+            buffer: 0,
+            n_bits: 0,
+        }
+    }
+
+    fn push(&mut self, value: u64, bits: u8) {
+        self.buffer |= value << self.n_bits;
+        self.n_bits += bits;
+        while self.n_bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.n_bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.n_bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Converts a BCF file into a BGEN file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_bcf::bgen::{bcf_to_bgen, BgenCompression, BgenOptions};
+///
+/// let options = BgenOptions {
+///     compression: BgenCompression::Zstd,
+///     bits: 16,
+/// };
+/// bcf_to_bgen("imputed.bcf", "imputed.bgen", options).unwrap();
+/// ```
+pub fn bcf_to_bgen<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: BgenOptions,
+) -> anyhow::Result<()> {
+    let records = BcfRecords::from_path(input)?;
+    let mut writer = BgenWriter::to_path(output, records.header(), options)?;
+    for record in records {
+        writer.write(&record)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,260 @@
+//! BGZF ("blocked gzip") compression, i.e. a series of gzip members of at most 64KiB each,
+//! which (unlike plain gzip) allows random access via virtual file offsets.
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+/// The maximum number of uncompressed bytes per block; the same limit as used by htslib,
+/// which guarantees that the compressed block never exceeds 64KiB.
+const MAX_BLOCK_DATA_SIZE: usize = 0xff00;
+
+/// The gzip header (with BGZF's extra subfield `BC`) up to the block size field.
+const BLOCK_HEADER: [u8; 16] = [
+    0x1f, 0x8b, // gzip magic
+    0x08, // compression method (deflate)
+    0x04, // flags (extra field present)
+    0x00, 0x00, 0x00, 0x00, // modification time
+    0x00, // extra flags
+    0xff, // operating system (unknown)
+    0x06, 0x00, // length of the extra field
+    b'B', b'C', // subfield identifier
+    0x02, 0x00, // length of the subfield
+];
+const BLOCK_HEADER_LENGTH: usize = BLOCK_HEADER.len() + 2;
+const BLOCK_FOOTER_LENGTH: usize = 8;
+
+/// An empty block, which marks the end of a BGZF file.
+pub(crate) const EOF_MARKER: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// Compresses everything written to it into BGZF blocks.
+/// `finish` has to be called in order to write the last block and the EOF marker.
+pub struct BgzfWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+    level: Compression,
+    /// The number of compressed bytes written to `inner` so far
+    compressed_offset: u64,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        BgzfWriter {
+            inner,
+            buf: Vec::with_capacity(MAX_BLOCK_DATA_SIZE),
+            level: Compression::default(),
+            compressed_offset: 0,
+        }
+    }
+
+    /// Returns the virtual file offset of the next byte to be written, i.e. the offset of the
+    /// current block in the compressed file in the upper 48 bits and the offset within the
+    /// uncompressed block in the lower 16 bits.
+    pub fn virtual_offset(&self) -> u64 {
+        self.compressed_offset << 16 | self.buf.len() as u64
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(
+            Vec::with_capacity(MAX_BLOCK_DATA_SIZE + BLOCK_HEADER_LENGTH),
+            self.level,
+        );
+        encoder.write_all(&self.buf)?;
+        let data = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(&self.buf);
+
+        let block_size = BLOCK_HEADER_LENGTH + data.len() + BLOCK_FOOTER_LENGTH;
+        self.inner.write_all(&BLOCK_HEADER)?;
+        self.inner
+            .write_all(&((block_size - 1) as u16).to_le_bytes())?;
+        self.inner.write_all(&data)?;
+        self.inner.write_all(&crc.sum().to_le_bytes())?;
+        self.inner
+            .write_all(&(self.buf.len() as u32).to_le_bytes())?;
+        self.compressed_offset += block_size as u64;
+        self.buf.clear();
+        Ok(())
+    }
+
+    /// Writes all pending data as a block, such that the next byte starts a new block.
+    pub fn flush_block(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the remaining data and the EOF marker, and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        self.inner.write_all(&EOF_MARKER)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = data.len().min(MAX_BLOCK_DATA_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&data[..n]);
+        if self.buf.len() == MAX_BLOCK_DATA_SIZE {
+            self.write_block()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_block()?;
+        self.inner.flush()
+    }
+}
+
+/// Decompresses BGZF blocks, keeping track of the virtual file offset of the next byte,
+/// such that reading can be resumed at that position via `seek`.
+pub struct BgzfReader<R: Read> {
+    inner: R,
+    /// The decompressed data of the current block
+    block: Vec<u8>,
+    /// The position within `block`
+    position: usize,
+    /// The offset of the current block in the compressed file
+    block_offset: u64,
+    /// The offset of the next block in the compressed file
+    next_block_offset: u64,
+}
+
+impl<R: Read> BgzfReader<R> {
+    pub fn new(inner: R) -> Self {
+        BgzfReader {
+            inner,
+            block: Vec::with_capacity(MAX_BLOCK_DATA_SIZE),
+            position: 0,
+            block_offset: 0,
+            next_block_offset: 0,
+        }
+    }
+
+    /// Returns the virtual file offset of the next byte to be read, see
+    /// `BgzfWriter::virtual_offset`. At the end of a block, this is the start of the next block.
+    pub fn virtual_offset(&self) -> u64 {
+        if self.position == self.block.len() {
+            self.next_block_offset << 16
+        } else {
+            self.block_offset << 16 | self.position as u64
+        }
+    }
+
+    /// Reads and decompresses the next block; returns `false` at the end of the file.
+    fn read_block(&mut self) -> io::Result<bool> {
+        self.block.clear();
+        self.position = 0;
+        self.block_offset = self.next_block_offset;
+        let mut header = [0u8; 12];
+        match self.inner.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        if header[..4] != BLOCK_HEADER[..4] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid BGZF block header",
+            ));
+        }
+        let extra_length = u16::from_le_bytes([header[10], header[11]]) as usize;
+        let mut extra = vec![0u8; extra_length];
+        self.inner.read_exact(&mut extra)?;
+        // find the BC subfield, which holds the total block size minus 1
+        let mut block_size = None;
+        let mut subfields = &extra[..];
+        while subfields.len() >= 4 {
+            let length = u16::from_le_bytes([subfields[2], subfields[3]]) as usize;
+            if subfields[..2] == *b"BC" && length == 2 && subfields.len() >= 6 {
+                block_size = Some(u16::from_le_bytes([subfields[4], subfields[5]]) as usize + 1);
+            }
+            subfields = &subfields[(4 + length).min(subfields.len())..];
+        }
+        let block_size = block_size
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing BGZF block size"))?;
+        if block_size < header.len() + extra_length + BLOCK_FOOTER_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "BGZF block size is smaller than its header and footer",
+            ));
+        }
+        let mut data = vec![0u8; block_size - header.len() - extra_length];
+        self.inner.read_exact(&mut data)?;
+        let (data, footer) = data.split_at(data.len() - BLOCK_FOOTER_LENGTH);
+        let size = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]) as usize;
+        // blocks hold at most 64 KiB of data, don't trust the footer for the allocation
+        if size > 1 << 16 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "BGZF block data exceeds 64 KiB",
+            ));
+        }
+        self.block.reserve(size);
+        DeflateDecoder::new(data).read_to_end(&mut self.block)?;
+        let mut crc = Crc::new();
+        crc.update(&self.block);
+        if self.block.len() != size || crc.sum().to_le_bytes() != footer[..4] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "corrupt BGZF block",
+            ));
+        }
+        self.next_block_offset += block_size as u64;
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// Continues reading at the given virtual file offset.
+    pub fn seek(&mut self, virtual_offset: u64) -> io::Result<()> {
+        self.inner.seek(SeekFrom::Start(virtual_offset >> 16))?;
+        self.next_block_offset = virtual_offset >> 16;
+        self.read_block()?;
+        self.position = (virtual_offset & 0xffff) as usize;
+        if self.position > self.block.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "virtual offset is beyond the end of its block",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> BufRead for BgzfReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // skip empty blocks, such as the EOF marker
+        while self.position == self.block.len() {
+            if !self.read_block()? {
+                break;
+            }
+        }
+        Ok(&self.block[self.position..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.position = (self.position + amt).min(self.block.len());
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let data = self.fill_buf()?;
+            let n = data.len().min(buf.len());
+            buf[..n].copy_from_slice(&data[..n]);
+            n
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
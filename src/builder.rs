@@ -0,0 +1,294 @@
+use std::sync::Arc;
+
+use crate::record::{BcfRecord, GenotypeAllele};
+use crate::types::{
+    Header, HeaderContig, HeaderFilter, HeaderFormat, HeaderInfo, InfoNumber, InfoType, TypedVec,
+    PASS_FILTER,
+};
+use crate::variant::VariantRecord;
+
+/// Builds encoded `BcfRecord`s for a given header, e.g. for generating test fixtures.
+///
+/// All values are validated against the header definitions when calling `build`.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::builder::RecordBuilder;
+/// use rust_bcf::record::GenotypeAllele::{Phased, Unphased};
+/// use rust_bcf::{BcfRecords, Record};
+///
+/// let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+/// let header = records.header().clone();
+/// let record = RecordBuilder::new(header.into())
+///     .chrom("chr1")
+///     .pos(0)
+///     .alleles(["A", "T"])
+///     .qual(30.)
+///     .filter("PASS")
+///     .info_i32("INT", &[5])
+///     .format_f32("FLOAT", [[0.25]])
+///     .genotypes(&[vec![Unphased(0), Phased(1)]])
+///     .build()
+///     .unwrap();
+/// assert_eq!(record.alt_alleles(), [b"T"]);
+/// assert_eq!(record.info(b"INT").unwrap().integer(), [5]);
+/// assert_eq!(record.genotypes(), [[Unphased(0), Phased(1)]]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordBuilder {
+    header: Arc<Header>,
+    record: VariantRecord,
+}
+
+impl RecordBuilder {
+    pub fn new(header: Arc<Header>) -> Self {
+        RecordBuilder {
+            header,
+            record: VariantRecord::new("", 0, vec![]),
+        }
+    }
+
+    /// Sets CHROM, which has to be declared as contig in the header.
+    pub fn chrom(mut self, chrom: &str) -> Self {
+        self.record.set_chrom(chrom.into());
+        self
+    }
+
+    /// Sets POS, 0-based.
+    pub fn pos(mut self, pos: u32) -> Self {
+        self.record.set_pos(pos);
+        self
+    }
+
+    pub fn id(mut self, id: &[u8]) -> Self {
+        self.record.set_id(id.into());
+        self
+    }
+
+    /// Sets REF and ALT, i.e. the first allele given is the reference allele.
+    pub fn alleles<I, A>(mut self, alleles: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<[u8]>,
+    {
+        self.record
+            .set_alleles(alleles.into_iter().map(|a| a.as_ref().into()).collect());
+        self
+    }
+
+    pub fn qual(mut self, qual: f32) -> Self {
+        self.record.set_qual(Some(qual));
+        self
+    }
+
+    /// Adds a filter to FILTER, see `VariantRecord::add_filter`.
+    /// If no filter is added, FILTER is missing (`.` in VCF).
+    pub fn filter(mut self, filter: &str) -> Self {
+        self.record.add_filter(filter);
+        self
+    }
+
+    pub fn info_i32(self, tag: &str, values: &[i32]) -> Self {
+        self.info(tag, TypedVec::Int32(values.into()))
+    }
+
+    pub fn info_f32(self, tag: &str, values: &[f32]) -> Self {
+        self.info(tag, TypedVec::Float32(values.into()))
+    }
+
+    /// Sets a String (or Character) INFO field; multiple values are joined by `,`.
+    pub fn info_string<I, S>(self, tag: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        self.info(tag, TypedVec::UString(join(values)))
+    }
+
+    /// Sets a Flag INFO field, encoded as recommended by the specification.
+    pub fn info_flag(self, tag: &str) -> Self {
+        self.info(tag, TypedVec::Int32(vec![1]))
+    }
+
+    fn info(mut self, tag: &str, value: TypedVec) -> Self {
+        self.record.info_mut().insert(tag.into(), value);
+        self
+    }
+
+    /// Sets an Integer FORMAT field, with one list of values per sample.
+    pub fn format_i32<I, V>(self, tag: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: AsRef<[i32]>,
+    {
+        let values = values
+            .into_iter()
+            .map(|v| TypedVec::Int32(v.as_ref().into()))
+            .collect();
+        self.format(tag, values)
+    }
+
+    /// Sets a Float FORMAT field, with one list of values per sample.
+    pub fn format_f32<I, V>(self, tag: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: AsRef<[f32]>,
+    {
+        let values = values
+            .into_iter()
+            .map(|v| TypedVec::Float32(v.as_ref().into()))
+            .collect();
+        self.format(tag, values)
+    }
+
+    /// Sets a String FORMAT field, with one value per sample.
+    pub fn format_string<I, S>(self, tag: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        let values = values
+            .into_iter()
+            .map(|v| TypedVec::UString(v.as_ref().into()))
+            .collect();
+        self.format(tag, values)
+    }
+
+    fn format(mut self, tag: &str, values: Vec<TypedVec>) -> Self {
+        self.record.format_mut().insert(tag.into(), values);
+        self
+    }
+
+    /// Sets GT, with one genotype per sample.
+    pub fn genotypes(mut self, genotypes: &[Vec<GenotypeAllele>]) -> Self {
+        self.record.set_genotypes(genotypes);
+        self
+    }
+
+    /// Validates the record against the header and encodes it.
+    pub fn build(self) -> anyhow::Result<BcfRecord> {
+        self.record.validate(&self.header)?;
+        self.record.to_bcf_record(self.header)
+    }
+}
+
+fn join<I, S>(values: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<[u8]>,
+{
+    values
+        .into_iter()
+        .map(|v| v.as_ref().to_vec())
+        .collect::<Vec<_>>()
+        .join(&b',')
+}
+
+/// Builds a `Header`, either from scratch or derived from an existing header.
+///
+/// IDX values of FILTER, INFO and FORMAT definitions are assigned automatically,
+/// see `Header::add_info`.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::builder::HeaderBuilder;
+/// use rust_bcf::types::{InfoNumber, InfoType};
+///
+/// let header = HeaderBuilder::new()
+///     .contig("chr1", Some(248956422))
+///     .filter("LowQual", "Low quality")
+///     .info("DP", InfoNumber::Count(1), InfoType::Integer, "Total depth")
+///     .format("DP", InfoNumber::Count(1), InfoType::Integer, "Read depth")
+///     .sample("NA12878")
+///     .build()
+///     .unwrap();
+/// assert_eq!(header.info_tag_to_offset()["DP"], header.format_tag_to_offset()["DP"]);
+/// assert_eq!(header.samples(), &["NA12878"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HeaderBuilder {
+    header: Header,
+    samples: Vec<String>,
+}
+
+impl Default for HeaderBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeaderBuilder {
+    /// Creates a builder for a VCFv4.3 header which only defines the `PASS` filter.
+    pub fn new() -> Self {
+        let mut header = Header::empty();
+        header.add_meta("fileformat", "VCFv4.3");
+        header.add_filter(HeaderFilter::new(PASS_FILTER, "All filters passed"));
+        HeaderBuilder {
+            header,
+            samples: vec![],
+        }
+    }
+
+    pub fn info(mut self, id: &str, number: InfoNumber, kind: InfoType, description: &str) -> Self {
+        self.header
+            .add_info(HeaderInfo::new(id, number, kind, description));
+        self
+    }
+
+    pub fn format(
+        mut self,
+        id: &str,
+        number: InfoNumber,
+        kind: InfoType,
+        description: &str,
+    ) -> Self {
+        self.header
+            .add_format(HeaderFormat::new(id, number, kind, description));
+        self
+    }
+
+    pub fn filter(mut self, id: &str, description: &str) -> Self {
+        self.header.add_filter(HeaderFilter::new(id, description));
+        self
+    }
+
+    pub fn contig(mut self, id: &str, length: Option<usize>) -> Self {
+        self.header.add_contig(HeaderContig::new(id, length));
+        self
+    }
+
+    /// Adds a generic `##key=value` line.
+    pub fn meta(mut self, key: &str, value: &str) -> Self {
+        if key == "fileformat" {
+            self.header.remove_meta(key);
+        }
+        self.header.add_meta(key, value);
+        self
+    }
+
+    /// Appends a sample.
+    pub fn sample(mut self, name: &str) -> Self {
+        self.samples.push(name.into());
+        self
+    }
+
+    /// Returns the header; fails if sample names are not unique.
+    pub fn build(mut self) -> anyhow::Result<Header> {
+        for sample in &self.samples {
+            self.header.add_sample(sample)?;
+        }
+        Ok(self.header)
+    }
+}
+
+impl From<Header> for HeaderBuilder {
+    /// Derives a new header from an existing one, keeping all of its IDX values.
+    fn from(header: Header) -> Self {
+        HeaderBuilder {
+            header,
+            samples: vec![],
+        }
+    }
+}
@@ -0,0 +1,299 @@
+//! Conversion of VCF into BCF, optionally inferring header lines which are missing in the VCF.
+use std::fs::{self, File};
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use crate::record::Record;
+use crate::types::{
+    Header, HeaderContig, HeaderFilter, HeaderFormat, HeaderInfo, InfoNumber, InfoType, PASS_FILTER,
+};
+use crate::variant::VariantRecord;
+use crate::vcf::{VcfRecord, VcfRecords};
+use crate::writer::{BcfWriter, Compression};
+
+/// The description of header lines added by `HeaderInference`.
+const INFERRED_DESCRIPTION: &str = "Added automatically, inferred from observed values";
+
+/// How to deal with INFO, FORMAT, FILTER and contigs which are not defined in the VCF header.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Mode {
+    /// Undefined fields and contigs are an error.
+    Strict,
+    /// Definitions for undefined fields and contigs are inferred from the records.
+    Lenient,
+}
+
+/// The values observed for a single undefined INFO or FORMAT field.
+#[derive(Debug, Default)]
+struct Observation {
+    /// The most general type of all values so far
+    kind: Option<InfoType>,
+    /// The number of values of the first occurrence
+    count: Option<usize>,
+    /// Whether the number of values was the same for all occurrences
+    constant: bool,
+    /// Whether the number of values was the number of alternative alleles for all occurrences
+    per_alt: bool,
+    /// Whether the number of values was the number of alleles for all occurrences
+    per_allele: bool,
+}
+
+impl Observation {
+    /// Returns the "rank" of a type, where each type can represent all types of lower rank.
+    fn rank(kind: &InfoType) -> u8 {
+        match kind {
+            InfoType::Flag => 0,
+            InfoType::Integer => 1,
+            InfoType::Float => 2,
+            InfoType::Character | InfoType::String => 3,
+        }
+    }
+
+    fn widen(&mut self, kind: InfoType) {
+        match &self.kind {
+            Some(current) if Self::rank(current) >= Self::rank(&kind) => {}
+            _ => self.kind = Some(kind),
+        }
+    }
+
+    /// Records a value (`None` for flags) of a record with `n_alleles` alleles.
+    fn observe(&mut self, value: Option<&str>, n_alleles: usize) {
+        let value = match value {
+            Some(value) => value,
+            None => return self.widen(InfoType::Flag),
+        };
+        let values = value.split(',').collect::<Vec<_>>();
+        // missing values (e.g. dropped trailing FORMAT fields) tell nothing about Type and Number
+        if values.iter().all(|&v| v == ".") {
+            return;
+        }
+        for value in values.iter().filter(|&&v| v != ".") {
+            if value.parse::<i32>().is_ok() {
+                self.widen(InfoType::Integer);
+            } else if is_float_literal(value) {
+                self.widen(InfoType::Float);
+            } else {
+                self.widen(InfoType::String);
+            }
+        }
+        let n = values.len();
+        match self.count {
+            None => {
+                self.count = Some(n);
+                self.constant = true;
+                self.per_alt = n + 1 == n_alleles;
+                self.per_allele = n == n_alleles;
+            }
+            Some(count) => {
+                self.constant &= count == n;
+                self.per_alt &= n + 1 == n_alleles;
+                self.per_allele &= n == n_alleles;
+            }
+        }
+    }
+
+    /// Returns the inferred Type and Number; fields which only had missing values are strings.
+    fn definition(&self) -> (InfoType, InfoNumber) {
+        let kind = self.kind.clone().unwrap_or(InfoType::String);
+        let number = match (&kind, self.count) {
+            (InfoType::Flag, _) => InfoNumber::Count(0),
+            (_, Some(count)) if self.constant => InfoNumber::Count(count),
+            _ if self.per_alt => InfoNumber::AlternateAlleles,
+            _ if self.per_allele => InfoNumber::Alleles,
+            _ => InfoNumber::Unknown,
+        };
+        (kind, number)
+    }
+}
+
+/// Returns `true` for numeric literals such as `0.5`, `-1e-3` or `.5`, but not for words which
+/// Rust parses as floats, such as `nan`, `inf` or `infinity`.
+fn is_float_literal(value: &str) -> bool {
+    value.parse::<f32>().is_ok()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'-' | b'+' | b'e' | b'E'))
+}
+
+/// Infers definitions for the INFO and FORMAT fields, filters and contigs of VCF records which
+/// are not defined in their header.
+///
+/// Type and Number are inferred from all observed values: Type is the most specific of `Flag`,
+/// `Integer`, `Float` and `String` which can represent all values, Number is either fixed,
+/// `A`, `R` or `.`.
+#[derive(Debug)]
+pub struct HeaderInference {
+    header: Header,
+    contigs: Vec<String>,
+    filters: Vec<String>,
+    info: IndexMap<String, Observation>,
+    format: IndexMap<String, Observation>,
+}
+
+impl HeaderInference {
+    /// Starts with the given header, whose definitions are kept as-is.
+    pub fn new(header: &Header) -> Self {
+        HeaderInference {
+            header: header.clone(),
+            contigs: vec![],
+            filters: vec![],
+            info: IndexMap::new(),
+            format: IndexMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, record: &VcfRecord) {
+        let header = &self.header;
+        let chrom = record.chrom();
+        if header.contig_index(chrom).is_none()
+            && !self.contigs.iter().any(|contig| contig == chrom)
+        {
+            self.contigs.push(chrom.into());
+        }
+        for filter in record.filters() {
+            if filter != PASS_FILTER
+                && header.filter_by_id(filter).is_none()
+                && !self.filters.iter().any(|f| f == filter)
+            {
+                self.filters.push(filter.into());
+            }
+        }
+        let n_alleles = 1 + record.alt_alleles().len();
+        for (tag, value) in record.info_fields() {
+            if !header.info_tag_to_offset.contains_key(tag) {
+                self.info
+                    .entry(tag.into())
+                    .or_default()
+                    .observe(value, n_alleles);
+            }
+        }
+        let tags = record.format_tags();
+        for (i, tag) in tags.iter().enumerate() {
+            if header.format_tag_to_offset.contains_key(*tag) {
+                continue;
+            }
+            let observation = self.format.entry(tag.to_string()).or_default();
+            for sample in record.samples() {
+                let value = sample.split(':').nth(i).unwrap_or(".");
+                observation.observe(Some(value), n_alleles);
+            }
+        }
+    }
+
+    /// Returns the header with all inferred definitions added, and a warning for each of them.
+    pub fn finish(self) -> (Header, Vec<String>) {
+        let mut header = self.header;
+        let mut warnings = vec![];
+        for contig in self.contigs {
+            warnings.push(format!(
+                "contig {} is not defined in the header, added without length",
+                contig
+            ));
+            header.add_contig(HeaderContig::new(&contig, None));
+        }
+        for filter in self.filters {
+            warnings.push(format!(
+                "FILTER {} is not defined in the header, added",
+                filter
+            ));
+            header.add_filter(HeaderFilter::new(&filter, INFERRED_DESCRIPTION));
+        }
+        for (tag, observation) in self.info {
+            let (kind, number) = observation.definition();
+            warnings.push(format!(
+                "INFO {} is not defined in the header, added with Number={},Type={}",
+                tag, number, kind
+            ));
+            header.add_info(HeaderInfo::new(&tag, number, kind, INFERRED_DESCRIPTION));
+        }
+        for (tag, observation) in self.format {
+            // GT has a dedicated encoding, regardless of its values
+            let (kind, number) = if tag == "GT" {
+                (InfoType::String, InfoNumber::Count(1))
+            } else {
+                observation.definition()
+            };
+            warnings.push(format!(
+                "FORMAT {} is not defined in the header, added with Number={},Type={}",
+                tag, number, kind
+            ));
+            header.add_format(HeaderFormat::new(&tag, number, kind, INFERRED_DESCRIPTION));
+        }
+        (header, warnings)
+    }
+}
+
+/// Encodes all given records with respect to the header of `writer`, which has to define all
+/// of their fields, filters and contigs (e.g. a header completed by `HeaderInference`).
+/// Values which do not match their declared type are an error naming the line and the field.
+pub fn convert<R: BufRead, W: Write>(
+    mut records: VcfRecords<R>,
+    writer: &mut BcfWriter<W>,
+) -> anyhow::Result<()> {
+    let header = Arc::new(writer.header().clone());
+    while let Some(record) = records.next() {
+        // parse the values according to the types declared in the output header
+        let variant = VcfRecord::new(record.line().into(), header.clone())
+            .and_then(|record| VariantRecord::try_from_record(&record))
+            .map_err(|e| anyhow::anyhow!("line {}: {}", records.line_number(), e))?;
+        writer.write_variant(&variant)?;
+    }
+    records.take_error().map_or(Ok(()), Err)
+}
+
+/// Converts a (possibly compressed) VCF file into a BCF file and returns warnings for all
+/// header lines which were added in `Mode::Lenient`. In this mode, the input is read twice:
+/// once for inferring missing header lines, and once for converting the records. Inputs which
+/// cannot be read twice, i.e. which are not regular files (such as pipes or `/dev/stdin`), are
+/// therefore read into memory first.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_bcf::convert::{vcf_to_bcf, Mode};
+/// use rust_bcf::writer::Compression;
+///
+/// let warnings = vcf_to_bcf("input.vcf.gz", "output.bcf", Compression::Bgzf, Mode::Lenient).unwrap();
+/// for warning in warnings {
+///     eprintln!("{}", warning);
+/// }
+/// ```
+pub fn vcf_to_bcf<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    compression: Compression,
+    mode: Mode,
+) -> anyhow::Result<Vec<String>> {
+    let buffer = if mode == Mode::Lenient && !fs::metadata(&input)?.is_file() {
+        let mut buffer = vec![];
+        File::open(&input)?.read_to_end(&mut buffer)?;
+        Some(buffer)
+    } else {
+        None
+    };
+    let open = || match &buffer {
+        Some(buffer) => VcfRecords::new(&buffer[..]),
+        None => VcfRecords::new(File::open(&input)?),
+    };
+    let mut records = open()?;
+    let (header, warnings) = match mode {
+        Mode::Strict => (records.header().clone(), vec![]),
+        Mode::Lenient => {
+            let mut inference = HeaderInference::new(records.header());
+            records
+                .by_ref()
+                .for_each(|record| inference.observe(&record));
+            if let Some(error) = records.take_error() {
+                return Err(error);
+            }
+            inference.finish()
+        }
+    };
+    let mut writer = BcfWriter::to_path(output, &header, compression)?;
+    convert(open()?, &mut writer)?;
+    writer.finish()?;
+    Ok(warnings)
+}
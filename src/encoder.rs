@@ -0,0 +1,359 @@
+//! The counterpart to `parser`: functions for writing values in their BCF binary encoding.
+use anyhow::bail;
+use itertools::Itertools;
+
+use std::collections::HashMap;
+
+use crate::types::{
+    Header, HeaderLine, HeaderValue, Layout, TypeKind, TypedVec, END_OF_VECTOR_FLOAT_32,
+    END_OF_VECTOR_INT_16, END_OF_VECTOR_INT_32, END_OF_VECTOR_INT_8, MISSING_FLOAT,
+    MISSING_INTEGER, MISSING_INT_16, MISSING_INT_8, PASS_FILTER,
+};
+
+/// The smallest and largest values representable by each integer type; the lowest 8 values
+/// of each type's range are reserved (for missing, end-of-vector etc.).
+const INT8_RANGE: (i32, i32) = (i8::MIN as i32 + 8, i8::MAX as i32);
+const INT16_RANGE: (i32, i32) = (i16::MIN as i32 + 8, i16::MAX as i32);
+
+/// Writes a `TypeDescriptor` for `num_elements` values of the given kind.
+/// If `num_elements` does not fit into the upper 4 bits, these are set to `0b1111`
+/// and the actual number of elements follows as a typed integer.
+pub(crate) fn type_descriptor(kind: TypeKind, num_elements: usize, buf: &mut Vec<u8>) {
+    if num_elements < 15 {
+        buf.push((num_elements as u8) << 4 | kind as u8);
+    } else {
+        buf.push(0b1111 << 4 | kind as u8);
+        typed_int(num_elements as i32, buf);
+    }
+}
+
+/// Returns the smallest integer type which can hold all of the given values.
+pub(crate) fn int_kind(values: &[i32]) -> TypeKind {
+    let (min, max) = values
+        .iter()
+        .filter(|&&v| v != MISSING_INTEGER)
+        .fold((0, 0), |(min, max), &v| (min.min(v), max.max(v)));
+    if min >= INT8_RANGE.0 && max <= INT8_RANGE.1 {
+        TypeKind::Int8
+    } else if min >= INT16_RANGE.0 && max <= INT16_RANGE.1 {
+        TypeKind::Int16
+    } else {
+        TypeKind::Int32
+    }
+}
+
+/// Writes a single integer of the given width (without type descriptor),
+/// translating `MISSING_INTEGER` into the width's missing value.
+pub(crate) fn int(kind: TypeKind, value: i32, buf: &mut Vec<u8>) {
+    match kind {
+        TypeKind::Int8 if value == MISSING_INTEGER => buf.push(MISSING_INT_8),
+        TypeKind::Int8 => buf.push(value as i8 as u8),
+        TypeKind::Int16 if value == MISSING_INTEGER => {
+            buf.extend_from_slice(&MISSING_INT_16.to_le_bytes())
+        }
+        TypeKind::Int16 => buf.extend_from_slice(&(value as i16).to_le_bytes()),
+        TypeKind::Int32 => buf.extend_from_slice(&value.to_le_bytes()),
+        other => panic!("Expected an integer type, got {:?}", other),
+    }
+}
+
+fn int_end_of_vector(kind: TypeKind, buf: &mut Vec<u8>) {
+    match kind {
+        TypeKind::Int8 => buf.push(END_OF_VECTOR_INT_8),
+        TypeKind::Int16 => buf.extend_from_slice(&END_OF_VECTOR_INT_16.to_le_bytes()),
+        TypeKind::Int32 => buf.extend_from_slice(&END_OF_VECTOR_INT_32.to_le_bytes()),
+        other => panic!("Expected an integer type, got {:?}", other),
+    }
+}
+
+/// Writes a single integer with its type descriptor, using the smallest possible width.
+pub(crate) fn typed_int(value: i32, buf: &mut Vec<u8>) {
+    typed_ints(&[value], buf);
+}
+
+/// Writes a vector of integers with a type descriptor, using the smallest possible width.
+/// An empty vector is written as a missing value.
+pub(crate) fn typed_ints(values: &[i32], buf: &mut Vec<u8>) {
+    if values.is_empty() {
+        type_descriptor(TypeKind::Missing, 0, buf);
+        return;
+    }
+    let kind = int_kind(values);
+    type_descriptor(kind, values.len(), buf);
+    values.iter().for_each(|&v| int(kind, v, buf));
+}
+
+/// Writes a vector of floats with a type descriptor.
+pub(crate) fn typed_floats(values: &[f32], buf: &mut Vec<u8>) {
+    type_descriptor(TypeKind::Float32, values.len(), buf);
+    values
+        .iter()
+        .for_each(|v| buf.extend_from_slice(&v.to_le_bytes()));
+}
+
+/// Writes a "typed string", i.e. a type descriptor followed by the characters/bytes.
+pub(crate) fn typed_string(value: &[u8], buf: &mut Vec<u8>) {
+    type_descriptor(TypeKind::String, value.len(), buf);
+    buf.extend_from_slice(value);
+}
+
+/// Writes a `TypedVec` with its type descriptor.
+pub(crate) fn typed_vec(value: &TypedVec, buf: &mut Vec<u8>) {
+    match value {
+        TypedVec::Missing => type_descriptor(TypeKind::Missing, 0, buf),
+        TypedVec::Int32(v) => typed_ints(v, buf),
+        TypedVec::Float32(v) => typed_floats(v, buf),
+        TypedVec::UString(v) => typed_string(v, buf),
+    }
+}
+
+/// Writes an INFO key/value pair, where the key is given as offset into the header dictionary.
+pub(crate) fn info_pair(offset: usize, value: &TypedVec, buf: &mut Vec<u8>) {
+    typed_int(offset as i32, buf);
+    typed_vec(value, buf);
+}
+
+/// Writes a FORMAT field for all samples, where the key is given as offset into the header
+/// dictionary. All samples share a single type descriptor, hence shorter values are padded
+/// (with end-of-vector values for numbers and NUL bytes for strings).
+pub(crate) fn genotype_field(
+    offset: usize,
+    samples: &[TypedVec],
+    buf: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    typed_int(offset as i32, buf);
+    let kinds = samples
+        .iter()
+        .filter(|v| **v != TypedVec::Missing)
+        .map(std::mem::discriminant)
+        .collect::<std::collections::HashSet<_>>();
+    if kinds.len() > 1 {
+        bail!("FORMAT values must have the same type for all samples");
+    }
+    match samples.iter().find(|v| **v != TypedVec::Missing) {
+        None => type_descriptor(TypeKind::Missing, 0, buf),
+        Some(TypedVec::Int32(_)) => {
+            let values = samples
+                .iter()
+                .map(|v| match v {
+                    TypedVec::Int32(v) => v.as_slice(),
+                    _ => &[MISSING_INTEGER],
+                })
+                .collect::<Vec<_>>();
+            let width = values.iter().map(|v| v.len()).max().unwrap_or(0);
+            let kind = int_kind(&values.concat());
+            type_descriptor(kind, width, buf);
+            for v in values {
+                v.iter().for_each(|&v| int(kind, v, buf));
+                (v.len()..width).for_each(|_| int_end_of_vector(kind, buf));
+            }
+        }
+        Some(TypedVec::Float32(_)) => {
+            let missing = [f32::from_bits(MISSING_FLOAT)];
+            let values = samples
+                .iter()
+                .map(|v| match v {
+                    TypedVec::Float32(v) => v.as_slice(),
+                    _ => &missing,
+                })
+                .collect::<Vec<_>>();
+            let width = values.iter().map(|v| v.len()).max().unwrap_or(0);
+            type_descriptor(TypeKind::Float32, width, buf);
+            for v in values {
+                v.iter()
+                    .for_each(|v| buf.extend_from_slice(&v.to_le_bytes()));
+                (v.len()..width)
+                    .for_each(|_| buf.extend_from_slice(&END_OF_VECTOR_FLOAT_32.to_le_bytes()));
+            }
+        }
+        Some(TypedVec::UString(_)) => {
+            let values = samples
+                .iter()
+                .map(|v| match v {
+                    TypedVec::UString(v) => v.as_slice(),
+                    _ => &[],
+                })
+                .collect::<Vec<_>>();
+            // like htslib, reserve space for at least one terminating NUL byte
+            let width = values.iter().map(|v| v.len()).max().unwrap_or(0) + 1;
+            type_descriptor(TypeKind::String, width, buf);
+            for v in values {
+                buf.extend_from_slice(v);
+                buf.resize(buf.len() + width - v.len(), 0);
+            }
+        }
+        Some(TypedVec::Missing) => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Formats a structured header line such as `##INFO=<ID=...,...>` from the given
+/// `(key, value, quoted)` fields. Fields are ordered and quoted as given by `layout`
+/// (i.e. as in the original line); fields not in `layout` follow in the given order.
+/// Note that the parser keeps escape sequences in quoted values as-is, hence these need
+/// not be escaped again.
+fn structured_line(key: &str, fields: Vec<(&str, String, bool)>, layout: &Layout) -> String {
+    let (mut known, rest): (Vec<_>, Vec<_>) = fields
+        .into_iter()
+        .partition(|(k, _, _)| layout.iter().any(|(l, _)| l == k));
+    let mut ordered = Vec::with_capacity(known.len() + rest.len());
+    for (k, quoted) in layout {
+        if let Some(i) = known.iter().position(|(key, _, _)| key == k) {
+            let (key, value, _) = known.remove(i);
+            ordered.push((key, value, *quoted));
+        }
+    }
+    ordered.extend(rest);
+    let fields = ordered
+        .into_iter()
+        .map(|(k, v, quoted)| {
+            if quoted {
+                format!("{}=\"{}\"", k, v)
+            } else {
+                format!("{}={}", k, v)
+            }
+        })
+        .join(",");
+    format!("##{}=<{}>", key, fields)
+}
+
+/// Whether a value which was not part of the original header needs to be quoted.
+fn needs_quotes(value: &str) -> bool {
+    value.contains([',', '<', '>', '=', ' ', '"'])
+}
+
+/// Returns the additional `key=value` pairs of a structured header line, sorted by key
+/// (their original order is restored by `structured_line`).
+fn additional_fields(additional: &HashMap<String, String>) -> Vec<(&str, String, bool)> {
+    additional
+        .iter()
+        .filter(|(k, _)| *k != "IDX")
+        .sorted()
+        .map(|(k, v)| (k.as_str(), v.clone(), needs_quotes(v)))
+        .collect()
+}
+
+/// Returns the VCF text representation of the given header, see `Header::to_vcf_text`.
+pub(crate) fn vcf_header_text(header: &Header) -> String {
+    header_text(header, false)
+}
+
+/// Returns the text representation of the given header, as stored in a BCF file.
+/// In contrast to `vcf_header_text`, all structured lines are written with their IDX, such
+/// that dictionary offsets are preserved, and the `PASS` filter is always defined.
+pub(crate) fn bcf_header_text(header: &Header) -> String {
+    header_text(header, true)
+}
+
+fn header_text(header: &Header, bcf: bool) -> String {
+    let has_idx = |layout: &Layout| layout.iter().any(|(k, _)| k == "IDX");
+    // IDX is either given for all FILTER, INFO and FORMAT lines or none of them,
+    // otherwise reading the header again would yield different offsets
+    let dictionary_idx = bcf
+        || header.filter.values().any(|f| has_idx(&f.layout))
+        || header.info.values().any(|i| has_idx(&i.layout))
+        || header.format.values().any(|f| has_idx(&f.layout));
+    let contig_idx = bcf || header.contigs.iter().any(|c| has_idx(&c.layout));
+
+    let mut lines = Vec::new();
+    let mut meta_counts: HashMap<&str, usize> = HashMap::new();
+    for line in &header.lines {
+        lines.push(match line {
+            HeaderLine::Meta(key) => {
+                let n = meta_counts.entry(key).or_insert(0);
+                let value = match &header.meta.get_vec(key).unwrap()[*n] {
+                    HeaderValue::String(value) => value,
+                    other => panic!("Unexpected generic header value {:?}", other),
+                };
+                *n += 1;
+                format!("##{}={}", key, value)
+            }
+            HeaderLine::Filter(id) => {
+                let filter = header.filter_by_id(id).unwrap();
+                let mut fields = vec![
+                    ("ID", filter.id.clone(), false),
+                    ("Description", filter.description().clone(), true),
+                ];
+                fields.extend(additional_fields(filter.additional()));
+                if dictionary_idx {
+                    fields.push(("IDX", filter.idx.to_string(), false));
+                }
+                structured_line("FILTER", fields, &filter.layout)
+            }
+            HeaderLine::Info(id) => {
+                let info = &header.info[&header.info_tag_to_offset[id]];
+                let mut fields = vec![
+                    ("ID", info.id.clone(), false),
+                    ("Number", info.number().to_string(), false),
+                    ("Type", info.kind().to_string(), false),
+                    ("Description", info.description().clone(), true),
+                ];
+                if !info.source().is_empty() {
+                    fields.push(("Source", info.source().clone(), true));
+                }
+                if !info.version().is_empty() {
+                    fields.push(("Version", info.version().clone(), true));
+                }
+                fields.extend(additional_fields(info.additional()));
+                if dictionary_idx {
+                    fields.push(("IDX", info.idx.to_string(), false));
+                }
+                structured_line("INFO", fields, &info.layout)
+            }
+            HeaderLine::Format(id) => {
+                let format = &header.format[&header.format_tag_to_offset[id]];
+                let mut fields = vec![
+                    ("ID", format.id.clone(), false),
+                    ("Number", format.number().to_string(), false),
+                    ("Type", format.kind().to_string(), false),
+                    ("Description", format.description().clone(), true),
+                ];
+                fields.extend(additional_fields(format.additional()));
+                if dictionary_idx {
+                    fields.push(("IDX", format.idx.to_string(), false));
+                }
+                structured_line("FORMAT", fields, &format.layout)
+            }
+            HeaderLine::Contig(id) => {
+                let (idx, contig) = header
+                    .contigs
+                    .iter()
+                    .find_position(|contig| contig.id == *id)
+                    .unwrap();
+                let mut fields = vec![("ID", contig.id.clone(), false)];
+                if let Some(length) = contig.length() {
+                    fields.push(("length", length.to_string(), false));
+                }
+                fields.extend(additional_fields(contig.additional()));
+                // records refer to contigs by their position in the header
+                if contig_idx {
+                    fields.push(("IDX", idx.to_string(), false));
+                }
+                structured_line("contig", fields, &contig.layout)
+            }
+        });
+    }
+    if bcf && !header.filter.values().any(|f| f.id == PASS_FILTER) {
+        let position = header
+            .lines
+            .iter()
+            .position(|line| !matches!(line, HeaderLine::Meta(_)))
+            .unwrap_or(lines.len());
+        lines.insert(
+            position,
+            format!(
+                "##FILTER=<ID={},Description=\"All filters passed\",IDX=0>",
+                PASS_FILTER
+            ),
+        );
+    }
+    let mut columns = vec![
+        "#CHROM", "POS", "ID", "REF", "ALT", "QUAL", "FILTER", "INFO",
+    ];
+    if !header.samples.is_empty() {
+        columns.push("FORMAT");
+        columns.extend(header.samples.iter().map(String::as_str));
+    }
+    lines.push(columns.join("\t"));
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
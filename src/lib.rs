@@ -1,16 +1,57 @@
+pub mod alias;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "bgen")]
+pub mod bgen;
+pub mod bgzf;
+pub mod builder;
+pub mod convert;
+pub(crate) mod encoder;
+#[cfg(feature = "ndarray")]
+pub mod matrix;
+#[cfg(feature = "parquet")]
+pub mod parquet;
 pub(crate) mod parser;
+pub mod plink;
 pub mod reader;
 pub mod record;
+pub mod region;
+#[cfg(feature = "serde")]
+pub(crate) mod serialization;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod tabix;
 pub mod types;
+pub mod variant;
+pub mod vcf;
+pub mod writer;
+#[cfg(feature = "zarr")]
+pub mod zarr;
 
 pub use reader::BcfRecords;
 pub use record::BcfRecord;
 pub use record::Record;
+pub use variant::VariantRecord;
+pub use vcf::{VcfRecord, VcfRecords};
+pub use writer::{BcfWriter, VcfWriter};
 
 #[cfg(test)]
 mod test {
+    use std::io::Read;
+
     use super::reader::BcfRecords;
-    use crate::record::Record;
+    use super::vcf::VcfRecords;
+    use crate::bgzf::BgzfReader;
+    use crate::builder::{HeaderBuilder, RecordBuilder};
+    use crate::record::{BcfRecord, GenotypeAllele, Record};
+    use crate::region::{Interval, Position, Region};
+    use crate::tabix::TabixIndex;
+    use crate::types::{
+        HeaderContig, HeaderFilter, HeaderFormat, HeaderInfo, InfoNumber, InfoType, TypedVec,
+        MISSING_INTEGER,
+    };
+    use crate::variant::VariantRecord;
+    use crate::writer::{BcfWriter, Compression, VcfWriter};
 
     #[test]
     fn test_samples() {
@@ -192,4 +233,2235 @@ mod test {
             assert_eq!(values[0], 1);
         });
     }
+
+    #[test]
+    fn test_filters_pass() {
+        let mut records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        if let Some(record) = records.next() {
+            assert_eq!(record.filters(), ["PASS"]);
+            assert!(record.is_pass());
+            assert!(record.has_filter("PASS"));
+            assert!(!record.has_filter("LowQual"));
+            let filters = record.filter_headers();
+            assert_eq!(filters.len(), 1);
+            assert_eq!(filters[0].description(), "All filters passed");
+        }
+    }
+
+    #[test]
+    fn test_filters_missing() {
+        let mut records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        if let Some(record) = records.next() {
+            // replace the encoded PASS filter (`0x11 0x00`) following the alleles by a missing value
+            let pass = b"\x17A\x11\x00";
+            let start = record
+                .shared
+                .windows(pass.len())
+                .position(|w| w == pass)
+                .unwrap();
+            let mut shared = record.shared.clone();
+            shared.splice(start + 2..start + 4, [0x00]);
+            let record = BcfRecord::new(shared, record.format.clone(), record.header.clone());
+            assert!(record.filters().is_empty());
+            assert!(!record.is_pass());
+            assert!(!record.has_filter("PASS"));
+            assert!(record.filter_headers().is_empty());
+            assert_eq!(record.info(b"INT").unwrap().integer(), [1]);
+        }
+    }
+
+    #[test]
+    fn test_filters_undefined() {
+        let mut records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        if let Some(record) = records.next() {
+            // replace the encoded PASS filter by offset 99, which the header does not define
+            let pass = b"\x17A\x11\x00";
+            let start = record
+                .shared
+                .windows(pass.len())
+                .position(|w| w == pass)
+                .unwrap();
+            let mut shared = record.shared.clone();
+            shared[start + 3] = 99;
+            let record = BcfRecord::new(shared, record.format.clone(), record.header.clone());
+            assert_eq!(
+                record.try_filters().unwrap_err().to_string(),
+                "FILTER offset 99 is not defined in the header"
+            );
+            assert!(!record.is_pass());
+            assert!(!record.has_filter("PASS"));
+            assert!(record.filter_headers().is_empty());
+            assert!(VariantRecord::try_from_record(&record).is_err());
+            let mut writer =
+                VcfWriter::new(Vec::new(), record.header(), Compression::Uncompressed).unwrap();
+            assert!(writer.write(&record).is_err());
+        }
+    }
+
+    #[test]
+    fn test_info_format_tags_undefined() {
+        let record = BcfRecords::from_path("resources/types.bcf")
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut header = record.header.as_ref().clone();
+        let info = header.info_tag_to_offset.remove("FLAG").unwrap();
+        header.info.remove(&info);
+        let format = header.format_tag_to_offset.remove("INT").unwrap();
+        header.format.remove(&format);
+        let record = BcfRecord::new(record.shared.clone(), record.format.clone(), header.into());
+        // fields whose offsets are not defined in the header are skipped
+        assert_eq!(record.info_tags()[..2], ["INT", "FLOAT"]);
+        assert!(!record.format_tags().contains(&"INT"));
+        assert!(!record.format_tags().is_empty());
+    }
+
+    #[test]
+    fn test_interval_saturates() {
+        let record = BcfRecords::from_path("resources/types.bcf")
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut shared = record.shared.clone();
+        shared[4..8].copy_from_slice(&(u32::MAX - 1).to_le_bytes());
+        shared[8..12].copy_from_slice(&5i32.to_le_bytes());
+        let record = BcfRecord::new(shared, record.format.clone(), record.header.clone());
+        assert_eq!(
+            record.interval(),
+            Interval::from_zero_based(u32::MAX - 1, u32::MAX)
+        );
+    }
+
+    #[test]
+    fn test_header_filter_by_id() {
+        let records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+        let header = records.header();
+        let filter = header.filter_by_id("GQlessthan70").unwrap();
+        assert_eq!(*filter.idx(), 1);
+        assert_eq!(
+            filter.description(),
+            "Sum of GQ for datasets with this genotype less than 70"
+        );
+        assert!(header.filter_by_id("LowQual").is_none());
+    }
+
+    #[test]
+    fn test_contigs() {
+        let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+        let header = records.header().clone();
+        let idx = header.contig_index("chr2").unwrap();
+        assert_eq!(header.contig(idx).unwrap().id(), "chr2");
+        assert!(header.contig_index("chrUn").is_none());
+        assert!(header.contig(header.contigs().len()).is_none());
+        for record in records {
+            assert_eq!(
+                header.contig(record.contig_index()).unwrap().id(),
+                record.chrom()
+            );
+            assert_eq!(record.contig().unwrap().id(), record.chrom());
+        }
+
+        // records with an undefined contig index are errors for exporters, not panics
+        let record = BcfRecords::from_path("resources/types.bcf")
+            .unwrap()
+            .next()
+            .unwrap();
+        let mut shared = record.shared.clone();
+        shared[..4].copy_from_slice(&99i32.to_le_bytes());
+        let record = BcfRecord::new(shared, record.format.clone(), record.header.clone());
+        assert!(record.contig().is_none());
+        assert_eq!(
+            record.try_chrom().unwrap_err().to_string(),
+            "contig index 99 is not defined in the header"
+        );
+        let mut writer =
+            VcfWriter::new(Vec::new(), record.header(), Compression::Uncompressed).unwrap();
+        assert!(writer.write(&record).is_err());
+
+        let mut contig = HeaderContig::new("chr1", None);
+        contig.set_length(Some(1000)).set_assembly("b37");
+        contig.set_attribute("md5", "f126cdf8a6e0c7f379d618ff66beb2da");
+        assert_eq!(contig.assembly(), Some("b37"));
+        assert_eq!(
+            contig.attribute("md5"),
+            Some("f126cdf8a6e0c7f379d618ff66beb2da")
+        );
+        assert!(contig.attribute("species").is_none());
+        let mut header = HeaderBuilder::new()
+            .info(
+                "END",
+                InfoNumber::Count(1),
+                InfoType::Integer,
+                "End position",
+            )
+            .build()
+            .unwrap();
+        header.add_contig(contig);
+        assert!(header.to_vcf_text().contains(
+            "##contig=<ID=chr1,length=1000,assembly=b37,md5=f126cdf8a6e0c7f379d618ff66beb2da>"
+        ));
+
+        let build = |pos: u32, ref_allele: &str, end: Option<i32>| {
+            let builder = RecordBuilder::new(header.clone().into())
+                .chrom("chr1")
+                .pos(pos)
+                .alleles([ref_allele, "<DEL>"]);
+            match end {
+                Some(end) => builder.info_i32("END", &[end]),
+                None => builder,
+            }
+            .build()
+        };
+        assert!(build(998, "AC", None).is_ok());
+        assert!(build(10, "A", Some(1000)).is_ok());
+        // the telomere at position length + 1
+        assert!(build(1000, "N", None).is_ok());
+        assert_eq!(
+            build(1001, "A", None).unwrap_err().to_string(),
+            "POS 1002 exceeds the length 1000 of contig chr1"
+        );
+        assert_eq!(
+            build(999, "AC", None).unwrap_err().to_string(),
+            "END 1001 exceeds the length 1000 of contig chr1"
+        );
+        assert!(build(10, "A", Some(1001)).is_err());
+    }
+
+    #[test]
+    fn test_contig_aliases() {
+        use crate::alias::ContigAliases;
+        use std::sync::Arc;
+
+        let mut aliases = ContigAliases::default();
+        assert!(aliases.is_alias("1", "chr1"));
+        assert!(aliases.is_alias("chrMT", "M"));
+        assert!(aliases.is_alias("NC_012920.1", "chrM"));
+        assert!(!aliases.is_alias("1", "chr2"));
+        assert!(!aliases.is_alias("chrUn", "chr1"));
+        aliases
+            .add_from_reader(
+                &b"# ucsc\tassembly\tgenbank\n\
+                chr1_KI270706v1_random\tHSCHR1_RANDOM_CTG1\tKI270706.1\n"[..],
+            )
+            .unwrap();
+        assert!(aliases.is_alias("KI270706.1", "chr1_KI270706v1_random"));
+        assert!(aliases.is_alias("HSCHR1_RANDOM_CTG1", "KI270706.1"));
+        assert!(!ContigAliases::empty().is_alias("1", "chr1"));
+        assert_eq!(aliases.find("2", ["1", "chr2", "2"]), Some("2"));
+        assert_eq!(aliases.find("2", ["1", "chr2"]), Some("chr2"));
+
+        let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+        let header = records.header().clone();
+        assert_eq!(header.resolve_contig("chrX"), header.contig_index("chrX"));
+        assert_eq!(header.resolve_contig("X"), header.contig_index("chrX"));
+        assert!(header.resolve_contig("chrUn").is_none());
+        let mut strict = header.clone();
+        strict.set_aliases(ContigAliases::empty());
+        assert!(strict.resolve_contig("X").is_none());
+
+        // renaming rewrites the contig dictionary, records are unaffected
+        let mut renamed = header.clone();
+        renamed
+            .rename_contigs(|id| match id {
+                "chrM" => Some("MT".into()),
+                _ => id.strip_prefix("chr").map(String::from),
+            })
+            .unwrap();
+        assert!(renamed.same_contigs(&header));
+        assert!(!strict.same_contigs(&renamed));
+        assert!(renamed
+            .to_vcf_text()
+            .contains("##contig=<ID=1,length=248956422"));
+        assert!(!renamed.to_vcf_text().contains("ID=chr1,"));
+        let renamed = Arc::new(renamed);
+        for record in records.take(10) {
+            let moved = BcfRecord::new(
+                record.shared.clone(),
+                record.format.clone(),
+                renamed.clone(),
+            );
+            assert_eq!(moved.chrom(), record.chrom().strip_prefix("chr").unwrap());
+            assert_eq!(moved.pos(), record.pos());
+        }
+
+        let mut header = header;
+        assert!(header.rename_contig("chr1", "chr2").is_err());
+        assert!(header.rename_contig("chrUn", "Un").is_err());
+        header.rename_contig("chr1", "1").unwrap();
+        assert_eq!(header.contigs()[0].id(), "1");
+        assert_eq!(
+            header
+                .rename_contigs(|id| match id {
+                    "chr2" => Some("1".into()),
+                    _ => None,
+                })
+                .unwrap_err()
+                .to_string(),
+            "contig 1 would not be unique"
+        );
+        assert_eq!(header.contigs()[1].id(), "chr2");
+    }
+
+    #[test]
+    fn test_regions() {
+        let parse = |region: &str| region.parse::<Region>();
+        let interval = |region: &str| *parse(region).unwrap().interval();
+        assert_eq!(parse("chr1").unwrap(), Region::whole("chr1"));
+        assert_eq!(
+            interval(" chr1:1,000-2,000 "),
+            Interval::from_zero_based(999, 2000)
+        );
+        assert_eq!(
+            interval("chr1:1000-1000"),
+            Interval::from_zero_based(999, 1000)
+        );
+        assert_eq!(
+            interval("chr1:5000-"),
+            Interval::new(Position::from_zero_based(4999), Position::MAX)
+        );
+        assert_eq!(interval("chr1:5000"), interval("chr1:5000-"));
+        assert_eq!(interval("chr1:-2000"), Interval::from_zero_based(0, 2000));
+        assert_eq!(parse("HLA-A*01:01N").unwrap().contig(), "HLA-A*01:01N");
+        assert_eq!(parse("chr1:1-2:3-4").unwrap().contig(), "chr1:1-2");
+        for invalid in &[
+            "",
+            "chr1:0-10",
+            "chr1:2000-1000",
+            ":1-2",
+            "chr1:1-99999999999",
+        ] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+        for region in &[
+            "chr1",
+            "chr1:5000-",
+            "chr1:1-2000",
+            "chrUn_KI270302v1:10-20",
+        ] {
+            assert_eq!(parse(region).unwrap().to_string(), *region);
+        }
+        assert!(parse("chr1:100-200")
+            .unwrap()
+            .overlaps(&parse("chr1:200-").unwrap()));
+        assert!(!parse("chr1:100-200")
+            .unwrap()
+            .overlaps(&parse("chr2:100-200").unwrap()));
+
+        let mut intervals = [
+            Interval::from_zero_based(10, 20),
+            Interval::from_zero_based(5, 30),
+            Interval::from_zero_based(10, 15),
+        ];
+        intervals.sort();
+        assert_eq!(intervals[0].start(), Position::from_zero_based(5));
+        assert_eq!(intervals[1], Interval::from_zero_based(10, 15));
+        let empty = Interval::from_zero_based(10, 10);
+        assert!(empty.is_empty() && !empty.overlaps(&intervals[0]));
+        assert!(intervals[2]
+            .intersection(&Interval::from_zero_based(20, 30))
+            .is_none());
+        assert_eq!(Interval::from_one_based(10, 9), None);
+        assert_eq!(Position::from_zero_based(0).one_based(), 1);
+
+        // record spans, from the encoded length and from INFO END
+        for record in BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap() {
+            let variant = VariantRecord::from(&record);
+            let span = record.interval();
+            assert_eq!(span.start(), record.position());
+            assert_eq!(span.len(), record.ref_allele().len() as u32);
+            assert_eq!(
+                variant
+                    .to_bcf_record(record.header().clone())
+                    .unwrap()
+                    .interval(),
+                span
+            );
+        }
+        let header = HeaderBuilder::new()
+            .contig("chr1", None)
+            .info(
+                "END",
+                InfoNumber::Count(1),
+                InfoType::Integer,
+                "End position",
+            )
+            .build()
+            .unwrap();
+        let record = RecordBuilder::new(header.into())
+            .chrom("chr1")
+            .pos(999)
+            .alleles(["N", "<DEL>"])
+            .info_i32("END", &[2000])
+            .build()
+            .unwrap();
+        assert_eq!(
+            record.interval(),
+            Interval::from_one_based(1000, 2000).unwrap()
+        );
+        let mut writer =
+            VcfWriter::new(Vec::new(), record.header(), Compression::Uncompressed).unwrap();
+        writer.write(&record).unwrap();
+        let text = writer.finish().unwrap();
+        let vcf_record = VcfRecords::new(&text[..]).unwrap().next().unwrap();
+        assert_eq!(vcf_record.interval(), record.interval());
+    }
+
+    #[test]
+    fn test_records_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<BcfRecord>();
+        assert_send_sync::<crate::VcfRecord>();
+        assert_send_sync::<crate::types::Header>();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let reader = std::thread::spawn(move || {
+            for record in BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap() {
+                sender.send(record).unwrap();
+            }
+        });
+        let records = receiver.into_iter().collect::<Vec<_>>();
+        reader.join().unwrap();
+        assert!(records.len() > 1);
+        // all records share the same header
+        assert!(records
+            .iter()
+            .all(|record| std::sync::Arc::ptr_eq(record.header(), records[0].header())));
+
+        let positions = std::thread::scope(|scope| {
+            let (first, second) = records.split_at(records.len() / 2);
+            let positions =
+                |records: &[BcfRecord]| records.iter().map(|r| r.pos()).collect::<Vec<_>>();
+            let first = scope.spawn(move || positions(first));
+            let second = scope.spawn(move || positions(second));
+            [first.join().unwrap(), second.join().unwrap()].concat()
+        });
+        assert_eq!(
+            positions,
+            records.iter().map(|r| r.pos()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_variant_record_roundtrip() {
+        for path in &["resources/types.bcf", "resources/example.uncompressed.bcf"] {
+            let records = BcfRecords::from_path(path).unwrap();
+            for record in records {
+                let variant = VariantRecord::from(&record);
+                let encoded = variant.to_bcf_record(record.header.clone()).unwrap();
+                assert_eq!(encoded.shared, record.shared);
+                assert_eq!(encoded.format, record.format);
+            }
+        }
+    }
+
+    #[test]
+    fn test_variant_record_modify() {
+        let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+        let record = records.next().unwrap();
+        let mut variant = VariantRecord::from(&record);
+        assert_eq!(variant.chrom(), "chr1");
+        assert_eq!(variant.alleles(), &[b"G".to_vec(), b"A".to_vec()]);
+
+        variant.set_id(b"rs1".to_vec());
+        variant.set_qual(None);
+        variant.add_filter("GQlessthan70");
+        variant.add_filter("cgonly");
+        variant
+            .info_mut()
+            .insert("DPSum".into(), TypedVec::Int32(vec![40000]));
+        variant.info_mut().shift_remove("platforms");
+        variant.set_genotypes(&[
+            vec![GenotypeAllele::Unphased(0), GenotypeAllele::Phased(1)],
+            vec![
+                GenotypeAllele::UnphasedMissing,
+                GenotypeAllele::PhasedMissing,
+            ],
+            vec![GenotypeAllele::Unphased(1)],
+        ]);
+        variant.format_mut().insert(
+            "DP".into(),
+            vec![
+                TypedVec::Missing,
+                TypedVec::Int32(vec![7]),
+                TypedVec::Missing,
+            ],
+        );
+
+        let encoded = variant.to_bcf_record(record.header.clone()).unwrap();
+        assert_eq!(encoded.id(), b"rs1");
+        assert_eq!(encoded.qual(), None);
+        assert_eq!(encoded.filters(), ["GQlessthan70", "cgonly"]);
+        assert_eq!(encoded.info(b"DPSum").unwrap().integer(), [40000]);
+        assert!(encoded.info(b"platforms").is_none());
+        assert_eq!(encoded.genotypes(), variant.genotypes(),);
+        let dp = encoded.format(b"DP").unwrap();
+        assert_eq!(dp[0].integer(), [MISSING_INTEGER]);
+        assert_eq!(dp[1].integer(), [7]);
+        let reencoded = VariantRecord::from(&encoded)
+            .to_bcf_record(record.header.clone())
+            .unwrap();
+        assert_eq!(reencoded.shared, encoded.shared);
+        assert_eq!(reencoded.format, encoded.format);
+
+        variant.add_filter("PASS");
+        assert_eq!(variant.filters(), &["PASS"]);
+    }
+
+    #[test]
+    fn test_variant_record_encode_end_and_gt_order() {
+        let header = HeaderBuilder::new()
+            .contig("chr1", None)
+            .info("END", InfoNumber::Count(1), InfoType::Integer, "End")
+            .format("GT", InfoNumber::Count(1), InfoType::String, "Genotype")
+            .format("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .sample("S1")
+            .build()
+            .unwrap();
+        let mut variant = VariantRecord::new("chr1", 9, vec![b"AC".to_vec(), b"A".to_vec()]);
+        variant
+            .format_mut()
+            .insert("DP".into(), vec![TypedVec::Int32(vec![3])]);
+        variant
+            .format_mut()
+            .insert("GT".into(), vec![TypedVec::Int32(vec![2, 4])]);
+        for (end, interval) in [(MISSING_INTEGER, (9, 11)), (5, (9, 11)), (20, (9, 20))] {
+            variant
+                .info_mut()
+                .insert("END".into(), TypedVec::Int32(vec![end]));
+            let encoded = variant.to_bcf_record(header.clone().into()).unwrap();
+            assert_eq!(
+                encoded.interval(),
+                Interval::from_zero_based(interval.0, interval.1)
+            );
+            assert_eq!(encoded.format_tags(), ["GT", "DP"]);
+        }
+    }
+
+    #[test]
+    fn test_variant_record_select_samples() {
+        let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+        let record = records.next().unwrap();
+        let mut variant = VariantRecord::from(&record);
+        let error = variant.select_samples(&[1, 3]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "sample index 3 is out of range, FORMAT GT has 3 samples"
+        );
+        variant.select_samples(&[1]).unwrap();
+        assert_eq!(
+            variant.format()["DP"],
+            [record.format(b"DP").unwrap()[1].clone()]
+        );
+        // the header still declares three samples
+        assert!(variant.to_bcf_record(record.header.clone()).is_err());
+
+        let mut header = record.header.as_ref().clone();
+        header.samples = vec!["INTEGRATION".into()];
+        let encoded = variant.to_bcf_record(header.into()).unwrap();
+        assert_eq!(encoded.format(b"DP").unwrap()[0].integer(), [1301]);
+        assert_eq!(encoded.genotypes(), [record.genotypes()[1].clone()]);
+    }
+
+    #[test]
+    fn test_record_builder() {
+        let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        let header = records.header().clone();
+        let record = RecordBuilder::new(header.into())
+            .chrom("chr1")
+            .pos(0)
+            .id(b"rs42")
+            .alleles(["A", "C", "G"])
+            .info_flag("FLAG")
+            .info_i32("INTA", &[1, 2])
+            .info_f32("FLOATR", &[0.5, 0.25, 0.125])
+            .info_string("STRING2", ["a", "b"])
+            .genotypes(&[vec![
+                GenotypeAllele::Unphased(1),
+                GenotypeAllele::Unphased(2),
+            ]])
+            .format_i32("INTG", [[1, 2, 3, 4, 5, 6]])
+            .format_string("STR", ["x"])
+            .build()
+            .unwrap();
+        assert_eq!(record.id(), b"rs42");
+        assert_eq!(record.alt_alleles(), [b"C", b"G"]);
+        assert_eq!(record.qual(), None);
+        assert!(record.filters().is_empty());
+        assert!(record.has_flag(b"FLAG"));
+        assert_eq!(record.info(b"INTA").unwrap().integer(), [1, 2]);
+        assert_eq!(record.info(b"FLOATR").unwrap().float(), [0.5, 0.25, 0.125]);
+        assert_eq!(record.info(b"STRING2").unwrap().string(), [b"a", b"b"]);
+        assert_eq!(
+            record.format(b"INTG").unwrap()[0].integer(),
+            [1, 2, 3, 4, 5, 6]
+        );
+        assert_eq!(record.format(b"STR").unwrap()[0].string(), [b"x"]);
+        assert_eq!(record.format_tags(), ["GT", "INTG", "STR"]);
+    }
+
+    #[test]
+    fn test_record_builder_validation() {
+        let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        let header = records.header().clone();
+        let builder = RecordBuilder::new(header.into())
+            .chrom("chr1")
+            .pos(0)
+            .alleles(["A", "C"]);
+        assert!(builder.clone().build().is_ok());
+        assert!(builder.clone().chrom("chr2").build().is_err());
+        assert!(builder.clone().filter("LowQual").build().is_err());
+        assert!(builder.clone().info_i32("UNDEFINED", &[1]).build().is_err());
+        assert!(builder.clone().info_f32("INT", &[1.0]).build().is_err());
+        assert!(builder.clone().info_i32("INT2", &[1]).build().is_err());
+        assert!(builder.clone().info_i32("INTR", &[1]).build().is_err());
+        assert!(builder.clone().info_i32("INTX", &[1, 2, 3]).build().is_ok());
+        assert!(builder
+            .clone()
+            .format_i32("INT", [[1], [2]])
+            .build()
+            .is_err());
+        assert!(builder
+            .clone()
+            .format_i32("INTG", [[1, 2]])
+            .build()
+            .is_err());
+        assert!(builder.format_i32("INTG", [[1, 2, 3]]).build().is_ok());
+    }
+
+    fn assert_written_records_equal(path: &str, compression: Compression) {
+        let records = BcfRecords::from_path(path).unwrap();
+        let header = records.header().clone();
+        let mut writer = BcfWriter::new(Vec::new(), &header, compression).unwrap();
+        let expected = records
+            .map(|record| {
+                writer.write(&record).unwrap();
+                (record.shared, record.format)
+            })
+            .collect::<Vec<_>>();
+        let output = writer.finish().unwrap();
+
+        let (reader, _format) = niffler::get_reader(Box::new(&output[..])).unwrap();
+        let records = BcfRecords::new(reader).unwrap();
+        assert_eq!(records.header().samples, header.samples);
+        assert_eq!(
+            records.header().info_tag_to_offset,
+            header.info_tag_to_offset
+        );
+        assert_eq!(
+            records.header().format_tag_to_offset,
+            header.format_tag_to_offset
+        );
+        let actual = records
+            .map(|record| (record.shared, record.format))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_writer_uncompressed() {
+        assert_written_records_equal("resources/types.bcf", Compression::Uncompressed);
+        assert_written_records_equal(
+            "resources/example.uncompressed.bcf",
+            Compression::Uncompressed,
+        );
+    }
+
+    #[test]
+    fn test_writer_bgzf() {
+        assert_written_records_equal("resources/types.bcf", Compression::Bgzf);
+        assert_written_records_equal("resources/example.compressed.bcf", Compression::Bgzf);
+    }
+
+    #[test]
+    fn test_writer_bgzf_eof_marker() {
+        let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        let writer = BcfWriter::new(Vec::new(), records.header(), Compression::Bgzf).unwrap();
+        let output = writer.finish().unwrap();
+        assert_eq!(output[..4], [0x1f, 0x8b, 0x08, 0x04]);
+        assert_eq!(output[output.len() - 28..], crate::bgzf::EOF_MARKER);
+    }
+
+    #[test]
+    fn test_writer_variant_records() {
+        let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        let header = records.header().clone();
+        let mut writer = BcfWriter::new(Vec::new(), &header, Compression::Uncompressed).unwrap();
+        let mut variant = VariantRecord::new("chr1", 0, vec![b"A".to_vec(), b"C".to_vec()]);
+        // long enough to require the overflow length encoding, with values requiring 16 and 32 bits
+        variant.set_id(b"an_identifier_longer_than_15".to_vec());
+        variant.info_mut().insert(
+            "INTX".into(),
+            TypedVec::Int32((0..20).map(|i| i * 1000).collect()),
+        );
+        variant.info_mut().insert(
+            "INT2".into(),
+            TypedVec::Int32(vec![-100_000, MISSING_INTEGER]),
+        );
+        writer.write_variant(&variant).unwrap();
+        let output = writer.finish().unwrap();
+
+        let mut records = BcfRecords::new(&output[..]).unwrap();
+        let record = records.next().unwrap();
+        assert_eq!(record.id(), b"an_identifier_longer_than_15");
+        assert_eq!(
+            record.info(b"INTX").unwrap().integer(),
+            (0..20).map(|i| i * 1000).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            record.info(b"INT2").unwrap().integer(),
+            [-100_000, MISSING_INTEGER]
+        );
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn test_header_builder_dictionary() {
+        let header = HeaderBuilder::new()
+            .contig("chr1", None)
+            .filter("q10", "Quality below 10")
+            .info("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .info(
+                "AF",
+                InfoNumber::AlternateAlleles,
+                InfoType::Float,
+                "Allele frequency",
+            )
+            .format("GT", InfoNumber::Count(1), InfoType::String, "Genotype")
+            .format("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .build()
+            .unwrap();
+        assert_eq!(header.filter_tag_to_offset()["PASS"], 0);
+        assert_eq!(header.filter_tag_to_offset()["q10"], 1);
+        assert_eq!(header.info_tag_to_offset()["DP"], 2);
+        assert_eq!(header.info_tag_to_offset()["AF"], 3);
+        assert_eq!(header.format_tag_to_offset()["GT"], 4);
+        // the same ID shares its dictionary offset across INFO and FORMAT
+        assert_eq!(header.format_tag_to_offset()["DP"], 2);
+    }
+
+    #[test]
+    fn test_header_mutation() {
+        let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        let mut header = records.header().clone();
+        let offsets = header.info_tag_to_offset().clone();
+
+        assert!(header.remove_info("INT").is_some());
+        assert!(header.remove_info("INT").is_none());
+        assert!(!header.info_tag_to_offset().contains_key("INT"));
+        for (tag, offset) in &offsets {
+            if tag != "INT" {
+                assert_eq!(header.info_tag_to_offset()[tag], *offset);
+            }
+        }
+
+        let max = *header.format_tag_to_offset().values().max().unwrap();
+        header.add_info(HeaderInfo::new(
+            "NEW",
+            InfoNumber::Count(1),
+            InfoType::Integer,
+            "A new field",
+        ));
+        assert_eq!(header.info_tag_to_offset()["NEW"], max + 1);
+        header.add_filter(HeaderFilter::new("NEW", "Shares its offset"));
+        assert_eq!(header.filter_tag_to_offset()["NEW"], max + 1);
+        header.add_format(HeaderFormat::new(
+            "FLOAT",
+            InfoNumber::Count(1),
+            InfoType::Float,
+            "Shares its offset with INFO/FLOAT",
+        ));
+        assert_eq!(header.format_tag_to_offset()["FLOAT"], offsets["FLOAT"]);
+
+        header.add_sample("Other").unwrap();
+        assert!(header.add_sample("Other").is_err());
+        header.rename_sample("Sample", "First").unwrap();
+        assert!(header.rename_sample("Sample", "Second").is_err());
+        assert_eq!(header.remove_sample("Other"), Some(1));
+        assert_eq!(header.samples(), &["First"]);
+    }
+
+    #[test]
+    fn test_header_implicit_idx() {
+        let text = "##fileformat=VCFv4.2\n\
+            ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+            ##FILTER=<ID=q10,Description=\"Quality below 10\">\n\
+            ##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+            ##FORMAT=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+            ##contig=<ID=chr1>\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n\0";
+        let mut bcf = b"BCF\x02\x02".to_vec();
+        bcf.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        bcf.extend_from_slice(text.as_bytes());
+        let records = BcfRecords::new(&bcf[..]).unwrap();
+        let header = records.header();
+        assert_eq!(header.info_tag_to_offset()["DP"], 1);
+        assert_eq!(header.filter_tag_to_offset()["q10"], 2);
+        assert_eq!(header.format_tag_to_offset()["GT"], 3);
+        assert_eq!(header.format_tag_to_offset()["DP"], 1);
+    }
+
+    #[test]
+    fn test_plink_writer() {
+        use crate::plink::{Multiallelic, PlinkWriter};
+
+        let header = HeaderBuilder::new()
+            .contig("chr1", Some(1000))
+            .format("GT", InfoNumber::Count(1), InfoType::String, "Genotype")
+            .sample("S1")
+            .sample("S2")
+            .sample("S3")
+            .sample("S4")
+            .sample("S5")
+            .build()
+            .unwrap();
+        let biallelic = RecordBuilder::new(header.clone().into())
+            .chrom("chr1")
+            .pos(9)
+            .id(b"rs1")
+            .alleles(["A", "C"])
+            .genotypes(&[
+                vec![GenotypeAllele::Unphased(0), GenotypeAllele::Unphased(0)],
+                vec![GenotypeAllele::Unphased(0), GenotypeAllele::Phased(1)],
+                vec![GenotypeAllele::Unphased(1), GenotypeAllele::Unphased(1)],
+                vec![
+                    GenotypeAllele::UnphasedMissing,
+                    GenotypeAllele::UnphasedMissing,
+                ],
+                vec![GenotypeAllele::Unphased(1)],
+            ])
+            .build()
+            .unwrap();
+        let multiallelic = RecordBuilder::new(header.clone().into())
+            .chrom("chr1")
+            .pos(19)
+            .alleles(["A", "C", "G"])
+            .genotypes(&[
+                vec![GenotypeAllele::Unphased(1), GenotypeAllele::Unphased(2)],
+                vec![GenotypeAllele::Unphased(2), GenotypeAllele::Unphased(2)],
+                vec![GenotypeAllele::Unphased(0), GenotypeAllele::Unphased(1)],
+                vec![GenotypeAllele::Unphased(0), GenotypeAllele::UnphasedMissing],
+                vec![GenotypeAllele::Unphased(0)],
+            ])
+            .build()
+            .unwrap();
+
+        let write = |multiallelic_mode| {
+            let (mut bed, mut bim, mut fam) = (vec![], vec![], vec![]);
+            let mut writer =
+                PlinkWriter::new(&mut bed, &mut bim, &mut fam, &header, multiallelic_mode).unwrap();
+            writer.write(&biallelic).unwrap();
+            writer.write(&multiallelic).unwrap();
+            let skipped = writer.finish().unwrap();
+            (bed, String::from_utf8(bim).unwrap(), fam, skipped)
+        };
+
+        let (bed, bim, fam, skipped) = write(Multiallelic::Skip);
+        assert_eq!(skipped, ["chr1:20 skipped, 2 ALT alleles"]);
+        assert_eq!(bim, "chr1\trs1\t0\t10\tC\tA\n");
+        assert_eq!(fam.iter().filter(|&&b| b == b'\n').count(), 5);
+        // hom A2, het, hom A1, missing | haploid A1
+        assert_eq!(bed, [0x6c, 0x1b, 0x01, 0b01_00_10_11, 0b00]);
+
+        let (bed, bim, _, skipped) = write(Multiallelic::Split);
+        assert!(skipped.is_empty());
+        assert_eq!(
+            bim.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "chr1\tchr1:20:A:C\t0\t20\tC\tA",
+                "chr1\tchr1:20:A:G\t0\t20\tG\tA"
+            ]
+        );
+        assert_eq!(&bed[5..], [0b01_10_11_10, 0b11, 0b01_11_00_10, 0b11]);
+    }
+
+    #[test]
+    fn test_header_builder_write() {
+        let header = HeaderBuilder::new()
+            .contig("chr1", Some(1000))
+            .filter("q10", "Quality below 10")
+            .info("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .format("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .sample("S1")
+            .sample("S2")
+            .build()
+            .unwrap();
+        let record = RecordBuilder::new(header.clone().into())
+            .chrom("chr1")
+            .pos(99)
+            .alleles(["A", "T"])
+            .filter("q10")
+            .info_i32("DP", &[20])
+            .format_i32("DP", [vec![8], vec![12]])
+            .build()
+            .unwrap();
+        let mut writer = BcfWriter::new(Vec::new(), &header, Compression::Bgzf).unwrap();
+        writer.write(&record).unwrap();
+        let output = writer.finish().unwrap();
+
+        let mut records =
+            BcfRecords::new(niffler::get_reader(Box::new(&output[..])).unwrap().0).unwrap();
+        assert_eq!(records.header().samples(), &["S1", "S2"]);
+        let record = records.next().unwrap();
+        assert_eq!(record.chrom(), "chr1");
+        assert_eq!(record.pos(), 99);
+        assert_eq!(record.filters(), ["q10"]);
+        assert_eq!(record.info(b"DP").unwrap().integer(), [20]);
+        assert_eq!(
+            record.format(b"DP").unwrap(),
+            [TypedVec::Int32(vec![8]), TypedVec::Int32(vec![12])]
+        );
+        assert!(records.next().is_none());
+    }
+
+    /// Returns the header text of a BCF file, without the terminating NUL byte.
+    fn raw_header_text(path: &str) -> String {
+        let (mut reader, _format) = niffler::from_path(path).unwrap();
+        let mut bcf = Vec::new();
+        reader.read_to_end(&mut bcf).unwrap();
+        let length = u32::from_le_bytes([bcf[5], bcf[6], bcf[7], bcf[8]]) as usize;
+        String::from_utf8(bcf[9..9 + length - 1].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_header_to_vcf_text() {
+        for path in &[
+            "resources/types.bcf",
+            "resources/example.uncompressed.bcf",
+            "resources/example.compressed.bcf",
+            "resources/example.id.bcf",
+        ] {
+            let records = BcfRecords::from_path(path).unwrap();
+            assert_eq!(records.header().to_vcf_text(), raw_header_text(path));
+        }
+    }
+
+    #[test]
+    fn test_header_to_vcf_text_without_idx() {
+        let text = "##fileformat=VCFv4.3\n\
+            ##ALT=<ID=DEL,Description=\"Deletion\">\n\
+            ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth \\\"raw\\\"\",Source=dbsnp,Version=\"138\">\n\
+            ##source=test\n\
+            ##FILTER=<ID=q10,Description=\"Quality below 10\",Extra=1>\n\
+            ##ALT=<ID=INS,Description=\"Insertion\">\n\
+            ##contig=<ID=chr1,assembly=\"b37\",length=1000>\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n";
+        let mut bcf = b"BCF\x02\x02".to_vec();
+        bcf.extend_from_slice(&(text.len() as u32 + 1).to_le_bytes());
+        bcf.extend_from_slice(text.as_bytes());
+        bcf.push(0);
+        let records = BcfRecords::new(&bcf[..]).unwrap();
+        let mut header = records.header().clone();
+        assert_eq!(header.to_vcf_text(), text);
+
+        header.add_filter(HeaderFilter::new("q20", "Quality below 20"));
+        header.remove_meta("ALT");
+        header.add_meta("fileformat", "VCFv4.2");
+        header.remove_meta("fileformat");
+        header.add_meta("fileformat", "VCFv4.2");
+        let lines = header.to_vcf_text();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "##fileformat=VCFv4.2");
+        assert_eq!(
+            lines[4],
+            "##FILTER=<ID=q20,Description=\"Quality below 20\">"
+        );
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn test_writer_header_text() {
+        let path = "resources/example.uncompressed.bcf";
+        let records = BcfRecords::from_path(path).unwrap();
+        let writer =
+            BcfWriter::new(Vec::new(), records.header(), Compression::Uncompressed).unwrap();
+        let output = writer.finish().unwrap();
+        let (mut reader, _format) = niffler::from_path(path).unwrap();
+        let mut bcf = Vec::new();
+        reader.read_to_end(&mut bcf).unwrap();
+        assert_eq!(output[..], bcf[..output.len()]);
+    }
+
+    fn vcf_text(path: &str, compression: Compression) -> Vec<u8> {
+        let records = BcfRecords::from_path(path).unwrap();
+        let mut writer = VcfWriter::new(Vec::new(), records.header(), compression).unwrap();
+        for record in records {
+            writer.write(&record).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_vcf_writer() {
+        let vcf = vcf_text("resources/types.bcf", Compression::Uncompressed);
+        let vcf = String::from_utf8(vcf).unwrap();
+        let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        assert!(vcf.starts_with(&records.header().to_vcf_text()));
+        let lines = vcf
+            .lines()
+            .filter(|l| !l.starts_with('#'))
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "chr1\t1\t.\tG\tA\t276\tPASS\t\
+             FLAG;INT=1;FLOAT=0.5;STRING=String;INT2=1,2;FLOAT2=0.5,1;STRING2=String1,String2;\
+             INTA=1;FLOATA=0.5;STRINGA=StringA;INTR=1,2;FLOATR=0.5,1;STRINGR=StringR1,StringR2;\
+             INTX=1,2,3,4;FLOATX=0.5,0.75;STRINGX=String1,String2,String3\t\
+             GT:INT:INT2:INTA:INTR:INTG:INTX\t0/1:1:1,2:1:1,2:1:1,2,3,4"
+        );
+    }
+
+    #[test]
+    fn test_vcf_writer_bgzf() {
+        let path = "resources/example.uncompressed.bcf";
+        let vcf = vcf_text(path, Compression::Uncompressed);
+        let output = vcf_text(path, Compression::Bgzf);
+        assert_eq!(output[output.len() - 28..], crate::bgzf::EOF_MARKER);
+        let (mut reader, _format) = niffler::get_reader(Box::new(&output[..])).unwrap();
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, vcf);
+
+        let vcf = String::from_utf8(vcf).unwrap();
+        let line = vcf.lines().find(|l| !l.starts_with('#')).unwrap();
+        let columns = line.split('\t').collect::<Vec<_>>();
+        assert_eq!(columns[8], "GT:DP:ADALL:AD:GQ:IGT:IPS:PS");
+        assert_eq!(columns[9], "1|1:823:0,381:78,454:283:1/1:.:PATMAT");
+        // trailing missing values are dropped
+        assert_eq!(columns[10], "1/1:1301:75,608:0,114:471");
+    }
+
+    #[test]
+    fn test_vcf_writer_missing_values() {
+        let header = HeaderBuilder::new()
+            .contig("chr1", None)
+            .info(
+                "AF",
+                InfoNumber::AlternateAlleles,
+                InfoType::Float,
+                "Frequency",
+            )
+            .format("GT", InfoNumber::Count(1), InfoType::String, "Genotype")
+            .format("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .format("AD", InfoNumber::Alleles, InfoType::Integer, "Depths")
+            .sample("S1")
+            .sample("S2")
+            .build()
+            .unwrap();
+        let mut record = VariantRecord::new("chr1", 9, vec![b"A".to_vec(), b"C".to_vec()]);
+        record.info_mut().insert(
+            "AF".into(),
+            TypedVec::Float32(vec![f32::from_bits(crate::types::MISSING_FLOAT)]),
+        );
+        record.set_genotypes(&[
+            vec![GenotypeAllele::UnphasedMissing, GenotypeAllele::Unphased(1)],
+            vec![GenotypeAllele::Unphased(0), GenotypeAllele::Phased(1)],
+        ]);
+        record.format_mut().insert(
+            "DP".into(),
+            vec![
+                TypedVec::Int32(vec![MISSING_INTEGER]),
+                TypedVec::Int32(vec![7]),
+            ],
+        );
+        record.format_mut().insert(
+            "AD".into(),
+            vec![
+                TypedVec::Int32(vec![MISSING_INTEGER, MISSING_INTEGER]),
+                TypedVec::Int32(vec![3, MISSING_INTEGER]),
+            ],
+        );
+        let bcf_record = record.to_bcf_record(header.clone().into()).unwrap();
+        let mut writer = VcfWriter::new(Vec::new(), &header, Compression::Uncompressed).unwrap();
+        writer.write(&bcf_record).unwrap();
+        // samples without values, e.g. of records with fewer samples, are written as missing
+        let mut single = header.clone();
+        single.samples.truncate(1);
+        record.select_samples(&[1]).unwrap();
+        let bcf_record = record.to_bcf_record(single.into()).unwrap();
+        writer.write(&bcf_record).unwrap();
+        let vcf = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let lines = vcf.lines().rev().take(2).collect::<Vec<_>>();
+        assert_eq!(
+            lines[1],
+            "chr1\t10\t.\tA\tC\t.\t.\tAF=.\tGT:DP:AD\t./1\t0|1:7:3,."
+        );
+        assert_eq!(
+            lines[0],
+            "chr1\t10\t.\tA\tC\t.\t.\tAF=.\tGT:DP:AD\t0|1:7:3,.\t."
+        );
+    }
+
+    #[test]
+    fn test_vcf_records() {
+        for path in &["resources/types.bcf", "resources/example.uncompressed.bcf"] {
+            let vcf = vcf_text(path, Compression::Bgzf);
+            let vcf_records = VcfRecords::new(&vcf[..]).unwrap();
+            let bcf_records = BcfRecords::from_path(path).unwrap();
+            assert_eq!(
+                vcf_records.header().to_vcf_text(),
+                bcf_records.header().to_vcf_text()
+            );
+            let mut n = 0;
+            for (vcf_record, bcf_record) in vcf_records.zip(bcf_records) {
+                assert_eq!(
+                    VariantRecord::from(&vcf_record),
+                    VariantRecord::from(&bcf_record)
+                );
+                assert_eq!(vcf_record.genotypes(), bcf_record.genotypes());
+                n += 1;
+            }
+            assert!(n > 0);
+        }
+    }
+
+    #[test]
+    fn test_vcf_records_missing_values() {
+        let text = "##fileformat=VCFv4.3\n\
+            ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+            ##INFO=<ID=DB,Number=0,Type=Flag,Description=\"dbSNP\">\n\
+            ##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+            ##FORMAT=<ID=AD,Number=R,Type=Integer,Description=\"Depths\">\n\
+            ##FORMAT=<ID=FT,Number=1,Type=String,Description=\"Filter\">\n\
+            ##contig=<ID=chr1>\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2\n\
+            chr1\t10\t.\tA\t.\t.\t.\t.\tGT:AD:FT\t./.\t0|1:3,.:q10\n\
+            chr1\t20\trs1\tA\tC,G\t5.5\tq10;q20\tDB;DP=.;XX=1\tGT\t1\t.\n";
+        let mut records = VcfRecords::new(text.as_bytes()).unwrap();
+        assert_eq!(records.header().info_tag_to_offset()["DB"], 2);
+
+        let record = records.next().unwrap();
+        assert_eq!(record.pos(), 9);
+        assert!(record.id().is_empty());
+        assert!(record.alt_alleles().is_empty());
+        assert_eq!(record.qual(), None);
+        assert!(record.filters().is_empty());
+        assert!(record.info_tags().is_empty());
+        assert_eq!(record.format_tags(), ["GT", "AD", "FT"]);
+        assert_eq!(
+            record.genotypes(),
+            [
+                vec![
+                    GenotypeAllele::UnphasedMissing,
+                    GenotypeAllele::UnphasedMissing
+                ],
+                vec![GenotypeAllele::Unphased(0), GenotypeAllele::Phased(1)],
+            ]
+        );
+        // dropped trailing fields are missing
+        assert_eq!(
+            record.format(b"AD").unwrap(),
+            [
+                TypedVec::Int32(vec![MISSING_INTEGER]),
+                TypedVec::Int32(vec![3, MISSING_INTEGER])
+            ]
+        );
+        assert_eq!(
+            record.format(b"FT").unwrap(),
+            [
+                TypedVec::UString(b".".to_vec()),
+                TypedVec::UString(b"q10".to_vec())
+            ]
+        );
+
+        let record = records.next().unwrap();
+        assert_eq!(record.id(), b"rs1");
+        assert_eq!(record.alt_alleles(), [b"C", b"G"]);
+        assert_eq!(record.qual(), Some(5.5));
+        assert_eq!(record.filters(), ["q10", "q20"]);
+        assert!(record.filter_headers().is_empty());
+        assert!(record.has_flag(b"DB"));
+        assert!(!record.has_flag(b"AA"));
+        assert_eq!(record.info(b"DP").unwrap().integer(), [MISSING_INTEGER]);
+        assert_eq!(
+            record.info(b"XX").unwrap(),
+            TypedVec::UString(b"1".to_vec())
+        );
+        assert_eq!(
+            record.genotypes(),
+            [
+                vec![GenotypeAllele::Unphased(1)],
+                vec![GenotypeAllele::UnphasedMissing]
+            ]
+        );
+        assert!(records.next().is_none());
+        assert!(records.take_error().is_none());
+    }
+
+    #[test]
+    fn test_vcf_records_errors() {
+        let header = "##fileformat=VCFv4.2\n\
+            ##contig=<ID=chr1>\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n";
+        // telomeric records at POS 0 are at the first position
+        let text = format!("{}chr1\t0\t.\tN\t.\t.\t.\t.\n", header);
+        let mut records = VcfRecords::new(text.as_bytes()).unwrap();
+        let record = records.next().unwrap();
+        assert_eq!(record.pos(), 0);
+        assert_eq!(record.interval(), Interval::from_zero_based(0, 1));
+
+        // lines with too few columns and invalid UTF-8 are errors, not the end of the input
+        let text = format!("{}chr1\t10\t.\tA\t.\t.\t.\t.\nchr1\t20\t.\tA\n", header);
+        let mut records = VcfRecords::new(text.as_bytes()).unwrap();
+        assert_eq!(records.by_ref().count(), 1);
+        assert!(records
+            .take_error()
+            .unwrap()
+            .to_string()
+            .contains("expected at least 8"));
+        let mut text = format!("{}chr1\t10\t.\tA\t.\t.\t.\t.\n", header).into_bytes();
+        text.extend_from_slice(b"chr1\t20\t.\tA\t.\t.\t.\t\xff\n");
+        let mut records = VcfRecords::new(&text[..]).unwrap();
+        assert_eq!(records.by_ref().count(), 1);
+        assert!(records.take_error().is_some());
+        assert!(records.next().is_none());
+
+        // invalid POS and QUAL are errors of the reader
+        for line in ["chr1\tx\t.\tA\t.\t.\t.\t.", "chr1\t10\t.\tA\t.\tfoo\t.\t."] {
+            let text = format!("{}{}\n", header, line);
+            let mut records = VcfRecords::new(text.as_bytes()).unwrap();
+            assert!(records.next().is_none());
+            assert!(records
+                .take_error()
+                .unwrap()
+                .to_string()
+                .starts_with("invalid"));
+        }
+
+        // invalid INFO and FORMAT values are errors of the fallible accessors
+        let text = "##fileformat=VCFv4.2\n\
+            ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+            ##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+            ##FORMAT=<ID=GQ,Number=1,Type=Integer,Description=\"Quality\">\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n\
+            chr1\t10\t.\tA\tC\t.\t.\tDP=1.5\tGT:GQ\ta/1:x\n";
+        let record = VcfRecords::new(text.as_bytes()).unwrap().next().unwrap();
+        assert_eq!(
+            record.try_info(b"DP").unwrap_err().to_string(),
+            "invalid Integer value 1.5 of INFO DP"
+        );
+        assert_eq!(
+            record.try_format(b"GT").unwrap_err().to_string(),
+            "invalid genotype a/1 of FORMAT GT"
+        );
+        assert_eq!(
+            record.try_format(b"GQ").unwrap_err().to_string(),
+            "invalid Integer value x of FORMAT GQ"
+        );
+        assert!(record.has_flag(b"DP"));
+        assert_eq!(record.try_info(b"AF").unwrap(), None);
+    }
+
+    /// Writes a BGZF compressed VCF with records of various lengths on several contigs.
+    fn indexed_vcf() -> (Vec<u8>, Vec<(String, u32, u32)>) {
+        let header = HeaderBuilder::new()
+            .contig("chr1", None)
+            .contig("chr2", None)
+            .contig("chr3", None)
+            .info(
+                "END",
+                InfoNumber::Count(1),
+                InfoType::Integer,
+                "End position",
+            )
+            .build()
+            .unwrap();
+        let mut writer = VcfWriter::new(Vec::new(), &header, Compression::Bgzf).unwrap();
+        let mut intervals = vec![];
+        for chrom in &["chr1", "chr3"] {
+            for i in 0..3000u32 {
+                let pos = i * 997;
+                let mut record = VariantRecord::new(chrom, pos, vec![b"ACGT".to_vec()]);
+                let mut end = pos + 4;
+                if i % 100 == 0 {
+                    end = pos + 200_000;
+                    record
+                        .info_mut()
+                        .insert("END".into(), TypedVec::Int32(vec![end as i32]));
+                }
+                let record = record.to_bcf_record(header.clone().into()).unwrap();
+                writer.write(&record).unwrap();
+                intervals.push((chrom.to_string(), pos, end));
+            }
+        }
+        (writer.finish().unwrap(), intervals)
+    }
+
+    #[test]
+    fn test_bgzf_malformed_blocks() {
+        use std::io::Read;
+
+        let read = |block: &[u8]| {
+            let mut data = vec![];
+            BgzfReader::new(block).read_to_end(&mut data).map(|_| data)
+        };
+        assert_eq!(read(&crate::bgzf::EOF_MARKER).unwrap(), b"");
+        // block sizes (BSIZE + 1) too small for the header, extra field and footer
+        for bsize in [0u16, 5, 18, 24] {
+            let mut block = crate::bgzf::EOF_MARKER.to_vec();
+            block[16..18].copy_from_slice(&bsize.to_le_bytes());
+            let error = read(&block).unwrap_err();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        }
+        // an uncompressed size beyond the maximum block size
+        let mut block = crate::bgzf::EOF_MARKER.to_vec();
+        block[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = read(&block).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tabix_index_roundtrip() {
+        let (vcf, _) = indexed_vcf();
+        let index = TabixIndex::build(&vcf[..]).unwrap();
+        assert_eq!(index.names(), &["chr1", "chr3"]);
+        let mut tbi = Vec::new();
+        index.write(&mut tbi).unwrap();
+        assert_eq!(TabixIndex::read(&tbi[..]).unwrap(), index);
+
+        let path = std::env::temp_dir().join(format!("rust-bcf-{}.tbi", std::process::id()));
+        index.to_path(&path).unwrap();
+        let read = TabixIndex::from_path(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), index);
+    }
+
+    #[test]
+    fn test_vcf_query_telomere() {
+        use std::io::Write;
+
+        let mut writer = crate::bgzf::BgzfWriter::new(Vec::new());
+        writer
+            .write_all(
+                b"##fileformat=VCFv4.2\n##contig=<ID=chr1>\n\
+                #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+                chr1\t0\t.\tN\t.\t.\t.\t.\n\
+                chr1\t1\t.\tA\t.\t.\t.\t.\n",
+            )
+            .unwrap();
+        let vcf = writer.finish().unwrap();
+        // records at POS 0 are indexed (and found) at the first position
+        let index = TabixIndex::build(&vcf[..]).unwrap();
+        let reader = BgzfReader::new(std::io::Cursor::new(vcf));
+        let mut records = VcfRecords::from_text(reader).unwrap().with_index(index);
+        let positions = records
+            .query("chr1", 0, 1)
+            .unwrap()
+            .map(|record| record.line().split('\t').nth(1).unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(positions, ["0", "1"]);
+        assert!(records.take_error().is_none());
+    }
+
+    #[test]
+    fn test_vcf_query() {
+        let (vcf, intervals) = indexed_vcf();
+        let index = TabixIndex::build(&vcf[..]).unwrap();
+        let reader = BgzfReader::new(std::io::Cursor::new(vcf));
+        let mut records = VcfRecords::from_text(reader).unwrap().with_index(index);
+        for (contig, start, end) in &[
+            ("chr1", 0, 1),
+            ("chr1", 5000, 5001),
+            ("chr1", 150_000, 400_000),
+            ("chr1", 2_990_000, 3_100_000),
+            ("chr2", 0, 1_000_000),
+            ("chr3", 1_000_000, 1_016_384),
+            ("chr3", 0, u32::MAX >> 2),
+            ("chrX", 0, 1000),
+        ] {
+            let expected = intervals
+                .iter()
+                .filter(|(c, s, e)| c == contig && s < end && e > start)
+                .map(|(_, s, _)| *s)
+                .collect::<Vec<_>>();
+            let actual = records
+                .query(contig, *start, *end)
+                .unwrap()
+                .map(|record| record.pos())
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected, "{}:{}-{}", contig, start, end);
+        }
+        // contigs may be given by an alias
+        let query = |records: &mut VcfRecords<_>, contig| {
+            records
+                .query(contig, 0, 1_000_000)
+                .unwrap()
+                .map(|record| record.chrom().to_string())
+                .collect::<Vec<_>>()
+        };
+        let chr3 = query(&mut records, "chr3");
+        assert!(!chr3.is_empty());
+        assert_eq!(query(&mut records, "3"), chr3);
+
+        // regions, including open-ended ones
+        for (region, start, end) in &[
+            ("chr3", 0, u32::MAX),
+            ("chr3:1,000,001-", 1_000_000, u32::MAX),
+            ("chr1:-5000", 0, 5000),
+            ("chr1:150,001-400,000", 150_000, 400_000),
+        ] {
+            let region = region.parse::<Region>().unwrap();
+            let expected = intervals
+                .iter()
+                .filter(|(c, s, e)| c == region.contig() && s < end && e > start)
+                .map(|(_, s, _)| *s)
+                .collect::<Vec<_>>();
+            let actual = records
+                .query_region(&region)
+                .unwrap()
+                .map(|record| record.pos())
+                .collect::<Vec<_>>();
+            assert!(!actual.is_empty());
+            assert_eq!(actual, expected, "{}", region);
+        }
+        assert!(records.query("chr1", 10, 5).is_err());
+    }
+
+    const UNDECLARED_VCF: &str = "##fileformat=VCFv4.3\n\
+        ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+        ##contig=<ID=chr1>\n\
+        #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\tS2\n\
+        chr1\t10\t.\tA\tC\t.\tPASS\tDP=3;AF=0.5;DB;AC=1;SC=inf\tGT:AD:XS\t0/1:1,2:a\t./.\n\
+        chr2\t20\t.\tA\tC,G\t.\tq10\tDP=4;AF=0.1,0.2;AC=1,1;NOTE=x;SC=1.5\tGT:AD\t1|2:1,2,3\t0/0:5,.,.\n";
+
+    #[test]
+    fn test_header_inference() {
+        let records = VcfRecords::new(UNDECLARED_VCF.as_bytes()).unwrap();
+        let mut inference = crate::convert::HeaderInference::new(records.header());
+        records.for_each(|record| inference.observe(&record));
+        let (header, warnings) = inference.finish();
+        assert_eq!(warnings.len(), 10);
+        assert_eq!(
+            warnings[0],
+            "contig chr2 is not defined in the header, added without length"
+        );
+        let info = |tag: &str| {
+            let info = &header.info()[&header.info_tag_to_offset()[tag]];
+            (info.number().to_string(), info.kind().to_string())
+        };
+        assert_eq!(info("DP"), ("1".into(), "Integer".into()));
+        assert_eq!(info("AF"), ("A".into(), "Float".into()));
+        assert_eq!(info("DB"), ("0".into(), "Flag".into()));
+        assert_eq!(info("AC"), ("A".into(), "Integer".into()));
+        assert_eq!(info("NOTE"), ("1".into(), "String".into()));
+        // words such as `inf` and `nan` are not numbers
+        assert_eq!(info("SC"), ("1".into(), "String".into()));
+        let format = |tag: &str| {
+            let format = &header.format()[&header.format_tag_to_offset()[tag]];
+            (format.number().to_string(), format.kind().to_string())
+        };
+        assert_eq!(format("GT"), ("1".into(), "String".into()));
+        assert_eq!(format("AD"), ("R".into(), "Integer".into()));
+        assert_eq!(format("XS"), ("1".into(), "String".into()));
+        assert!(header.filter_by_id("q10").is_some());
+        assert_eq!(header.contigs().len(), 2);
+    }
+
+    #[test]
+    fn test_vcf_to_bcf() {
+        use crate::convert::{vcf_to_bcf, Mode};
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("rust-bcf-{}-input.vcf", std::process::id()));
+        let output = dir.join(format!("rust-bcf-{}-output.bcf", std::process::id()));
+        std::fs::write(&input, UNDECLARED_VCF).unwrap();
+
+        let strict = vcf_to_bcf(&input, &output, Compression::Bgzf, Mode::Strict);
+        let lenient = vcf_to_bcf(&input, &output, Compression::Bgzf, Mode::Lenient);
+        let bcf = BcfRecords::from_path(&output).map(|records| {
+            let header = records.header().clone();
+            (header, records.collect::<Vec<_>>())
+        });
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert!(strict.is_err());
+        assert_eq!(lenient.unwrap().len(), 10);
+        let (header, records) = bcf.unwrap();
+        assert_eq!(header.samples(), &["S1", "S2"]);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].info(b"AF").unwrap().float(), [0.5]);
+        assert!(records[0].has_flag(b"DB"));
+        assert_eq!(
+            records[0].format(b"XS").unwrap(),
+            [
+                TypedVec::UString(b"a".to_vec()),
+                TypedVec::UString(b".".to_vec())
+            ]
+        );
+        assert_eq!(records[1].chrom(), "chr2");
+        assert_eq!(records[1].filters(), ["q10"]);
+        assert_eq!(records[1].info(b"AC").unwrap().integer(), [1, 1]);
+        assert_eq!(
+            records[1].genotypes()[0],
+            [GenotypeAllele::Unphased(1), GenotypeAllele::Phased(2)]
+        );
+        assert_eq!(
+            records[1].format(b"AD").unwrap()[1].integer(),
+            [5, MISSING_INTEGER, MISSING_INTEGER]
+        );
+    }
+
+    #[test]
+    fn test_convert_invalid_values() {
+        use crate::convert::convert;
+        let text = "##fileformat=VCFv4.2\n\
+            ##contig=<ID=chr1>\n\
+            ##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+            #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+            chr1\t10\t.\tA\t.\t.\t.\tDP=3\n\
+            chr1\t20\t.\tA\t.\t.\t.\tDP=abc\n";
+        let records = VcfRecords::new(text.as_bytes()).unwrap();
+        let mut writer =
+            BcfWriter::new(vec![], records.header(), Compression::Uncompressed).unwrap();
+        let error = convert(records, &mut writer).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 6: invalid Integer value abc of INFO DP"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_vcf_to_bcf_pipe() {
+        use crate::convert::{vcf_to_bcf, Mode};
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("rust-bcf-{}-input.fifo", std::process::id()));
+        let output = dir.join(format!("rust-bcf-{}-pipe.bcf", std::process::id()));
+        let status = std::process::Command::new("mkfifo")
+            .arg(&input)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        // a pipe can only be read once, which lenient mode has to take into account
+        let fifo = input.clone();
+        let writer = std::thread::spawn(move || std::fs::write(fifo, UNDECLARED_VCF));
+        let lenient = vcf_to_bcf(&input, &output, Compression::Bgzf, Mode::Lenient);
+        writer.join().unwrap().unwrap();
+        let records = BcfRecords::from_path(&output).map(|records| records.count());
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(lenient.unwrap().len(), 10);
+        assert_eq!(records.unwrap(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_records() {
+        for record in BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap() {
+            let variant = VariantRecord::from(&record);
+            // missing floats are not equal to themselves, hence compare serialized forms
+            let json = serde_json::to_string(&record).unwrap();
+            assert_eq!(json, serde_json::to_string(&variant).unwrap());
+            let from_json: VariantRecord = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&from_json).unwrap(), json);
+
+            let msgpack = rmp_serde::to_vec(&variant).unwrap();
+            let from_msgpack: VariantRecord = rmp_serde::from_slice(&msgpack).unwrap();
+            assert_eq!(rmp_serde::to_vec(&from_msgpack).unwrap(), msgpack);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_values() {
+        let mut record = VariantRecord::new("chr1", 99, vec![b"A".to_vec(), b"T".to_vec()]);
+        record.set_id(b"rs1".to_vec());
+        record
+            .info_mut()
+            .insert("AC".into(), TypedVec::Int32(vec![1, MISSING_INTEGER]));
+        record.info_mut().insert("DB".into(), TypedVec::Missing);
+        record
+            .format_mut()
+            .insert("XS".into(), vec![TypedVec::UString(b"a".to_vec())]);
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "chrom": "chr1",
+                "pos": 99,
+                "id": "rs1",
+                "alleles": ["A", "T"],
+                "qual": null,
+                "filters": [],
+                "info": {"AC": {"Int32": [1, null]}, "DB": "Missing"},
+                "format": {"XS": [{"UString": "a"}]},
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<VariantRecord>(json).unwrap(),
+            record
+        );
+
+        // NaN and infinity are kept distinct from missing values
+        use crate::types::MISSING_FLOAT;
+        record.set_qual(Some(f32::NAN));
+        record.info_mut().insert(
+            "AF".into(),
+            TypedVec::Float32(vec![
+                f32::NAN,
+                f32::from_bits(MISSING_FLOAT),
+                f32::NEG_INFINITY,
+                0.5,
+            ]),
+        );
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["qual"], "NaN");
+        assert_eq!(
+            json["info"]["AF"],
+            serde_json::json!({"Float32": ["NaN", null, "-Inf", 0.5]})
+        );
+        let from_json = serde_json::from_value::<VariantRecord>(json.clone()).unwrap();
+        assert!(from_json.qual().unwrap().is_nan());
+        let af = from_json.info()["AF"].float();
+        assert!(af[0].is_nan() && af[0].to_bits() != MISSING_FLOAT);
+        assert_eq!(af[1].to_bits(), MISSING_FLOAT);
+        assert_eq!(serde_json::to_value(&from_json).unwrap(), json);
+        let msgpack = rmp_serde::to_vec(&record).unwrap();
+        let from_msgpack: VariantRecord = rmp_serde::from_slice(&msgpack).unwrap();
+        assert_eq!(serde_json::to_value(&from_msgpack).unwrap(), json);
+        assert!(serde_json::from_value::<VariantRecord>(serde_json::json!({
+            "chrom": "chr1", "pos": 0, "id": "", "alleles": ["A"], "qual": "high",
+            "filters": [], "info": {}, "format": {},
+        }))
+        .is_err());
+
+        assert_eq!(
+            serde_json::to_value(GenotypeAllele::Phased(1)).unwrap(),
+            serde_json::json!({"Phased": 1})
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_header() {
+        let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+        let json = serde_json::to_value(records.header()).unwrap();
+        assert_eq!(
+            json["meta"][0],
+            serde_json::json!(["fileformat", "VCFv4.1"])
+        );
+        assert_eq!(json["filters"][0]["id"], "PASS");
+        assert_eq!(json["samples"], serde_json::json!(["Sample"]));
+        let info = &json["info"][0];
+        let parsed: HeaderInfo = serde_json::from_value(info.clone()).unwrap();
+        assert_eq!(parsed.id(), info["id"].as_str().unwrap());
+        assert_eq!(
+            serde_json::to_value(parsed.number()).unwrap(),
+            info["number"]
+        );
+        let format: HeaderFormat = serde_json::from_value(json["format"][0].clone()).unwrap();
+        assert_eq!(format.id(), "GT");
+        assert_eq!(format.kind(), &InfoType::String);
+
+        // deserialized headers keep the dictionary offsets, hence decode the original records
+        for path in &["resources/types.bcf", "resources/example.uncompressed.bcf"] {
+            let records = BcfRecords::from_path(path).unwrap();
+            let json = serde_json::to_string(records.header()).unwrap();
+            let header: crate::types::Header = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&header).unwrap(), json);
+            let header = std::sync::Arc::new(header);
+            for record in records {
+                let decoded =
+                    BcfRecord::new(record.shared.clone(), record.format.clone(), header.clone());
+                assert_eq!(
+                    serde_json::to_value(&decoded).unwrap(),
+                    serde_json::to_value(&record).unwrap()
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_arrow_batches() {
+        use crate::arrow::{ArrowConverter, ArrowOptions};
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::{Int32Type, UInt32Type};
+        use arrow_array::Array;
+
+        let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+        let n_records = BcfRecords::from_path("resources/example.uncompressed.bcf")
+            .unwrap()
+            .count();
+        let options = ArrowOptions {
+            batch_size: 3,
+            ..Default::default()
+        };
+        let converter = ArrowConverter::new(records.header(), options).unwrap();
+        // fixed columns, 19 INFO fields and 3 samples
+        assert_eq!(converter.schema().fields().len(), 7 + 19 + 3);
+        let batches = converter
+            .batches(records)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(batches.len(), n_records.div_ceil(3));
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).sum::<usize>(),
+            n_records
+        );
+
+        let batch = &batches[0];
+        let pos = batch
+            .column_by_name("POS")
+            .unwrap()
+            .as_primitive::<UInt32Type>();
+        assert_eq!(pos.values()[..2], [817186, 817341]);
+        let sample = batch.column_by_name("HG001").unwrap().as_struct();
+        let gt = sample.column_by_name("GT").unwrap().as_string::<i32>();
+        assert_eq!(gt.value(0), "1|1");
+        let ad = sample.column_by_name("AD").unwrap().as_list::<i32>();
+        assert_eq!(
+            ad.value(0).as_primitive::<Int32Type>().values()[..],
+            [78, 454]
+        );
+        let platforms = batch
+            .column_by_name("INFO_platformnames")
+            .unwrap()
+            .as_list::<i32>();
+        assert_eq!(
+            platforms
+                .value(2)
+                .as_string::<i32>()
+                .iter()
+                .collect::<Vec<_>>(),
+            [Some("Illumina"), Some("CG")]
+        );
+
+        // sites only, and projected INFO fields
+        let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+        let options = ArrowOptions {
+            info: Some(vec!["platforms".into()]),
+            format: Some(vec![]),
+            ..Default::default()
+        };
+        let converter = ArrowConverter::new(records.header(), options).unwrap();
+        assert_eq!(converter.schema().fields().len(), 8);
+        let batch = converter.batches(records).next().unwrap().unwrap();
+        assert_eq!(batch.num_rows(), n_records);
+        assert_eq!(batch.column(7).null_count(), 0);
+
+        let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+        let options = ArrowOptions {
+            info: Some(vec!["undefined".into()]),
+            ..Default::default()
+        };
+        assert!(ArrowConverter::new(records.header(), options).is_err());
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn test_parquet_writer() {
+        use crate::arrow::TableLayout;
+        use crate::parquet::{bcf_to_parquet, ParquetOptions, ParquetWriter};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let path = "resources/example.uncompressed.bcf";
+        let records = BcfRecords::from_path(path).unwrap().collect::<Vec<_>>();
+        let header = BcfRecords::from_path(path).unwrap().header().clone();
+        let n_contigs = records
+            .iter()
+            .map(|r| r.chrom().to_string())
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+
+        let read = |file: &std::path::Path| {
+            let builder =
+                ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(file).unwrap())
+                    .unwrap();
+            let row_groups = builder.metadata().num_row_groups();
+            let schema = builder.schema().clone();
+            let rows = builder
+                .build()
+                .unwrap()
+                .map(|batch| batch.unwrap().num_rows())
+                .sum::<usize>();
+            (schema, row_groups, rows)
+        };
+
+        // sites only, with one row group per contig
+        let sites = std::env::temp_dir().join("rust_bcf_test_sites.parquet");
+        let options = ParquetOptions {
+            sites_only: true,
+            region_size: Some(u32::MAX),
+            ..Default::default()
+        };
+        bcf_to_parquet(path, &sites, options).unwrap();
+        let (schema, row_groups, rows) = read(&sites);
+        assert_eq!(schema.fields().len(), 7 + 19);
+        assert_eq!(row_groups, n_contigs);
+        assert_eq!(rows, records.len());
+
+        // long layout with selected fields
+        let long = std::env::temp_dir().join("rust_bcf_test_long.parquet");
+        let options = ParquetOptions {
+            info: Some(vec![]),
+            format: Some(vec!["GT".into(), "AD".into()]),
+            layout: TableLayout::Long,
+            row_group_size: 10,
+            ..Default::default()
+        };
+        let mut writer = ParquetWriter::to_path(&long, &header, options).unwrap();
+        writer.write(records.iter().cloned()).unwrap();
+        writer.finish().unwrap();
+        let (schema, row_groups, rows) = read(&long);
+        let names = schema
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["CHROM", "POS", "ID", "REF", "ALT", "QUAL", "FILTER", "SAMPLE", "GT", "AD"]
+        );
+        assert_eq!(rows, records.len() * 3);
+        assert_eq!(row_groups, rows.div_ceil(10));
+
+        std::fs::remove_file(&sites).unwrap();
+        std::fs::remove_file(&long).unwrap();
+
+        let options = ParquetOptions {
+            region_size: Some(0),
+            ..Default::default()
+        };
+        assert!(ParquetWriter::new(vec![], &header, options).is_err());
+    }
+
+    #[cfg(feature = "zarr")]
+    #[test]
+    fn test_bcf_to_zarr() {
+        use crate::zarr::{bcf_to_zarr, ZarrOptions};
+        use flate2::read::ZlibDecoder;
+
+        let path = "resources/example.uncompressed.bcf";
+        let records = BcfRecords::from_path(path).unwrap().collect::<Vec<_>>();
+        let store = std::env::temp_dir().join("rust_bcf_test.vcz");
+        let _ = std::fs::remove_dir_all(&store);
+        let options = ZarrOptions {
+            variants_chunk_size: 4,
+            samples_chunk_size: 2,
+            ..Default::default()
+        };
+        bcf_to_zarr(path, &store, options).unwrap();
+        let metadata = |key: &str| -> serde_json::Value {
+            serde_json::from_slice(&std::fs::read(store.join(key)).unwrap()).unwrap()
+        };
+        let chunk = |key: &str| {
+            let mut bytes = vec![];
+            ZlibDecoder::new(std::fs::File::open(store.join(key)).unwrap())
+                .read_to_end(&mut bytes)
+                .unwrap();
+            bytes
+        };
+
+        let zarray = metadata("call_genotype/.zarray");
+        assert_eq!(zarray["shape"], serde_json::json!([records.len(), 3, 2]));
+        assert_eq!(zarray["chunks"], serde_json::json!([4, 2, 2]));
+        assert_eq!(
+            metadata("call_genotype/.zattrs")["_ARRAY_DIMENSIONS"],
+            serde_json::json!(["variants", "samples", "ploidy"])
+        );
+        assert!(metadata(".zmetadata")["metadata"]["call_AD/.zarray"].is_object());
+
+        // variants 0..4 of samples 0..2, and variants 0..4 of sample 2, padded with fill values
+        let gt = chunk("call_genotype/0.0.0");
+        assert_eq!(gt.len(), 4 * 2 * 2);
+        assert_eq!(gt[..4], [1, 1, 1, 1]);
+        let gt = chunk("call_genotype/0.1.0");
+        assert_eq!(gt[2..4], [(-2i8) as u8, (-2i8) as u8]);
+        let n_chunks = records.len().div_ceil(4);
+        assert!(store
+            .join(format!("call_genotype/{}.1.0", n_chunks - 1))
+            .exists());
+
+        let pos = chunk("variant_position/0");
+        assert_eq!(pos[..4], 817186i32.to_le_bytes());
+        let ad = chunk("call_AD/0.0.0");
+        let alleles = metadata("call_AD/.zarray")["shape"][2].as_u64().unwrap() as usize;
+        assert_eq!(ad[..4], 78i32.to_le_bytes());
+        assert_eq!(ad[4..8], 454i32.to_le_bytes());
+        if alleles > 2 {
+            assert_eq!(ad[8..12], (-2i32).to_le_bytes());
+        }
+
+        // vlen-utf8 encoded sample names
+        let samples = chunk("sample_id/0");
+        assert_eq!(samples[..4], 2u32.to_le_bytes());
+        assert_eq!(samples[4..8], 5u32.to_le_bytes());
+        assert_eq!(&samples[8..13], b"HG001");
+
+        assert!(bcf_to_zarr(path, &store, ZarrOptions::default()).is_err());
+        std::fs::remove_dir_all(&store).unwrap();
+
+        // fields must not replace the fixed arrays
+        let bcf = std::env::temp_dir().join("rust_bcf_test_zarr_clash.bcf");
+        for (info, format, message) in [
+            ("id", "DP", "INFO id clashes with the array variant_id"),
+            (
+                "DP",
+                "genotype",
+                "FORMAT genotype clashes with the array call_genotype",
+            ),
+        ] {
+            let header = HeaderBuilder::new()
+                .contig("chr1", None)
+                .info(info, InfoNumber::Count(1), InfoType::Integer, "Clash")
+                .format(format, InfoNumber::Count(1), InfoType::Integer, "Clash")
+                .sample("S1")
+                .build()
+                .unwrap();
+            BcfWriter::to_path(&bcf, &header, Compression::Uncompressed)
+                .unwrap()
+                .finish()
+                .unwrap();
+            let error = bcf_to_zarr(&bcf, &store, ZarrOptions::default()).unwrap_err();
+            assert_eq!(error.to_string(), message);
+            assert!(!store.exists());
+        }
+        std::fs::remove_file(&bcf).unwrap();
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_genotype_matrix() {
+        use crate::matrix::{GenotypeSelection, FILL_ALLELE, MISSING_ALLELE};
+
+        let path = "resources/example.uncompressed.bcf";
+        let genotypes = BcfRecords::from_path(path)
+            .unwrap()
+            .map(|record| record.genotypes())
+            .collect::<Vec<_>>();
+        let selection = GenotypeSelection::default();
+        let calls = BcfRecords::from_path(path)
+            .unwrap()
+            .genotype_calls(&selection)
+            .unwrap();
+        let dosages = BcfRecords::from_path(path)
+            .unwrap()
+            .dosages(&selection)
+            .unwrap();
+        assert_eq!(dosages.dim(), (genotypes.len(), 3));
+        assert_eq!(calls.dim(), (genotypes.len(), 3, 2));
+        for (i, record) in genotypes.iter().enumerate() {
+            for (j, call) in record.iter().enumerate() {
+                let alleles = call.iter().map(|a| a.index()).collect::<Vec<_>>();
+                for k in 0..2 {
+                    let expected = match alleles.get(k) {
+                        Some(Some(index)) => *index as i8,
+                        Some(None) => MISSING_ALLELE,
+                        None => FILL_ALLELE,
+                    };
+                    assert_eq!(calls[[i, j, k]], expected);
+                }
+                let expected = if alleles.iter().any(Option::is_none) {
+                    MISSING_ALLELE
+                } else {
+                    alleles.iter().filter(|a| **a != Some(0)).count() as i8
+                };
+                assert_eq!(dosages[[i, j]], expected);
+            }
+        }
+
+        let selection = GenotypeSelection {
+            samples: Some(vec!["HG003".into()]),
+            region: Some("chr1:817,187-10,000,000".parse().unwrap()),
+        };
+        let dosages = BcfRecords::from_path(path)
+            .unwrap()
+            .dosages(&selection)
+            .unwrap();
+        assert_eq!(dosages.dim().1, 1);
+        assert!(dosages.dim().0 < genotypes.len());
+        // regions select overlapping records, also by contig alias
+        let selection = GenotypeSelection {
+            samples: None,
+            region: Some("1:817,186-10,000,000".parse().unwrap()),
+        };
+        let overlapping = BcfRecords::from_path(path)
+            .unwrap()
+            .dosages(&selection)
+            .unwrap();
+        assert_eq!(overlapping.dim().0, dosages.dim().0 + 1);
+        let selection = GenotypeSelection {
+            samples: None,
+            region: Some("chrUn".parse().unwrap()),
+        };
+        let none = BcfRecords::from_path(path)
+            .unwrap()
+            .dosages(&selection)
+            .unwrap();
+        assert_eq!(none.dim(), (0, 3));
+        let selection = GenotypeSelection {
+            samples: Some(vec!["unknown".into()]),
+            ..Default::default()
+        };
+        assert!(BcfRecords::from_path(path)
+            .unwrap()
+            .dosages(&selection)
+            .is_err());
+    }
+
+    #[cfg(feature = "bgen")]
+    #[test]
+    fn test_bgen_writer() {
+        use crate::bgen::{BgenCompression, BgenOptions, BgenWriter};
+        use std::io::Cursor;
+
+        let header = HeaderBuilder::new()
+            .contig("chr1", Some(1000))
+            .format("GT", InfoNumber::Count(1), InfoType::String, "Genotype")
+            .format(
+                "GP",
+                InfoNumber::Genotypes,
+                InfoType::Float,
+                "Probabilities",
+            )
+            .sample("S1")
+            .sample("S2")
+            .sample("S3")
+            .build()
+            .unwrap();
+        let missing = f32::from_bits(crate::types::MISSING_FLOAT);
+        let record = RecordBuilder::new(header.clone().into())
+            .chrom("chr1")
+            .pos(9)
+            .alleles(["A", "C"])
+            .genotypes(&[
+                vec![GenotypeAllele::Unphased(0), GenotypeAllele::Unphased(1)],
+                vec![GenotypeAllele::Unphased(1), GenotypeAllele::Unphased(1)],
+                vec![
+                    GenotypeAllele::UnphasedMissing,
+                    GenotypeAllele::UnphasedMissing,
+                ],
+            ])
+            .format_f32(
+                "GP",
+                [vec![0.25, 0.5, 0.25], vec![missing; 3], vec![missing; 3]],
+            )
+            .build()
+            .unwrap();
+
+        for compression in [BgenCompression::Zlib, BgenCompression::Zstd] {
+            let options = BgenOptions {
+                compression,
+                bits: 8,
+            };
+            let mut writer = BgenWriter::new(Cursor::new(vec![]), &header, options).unwrap();
+            writer.write(&record).unwrap();
+            let bgen = writer.finish().unwrap().into_inner();
+            let u32_at =
+                |i: usize| u32::from_le_bytes([bgen[i], bgen[i + 1], bgen[i + 2], bgen[i + 3]]);
+            let u16_at = |i: usize| u16::from_le_bytes([bgen[i], bgen[i + 1]]) as usize;
+            // header block: number of variants and samples, magic, flags
+            assert_eq!((u32_at(8), u32_at(12)), (1, 3));
+            assert_eq!(&bgen[16..20], b"bgen");
+            assert_eq!(
+                u32_at(20) & 0b111111,
+                if compression == BgenCompression::Zlib {
+                    9
+                } else {
+                    10
+                }
+            );
+            assert_eq!(&bgen[32..36], b"\x02\x00S1");
+            // variant identifying data
+            let mut i = 4 + u32_at(0) as usize;
+            assert_eq!(&bgen[i + 2..i + 2 + u16_at(i)], b"chr1:10:A:C");
+            i += 2 + u16_at(i);
+            i += 2 + u16_at(i);
+            assert_eq!(&bgen[i + 2..i + 6], b"chr1");
+            i += 6;
+            assert_eq!((u32_at(i), u16_at(i + 4)), (10, 2));
+            i += 6 + 2 * 5;
+            let length = u32_at(i) as usize;
+            assert_eq!(i + 4 + length, bgen.len());
+            let compressed = &bgen[i + 8..];
+            let data = match compression {
+                BgenCompression::Zlib => {
+                    let mut data = vec![];
+                    flate2::read::ZlibDecoder::new(compressed)
+                        .read_to_end(&mut data)
+                        .unwrap();
+                    data
+                }
+                BgenCompression::Zstd => zstd::decode_all(compressed).unwrap(),
+            };
+            assert_eq!(data.len(), u32_at(i + 4) as usize);
+            assert_eq!(
+                data,
+                [3, 0, 0, 0, 2, 0, 2, 2, 2, 2, 0x82, 0, 8, 64, 127, 0, 0, 0, 0]
+            );
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_writer() {
+        use crate::sqlite::{SqliteOptions, SqliteWriter};
+        use rusqlite::types::Value;
+
+        let header = HeaderBuilder::new()
+            .contig("chr1", Some(1000))
+            .filter("q10", "Quality below 10")
+            .info("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .info(
+                "AF",
+                InfoNumber::AlternateAlleles,
+                InfoType::Float,
+                "Frequency",
+            )
+            .info("DB", InfoNumber::Count(0), InfoType::Flag, "dbSNP")
+            .format("GT", InfoNumber::Count(1), InfoType::String, "Genotype")
+            .format("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .format(
+                "AD",
+                InfoNumber::Alleles,
+                InfoType::Integer,
+                "Allele depths",
+            )
+            .sample("S1")
+            .sample("S2")
+            .build()
+            .unwrap();
+        let builder = RecordBuilder::new(header.clone().into())
+            .chrom("chr1")
+            .alleles(["A", "C", "G"])
+            .genotypes(&[
+                vec![GenotypeAllele::Unphased(0), GenotypeAllele::Phased(1)],
+                vec![GenotypeAllele::Unphased(1), GenotypeAllele::Unphased(2)],
+            ])
+            .format_i32("DP", [vec![10], vec![MISSING_INTEGER]])
+            .format_i32("AD", [vec![5, 5, 0], vec![0, 3, 4]]);
+        let records = vec![
+            builder
+                .clone()
+                .pos(99)
+                .id(b"rs1")
+                .qual(30.0)
+                .info_i32("DP", &[20])
+                .info_f32("AF", &[0.25, 0.5])
+                .info_flag("DB")
+                .build()
+                .unwrap(),
+            builder.pos(199).filter("q10").build().unwrap(),
+        ];
+
+        let options = SqliteOptions {
+            genotypes: Some(vec!["DP".into(), "AD".into()]),
+            batch_size: 1,
+        };
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        let mut writer = SqliteWriter::new(connection, &header, options).unwrap();
+        writer.write(records).unwrap();
+        let connection = writer.finish().unwrap();
+        let rows = |query: &str| -> Vec<Vec<Value>> {
+            let mut statement = connection.prepare(query).unwrap();
+            let n = statement.column_count();
+            statement
+                .query_map([], |row| (0..n).map(|i| row.get(i)).collect())
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+        assert_eq!(
+            rows("SELECT * FROM samples"),
+            [
+                [Value::Integer(1), Value::Text("S1".into())],
+                [Value::Integer(2), Value::Text("S2".into())]
+            ]
+        );
+        assert_eq!(
+            rows("SELECT * FROM variants ORDER BY POS"),
+            [
+                vec![
+                    Value::Integer(1),
+                    Value::Text("chr1".into()),
+                    Value::Integer(100),
+                    Value::Text("rs1".into()),
+                    Value::Text("A".into()),
+                    Value::Text("C,G".into()),
+                    Value::Real(30.0),
+                    Value::Null,
+                    Value::Integer(20),
+                    Value::Text("0.25,0.5".into()),
+                    Value::Integer(1),
+                ],
+                vec![
+                    Value::Integer(2),
+                    Value::Text("chr1".into()),
+                    Value::Integer(200),
+                    Value::Null,
+                    Value::Text("A".into()),
+                    Value::Text("C,G".into()),
+                    Value::Null,
+                    Value::Text("q10".into()),
+                    Value::Null,
+                    Value::Null,
+                    Value::Integer(0),
+                ]
+            ]
+        );
+        assert_eq!(
+            rows("SELECT * FROM genotypes WHERE variant_id = 1"),
+            [
+                [
+                    Value::Integer(1),
+                    Value::Integer(1),
+                    Value::Text("0|1".into()),
+                    Value::Integer(10),
+                    Value::Text("5,5,0".into())
+                ],
+                [
+                    Value::Integer(1),
+                    Value::Integer(2),
+                    Value::Text("1/2".into()),
+                    Value::Null,
+                    Value::Text("0,3,4".into())
+                ]
+            ]
+        );
+        assert!(SqliteWriter::new(
+            rusqlite::Connection::open_in_memory().unwrap(),
+            &header,
+            SqliteOptions {
+                genotypes: Some(vec!["XX".into()]),
+                ..Default::default()
+            }
+        )
+        .is_err());
+
+        // column names are case-insensitive and must not clash with the fixed columns
+        let header = HeaderBuilder::new()
+            .contig("chr1", None)
+            .info("DP", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .info("dp", InfoNumber::Count(1), InfoType::Integer, "Depth")
+            .format("gt", InfoNumber::Count(1), InfoType::String, "Genotype")
+            .format("Sample_ID", InfoNumber::Count(1), InfoType::String, "ID")
+            .build()
+            .unwrap();
+        let error = |header: &crate::types::Header, genotypes: Option<Vec<String>>| {
+            let connection = rusqlite::Connection::open_in_memory().unwrap();
+            let options = SqliteOptions {
+                genotypes,
+                ..Default::default()
+            };
+            SqliteWriter::new(connection, header, options)
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            error(&header, None),
+            "INFO DP and INFO dp clash in column INFO_dp of table variants"
+        );
+        let mut header = header;
+        header.remove_info("dp");
+        assert_eq!(
+            error(&header, Some(vec!["gt".into()])),
+            "FORMAT gt clashes with column GT of table genotypes"
+        );
+        assert_eq!(
+            error(&header, Some(vec!["Sample_ID".into()])),
+            "FORMAT Sample_ID clashes with column sample_id of table genotypes"
+        );
+    }
 }
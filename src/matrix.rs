@@ -0,0 +1,159 @@
+//! Dense genotype matrices as `ndarray` arrays (behind the `ndarray` feature).
+use std::io::Read;
+
+use anyhow::anyhow;
+use ndarray::{Array2, Array3};
+
+use crate::reader::BcfRecords;
+use crate::record::{BcfRecord, Record};
+use crate::region::{Interval, Region};
+use crate::types::{Header, RawVec, TypedVec, END_OF_VECTOR_INT_8, MISSING_INTEGER, MISSING_INT_8};
+
+/// The value of missing alleles and dosages.
+pub const MISSING_ALLELE: i8 = -1;
+/// The value of non-existent alleles, i.e. beyond the ploidy of a call.
+pub const FILL_ALLELE: i8 = -2;
+
+/// The samples and records to include in a genotype matrix.
+#[derive(Debug, Clone, Default)]
+pub struct GenotypeSelection {
+    /// The samples (in this order), or `None` for all samples
+    pub samples: Option<Vec<String>>,
+    /// Only records which overlap this region, whose contig may be an alias of the header's
+    pub region: Option<Region>,
+}
+
+impl GenotypeSelection {
+    /// Returns the indices of the selected samples.
+    fn sample_indices(&self, samples: &[String]) -> anyhow::Result<Vec<usize>> {
+        match &self.samples {
+            None => Ok((0..samples.len()).collect()),
+            Some(selected) => selected
+                .iter()
+                .map(|name| {
+                    samples
+                        .iter()
+                        .position(|s| s == name)
+                        .ok_or_else(|| anyhow!("sample {} does not exist", name))
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the contig index and interval of the region, with `None` as index if the
+    /// header has no such contig (or alias of it).
+    fn contig_interval(&self, header: &Header) -> Option<(Option<usize>, Interval)> {
+        self.region
+            .as_ref()
+            .map(|region| (header.resolve_contig(region.contig()), *region.interval()))
+    }
+}
+
+/// Appends the allele indices of a GT value (`MISSING_ALLELE` for missing alleles).
+/// The common case of Int8 values is decoded directly from the encoded bytes.
+fn push_alleles(value: RawVec<'_>, alleles: &mut Vec<i8>) {
+    match value {
+        RawVec::Int8(bytes) => {
+            alleles.extend(bytes.iter().take_while(|&&b| b != END_OF_VECTOR_INT_8).map(
+                |&b| match b {
+                    MISSING_INT_8 => MISSING_ALLELE,
+                    b => (b >> 1) as i8 - 1,
+                },
+            ))
+        }
+        value => {
+            if let TypedVec::Int32(values) = TypedVec::from(value) {
+                alleles.extend(values.into_iter().map(|v| match v {
+                    MISSING_INTEGER => MISSING_ALLELE,
+                    v => ((v >> 1) - 1) as i8,
+                }))
+            }
+        }
+    }
+}
+
+impl<R: Read> BcfRecords<R> {
+    /// Calls `f` with the allele indices of each selected sample of each selected record.
+    fn for_each_call<F: FnMut(&[i8])>(
+        self,
+        selection: &GenotypeSelection,
+        mut f: F,
+    ) -> anyhow::Result<(usize, usize)> {
+        let samples = selection.sample_indices(self.header().samples())?;
+        let region = selection.contig_interval(self.header());
+        let contains = |record: &BcfRecord| match region {
+            None => true,
+            Some((contig, interval)) => {
+                contig == Some(record.contig_index()) && record.interval().overlaps(&interval)
+            }
+        };
+        let mut n_records = 0;
+        let mut alleles = vec![];
+        for record in self.filter(|record| contains(record)) {
+            n_records += 1;
+            let mut values = record.raw_format_field("GT").unwrap_or_default();
+            for &sample in &samples {
+                alleles.clear();
+                if sample < values.len() {
+                    push_alleles(
+                        std::mem::replace(&mut values[sample], RawVec::Missing),
+                        &mut alleles,
+                    );
+                }
+                f(&alleles);
+            }
+        }
+        Ok((n_records, samples.len()))
+    }
+
+    /// Returns the alt allele dosages (the number of non-reference alleles of each call) as
+    /// a records × samples matrix, with `MISSING_ALLELE` for calls with a missing allele.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::matrix::GenotypeSelection;
+    /// use rust_bcf::BcfRecords;
+    ///
+    /// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+    /// let selection = GenotypeSelection {
+    ///     samples: Some(vec!["HG003".into(), "HG001".into()]),
+    ///     region: Some("chr1:1-817186".parse().unwrap()),
+    /// };
+    /// let dosages = records.dosages(&selection).unwrap();
+    /// assert_eq!(dosages.dim(), (1, 2));
+    /// assert_eq!(dosages[[0, 1]], 2);
+    /// ```
+    pub fn dosages(self, selection: &GenotypeSelection) -> anyhow::Result<Array2<i8>> {
+        let mut dosages = vec![];
+        let shape = self.for_each_call(selection, |alleles| {
+            dosages.push(if alleles.is_empty() || alleles.contains(&MISSING_ALLELE) {
+                MISSING_ALLELE
+            } else {
+                alleles.iter().filter(|&&a| a > 0).count() as i8
+            })
+        })?;
+        Ok(Array2::from_shape_vec(shape, dosages)?)
+    }
+
+    /// Returns the allele indices of all calls as a records × samples × ploidy array, where
+    /// ploidy is the maximum ploidy of all calls. Missing alleles are `MISSING_ALLELE`, and
+    /// calls of lower ploidy are padded with `FILL_ALLELE`.
+    pub fn genotype_calls(self, selection: &GenotypeSelection) -> anyhow::Result<Array3<i8>> {
+        let mut alleles = vec![];
+        let mut lengths = vec![];
+        let (n_records, n_samples) = self.for_each_call(selection, |call| {
+            alleles.extend_from_slice(call);
+            lengths.push(call.len());
+        })?;
+        let ploidy = lengths.iter().copied().max().unwrap_or(0);
+        let mut array = Array3::from_elem((n_records, n_samples, ploidy), FILL_ALLELE);
+        let mut alleles = alleles.into_iter();
+        for (i, length) in lengths.into_iter().enumerate() {
+            for (j, allele) in alleles.by_ref().take(length).enumerate() {
+                array[[i / n_samples, i % n_samples, j]] = allele;
+            }
+        }
+        Ok(array)
+    }
+}
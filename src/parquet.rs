@@ -0,0 +1,186 @@
+//! Export of records into Parquet files (behind the `parquet` feature).
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::bail;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::arrow::{ArrowConverter, ArrowOptions, TableLayout};
+use crate::reader::BcfRecords;
+use crate::record::Record;
+use crate::types::Header;
+
+/// The content and layout of a Parquet file, see `ParquetWriter`.
+#[derive(Debug, Clone)]
+pub struct ParquetOptions {
+    /// Only write the columns of records, i.e. no FORMAT fields
+    pub sites_only: bool,
+    /// The INFO fields to write, or `None` for all fields of the header
+    pub info: Option<Vec<String>>,
+    /// The FORMAT fields to write, or `None` for all fields of the header
+    pub format: Option<Vec<String>>,
+    pub layout: TableLayout,
+    /// If set, each row group only contains records of a single contig and of a single window
+    /// of this many base pairs, which allows skipping row groups when querying a region
+    pub region_size: Option<u32>,
+    /// The maximum number of rows per row group
+    pub row_group_size: usize,
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        ParquetOptions {
+            sites_only: false,
+            info: None,
+            format: None,
+            layout: TableLayout::Wide,
+            region_size: None,
+            row_group_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Writes records into a Snappy-compressed Parquet file with the schema of an `ArrowConverter`.
+///
+/// Call `finish` when done, which writes the last row group and the file footer.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::arrow::TableLayout;
+/// use rust_bcf::parquet::{ParquetOptions, ParquetWriter};
+/// use rust_bcf::BcfRecords;
+///
+/// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+/// let options = ParquetOptions {
+///     format: Some(vec!["GT".into()]),
+///     layout: TableLayout::Long,
+///     region_size: Some(1_000_000),
+///     ..Default::default()
+/// };
+/// let mut writer = ParquetWriter::new(vec![], records.header(), options).unwrap();
+/// writer.write(records).unwrap();
+/// let parquet = writer.finish().unwrap();
+/// assert_eq!(&parquet[..4], b"PAR1");
+/// ```
+pub struct ParquetWriter<W: Write + Send> {
+    converter: ArrowConverter,
+    inner: ArrowWriter<W>,
+    region_size: Option<u32>,
+    /// The contig and window of the current row group
+    region: Option<(String, u32)>,
+}
+
+impl ParquetWriter<BufWriter<File>> {
+    pub fn to_path<P: AsRef<Path>>(
+        path: P,
+        header: &Header,
+        options: ParquetOptions,
+    ) -> anyhow::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Self::new(file, header, options)
+    }
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Creates a new writer with the schema derived from the given header, see `ArrowConverter`;
+    /// fails if a selected field is not defined in the header.
+    pub fn new(inner: W, header: &Header, options: ParquetOptions) -> anyhow::Result<Self> {
+        if options.region_size == Some(0) {
+            bail!("region size must be positive");
+        }
+        let arrow_options = ArrowOptions {
+            batch_size: options
+                .row_group_size
+                .min(ArrowOptions::default().batch_size),
+            info: options.info,
+            format: if options.sites_only {
+                Some(vec![])
+            } else {
+                options.format
+            },
+            layout: if options.sites_only {
+                TableLayout::Wide
+            } else {
+                options.layout
+            },
+        };
+        let converter = ArrowConverter::new(header, arrow_options)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(options.row_group_size)
+            .build();
+        let inner = ArrowWriter::try_new(inner, converter.schema(), Some(properties))?;
+        Ok(ParquetWriter {
+            converter,
+            inner,
+            region_size: options.region_size,
+            region: None,
+        })
+    }
+
+    /// Writes all given records, which have to be sorted by position if `region_size` is set.
+    pub fn write<R: Record, I: IntoIterator<Item = R>>(
+        &mut self,
+        records: I,
+    ) -> anyhow::Result<()> {
+        let mut batch = vec![];
+        for record in records {
+            if let Some(region_size) = self.region_size {
+                let region = (record.try_chrom()?.to_string(), record.pos() / region_size);
+                if self.region.as_ref() != Some(&region) {
+                    self.write_batch(&batch)?;
+                    batch.clear();
+                    self.inner.flush()?;
+                    self.region = Some(region);
+                }
+            }
+            batch.push(record);
+            if batch.len() == self.converter.batch_size() {
+                self.write_batch(&batch)?;
+                batch.clear();
+            }
+        }
+        self.write_batch(&batch)
+    }
+
+    fn write_batch<R: Record>(&mut self, records: &[R]) -> anyhow::Result<()> {
+        if !records.is_empty() {
+            self.inner.write(&self.converter.batch(records)?)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the last row group and the footer, and returns the inner writer.
+    pub fn finish(self) -> anyhow::Result<W> {
+        Ok(self.inner.into_inner()?)
+    }
+}
+
+/// Converts a BCF file into a Parquet file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_bcf::parquet::{bcf_to_parquet, ParquetOptions};
+///
+/// let options = ParquetOptions {
+///     sites_only: true,
+///     ..Default::default()
+/// };
+/// bcf_to_parquet("input.bcf", "sites.parquet", options).unwrap();
+/// ```
+pub fn bcf_to_parquet<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: ParquetOptions,
+) -> anyhow::Result<()> {
+    let records = BcfRecords::from_path(input)?;
+    let mut writer = ParquetWriter::to_path(output, records.header(), options)?;
+    writer.write(records)?;
+    writer.finish()?.flush()?;
+    Ok(())
+}
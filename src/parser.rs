@@ -1,9 +1,6 @@
 use indexmap::IndexMap;
 use std::collections::HashMap;
 use std::convert::TryFrom;
-#[cfg(not(feature = "sync"))]
-use std::rc::Rc;
-#[cfg(feature = "sync")]
 use std::sync::Arc;
 
 use itertools::Itertools;
@@ -23,8 +20,9 @@ use nom::{
 };
 
 use crate::types::{
-    Header, HeaderContig, HeaderFilter, HeaderFormat, HeaderInfo, HeaderKey, HeaderValue, InfoKey,
-    InfoNumber, RawVec, Text, TypeDescriptor, TypeKind, Version,
+    Header, HeaderContig, HeaderFilter, HeaderFormat, HeaderInfo, HeaderKey, HeaderLine,
+    HeaderValue, InfoKey, InfoNumber, Layout, RawVec, Text, TypeDescriptor, TypeKind, Version,
+    PASS_FILTER,
 };
 use crate::BcfRecord;
 
@@ -216,8 +214,7 @@ pub(crate) fn record_length(input: &[u8]) -> IResult<&[u8], (u32, u32)> {
 pub(crate) fn raw_record_from_length(
     l_shared: u32,
     l_indiv: u32,
-    #[cfg(not(feature = "sync"))] header: Rc<Header>,
-    #[cfg(feature = "sync")] header: Arc<Header>,
+    header: Arc<Header>,
     input: &[u8],
 ) -> IResult<&[u8], BcfRecord> {
     let (shared, input) = input.split_at(l_shared as usize);
@@ -261,28 +258,44 @@ fn delimited_string(input: &[u8]) -> IResult<&[u8], &[u8]> {
     )(input)
 }
 
-/// This reads `key=value` pairs (in the header)
-fn keys_and_values(input: &[u8]) -> IResult<&[u8], Vec<(&str, &str)>> {
-    fn key_value(input: &[u8]) -> IResult<&[u8], (&str, &str)> {
-        let (input, (key, value)) = separated_pair(
+type KeysAndValues<'a> = (Vec<(&'a str, &'a str)>, Layout);
+
+/// This reads `key=value` pairs (in the header), in order of appearance.
+/// Additionally returns the `Layout` of the pairs, i.e. the keys and whether the value was quoted.
+fn keys_and_values(input: &[u8]) -> IResult<&[u8], KeysAndValues<'_>> {
+    fn key_value(input: &[u8]) -> IResult<&[u8], ((&str, &str), bool)> {
+        let (input, (key, (value, quoted))) = separated_pair(
             is_not("<,=\n"),
             tag(b"="),
-            alt((delimited_string, is_not(">,=\n"))),
+            alt((
+                map(delimited_string, |v| (v, true)),
+                map(is_not(">,=\n"), |v| (v, false)),
+            )),
         )(input)?;
         Ok((
             input,
             (
-                std::str::from_utf8(key).unwrap(),
-                std::str::from_utf8(value).unwrap(),
+                (
+                    std::str::from_utf8(key).unwrap(),
+                    std::str::from_utf8(value).unwrap(),
+                ),
+                quoted,
             ),
         ))
     }
-    separated_list0(tag(","), key_value)(input)
+    let (input, pairs) = separated_list0(tag(","), key_value)(input)?;
+    let layout = pairs
+        .iter()
+        .map(|((key, _), quoted)| (key.to_string(), *quoted))
+        .collect();
+    Ok((
+        input,
+        (pairs.into_iter().map(|(pair, _)| pair).collect(), layout),
+    ))
 }
 
-fn header_value_mapping(input: &[u8]) -> IResult<&[u8], HashMap<&str, &str>> {
-    let (input, mapping) = keys_and_values(input)?;
-    Ok((input, mapping.into_iter().collect()))
+fn header_value_mapping(input: &[u8]) -> IResult<&[u8], KeysAndValues<'_>> {
+    delimited(tag("<"), keys_and_values, tag(">"))(input)
 }
 
 fn header_line(input: &[u8]) -> IResult<&[u8], &[u8]> {
@@ -296,29 +309,75 @@ fn header_entry(input: &[u8]) -> IResult<&[u8], (HeaderKey, HeaderValue)> {
     let key = std::str::from_utf8(key).unwrap();
     let value = match key {
         "INFO" => {
-            let data = delimited(tag("<"), header_value_mapping, tag(">"))(value)?.1;
-            HeaderValue::Info(HeaderInfo::from(data.into_iter().collect_vec()))
+            let (data, layout) = header_value_mapping(value)?.1;
+            let mut info = HeaderInfo::from(data);
+            info.layout = layout;
+            HeaderValue::Info(info)
         }
         "FORMAT" => {
-            let data = delimited(tag("<"), header_value_mapping, tag(">"))(value)?.1;
-            HeaderValue::Format(HeaderFormat::from(data.into_iter().collect_vec()))
+            let (data, layout) = header_value_mapping(value)?.1;
+            let mut format = HeaderFormat::from(data);
+            format.layout = layout;
+            HeaderValue::Format(format)
         }
         "contig" => {
-            let data = delimited(tag("<"), header_value_mapping, tag(">"))(value)?.1;
-            HeaderValue::Contig(HeaderContig::from(data.into_iter().collect_vec()))
+            let (data, layout) = header_value_mapping(value)?.1;
+            let mut contig = HeaderContig::from(data);
+            contig.layout = layout;
+            HeaderValue::Contig(contig)
         }
         "FILTER" => {
-            let data = delimited(tag("<"), header_value_mapping, tag(">"))(value)?.1;
-            HeaderValue::Filter(HeaderFilter::from(data.into_iter().collect_vec()))
+            let (data, layout) = header_value_mapping(value)?.1;
+            let mut filter = HeaderFilter::from(data);
+            filter.layout = layout;
+            HeaderValue::Filter(filter)
         }
         _ => HeaderValue::String(std::str::from_utf8(value).unwrap().into()),
     };
     Ok((input, (key, value)))
 }
 
+/// FILTER, INFO and FORMAT IDs share a dictionary of strings, whose offsets are usually given
+/// explicitly via `IDX` in BCF headers. If none of the lines specifies an `IDX` (as in VCF
+/// headers), offsets are assigned in order of first appearance, with `PASS` always at offset 0.
+fn assign_implicit_idx(entries: &mut [(HeaderKey<'_>, HeaderValue)]) {
+    fn id_and_idx(value: &mut HeaderValue) -> Option<(&str, &mut usize)> {
+        match value {
+            HeaderValue::Filter(f) => Some((&f.id, &mut f.idx)),
+            HeaderValue::Info(i) => Some((&i.id, &mut i.idx)),
+            HeaderValue::Format(f) => Some((&f.id, &mut f.idx)),
+            _ => None,
+        }
+    }
+    let explicit = entries
+        .iter_mut()
+        .filter_map(|(_, v)| id_and_idx(v))
+        .any(|(_, idx)| *idx != 0);
+    if explicit {
+        return;
+    }
+    let mut dictionary: HashMap<String, usize> = HashMap::new();
+    dictionary.insert(PASS_FILTER.into(), 0);
+    for (id, idx) in entries.iter_mut().filter_map(|(_, v)| id_and_idx(v)) {
+        let next = dictionary.len();
+        *idx = *dictionary.entry(id.into()).or_insert(next);
+    }
+}
+
 pub(crate) fn header(header_length: u32, input: &[u8]) -> IResult<&[u8], Header> {
     let (input, header) = take(header_length)(input)?;
-    let (_header, entries) = many0(header_entry)(header)?;
+    let (_header, mut entries) = many0(header_entry)(header)?;
+    assign_implicit_idx(&mut entries);
+    let lines = entries
+        .iter()
+        .map(|(key, value)| match value {
+            HeaderValue::Info(info) => HeaderLine::Info(info.id.clone()),
+            HeaderValue::Format(format) => HeaderLine::Format(format.id.clone()),
+            HeaderValue::Filter(filter) => HeaderLine::Filter(filter.id.clone()),
+            HeaderValue::Contig(contig) => HeaderLine::Contig(contig.id.clone()),
+            HeaderValue::String(_) => HeaderLine::Meta(key.to_string()),
+        })
+        .collect();
     let mut entries = entries
         .into_iter()
         .map(|(k, v)| (k.into(), v))
@@ -326,6 +385,7 @@ pub(crate) fn header(header_length: u32, input: &[u8]) -> IResult<&[u8], Header>
     let info = entries.remove("INFO").unwrap_or_else(Vec::new);
     let format = entries.remove("FORMAT").unwrap_or_else(Vec::new);
     let contigs = entries.remove("contig").unwrap_or_else(Vec::new);
+    let filter = entries.remove("FILTER").unwrap_or_else(Vec::new);
 
     let info: IndexMap<usize, HeaderInfo> = info
         .into_iter()
@@ -348,6 +408,18 @@ pub(crate) fn header(header_length: u32, input: &[u8]) -> IResult<&[u8], Header>
         .map(|(idx, hi)| (hi.id.clone(), *idx))
         .collect();
 
+    let filter: IndexMap<usize, HeaderFilter> = filter
+        .into_iter()
+        .filter_map(|v| match v {
+            HeaderValue::Filter(filter) => Some((filter.idx, filter)),
+            _ => None,
+        })
+        .collect();
+    let filter_tag_to_offset = filter
+        .iter()
+        .map(|(idx, hf)| (hf.id.clone(), *idx))
+        .collect();
+
     let (_rest, samples) = delimited(tag(b"#"), is_not("\n"), tag("\n\x00"))(_header)?;
     assert!(_rest.is_empty());
     let samples = std::str::from_utf8(samples)
@@ -370,7 +442,11 @@ pub(crate) fn header(header_length: u32, input: &[u8]) -> IResult<&[u8], Header>
             .collect(),
         format,
         format_tag_to_offset,
+        filter,
+        filter_tag_to_offset,
         samples,
+        aliases: Default::default(),
+        lines,
     };
     Ok((input, header))
 }
@@ -0,0 +1,210 @@
+//! Export of genotypes into PLINK 1.9 binary filesets (`.bed`, `.bim` and `.fam`).
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::reader::BcfRecords;
+use crate::record::{GenotypeAllele, Record};
+use crate::types::Header;
+
+/// The magic bytes of a `.bed` file in SNP-major mode.
+const BED_MAGIC: [u8; 3] = [0x6c, 0x1b, 0x01];
+
+/// The 2-bit codes of `.bed` files, by number of A1 (i.e. ALT) alleles.
+const HOM_A1: u8 = 0b00;
+const MISSING: u8 = 0b01;
+const HET: u8 = 0b10;
+const HOM_A2: u8 = 0b11;
+
+/// How to deal with records with more than one ALT allele, which PLINK 1.9 cannot represent.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Multiallelic {
+    /// Skip such records (and report them)
+    Skip,
+    /// Write one variant per ALT allele, where calls of the other ALT alleles count as REF
+    Split,
+}
+
+/// Writes biallelic variants as PLINK 1.9 binary fileset, i.e. the SNP-major `.bed` file,
+/// the `.bim` file and the `.fam` file with one family per sample.
+///
+/// ALT is allele 1 and REF allele 2, as with `plink --vcf`; variants without ID get the ID
+/// `CHROM:POS:REF:ALT`, which is also used for all variants split from a multiallelic record.
+/// Haploid calls are written as homozygous, calls of higher ploidy as missing.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::plink::{Multiallelic, PlinkWriter};
+/// use rust_bcf::BcfRecords;
+///
+/// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+/// let (mut bed, mut bim, mut fam) = (vec![], vec![], vec![]);
+/// let mut writer =
+///     PlinkWriter::new(&mut bed, &mut bim, &mut fam, records.header(), Multiallelic::Skip).unwrap();
+/// for record in records {
+///     writer.write(&record).unwrap();
+/// }
+/// let skipped = writer.finish().unwrap();
+/// assert!(skipped.is_empty());
+/// assert_eq!(&bed[..3], &[0x6c, 0x1b, 0x01]);
+/// assert!(String::from_utf8(fam).unwrap().starts_with("HG001\tHG001\t0\t0\t0\t-9\n"));
+/// ```
+pub struct PlinkWriter<W: Write> {
+    bed: W,
+    bim: W,
+    fam: W,
+    multiallelic: Multiallelic,
+    n_samples: usize,
+    /// Messages about skipped records
+    skipped: Vec<String>,
+}
+
+impl PlinkWriter<BufWriter<File>> {
+    /// Creates `<prefix>.bed`, `<prefix>.bim` and `<prefix>.fam`.
+    pub fn to_prefix<P: AsRef<Path>>(
+        prefix: P,
+        header: &Header,
+        multiallelic: Multiallelic,
+    ) -> anyhow::Result<Self> {
+        let prefix = prefix.as_ref().to_string_lossy().into_owned();
+        let create = |extension: &str| -> anyhow::Result<BufWriter<File>> {
+            Ok(BufWriter::new(File::create(format!(
+                "{}.{}",
+                prefix, extension
+            ))?))
+        };
+        Self::new(
+            create("bed")?,
+            create("bim")?,
+            create("fam")?,
+            header,
+            multiallelic,
+        )
+    }
+}
+
+impl<W: Write> PlinkWriter<W> {
+    /// Creates a new writer and writes the `.bed` magic bytes and all samples into `.fam`.
+    pub fn new(
+        mut bed: W,
+        bim: W,
+        mut fam: W,
+        header: &Header,
+        multiallelic: Multiallelic,
+    ) -> anyhow::Result<Self> {
+        bed.write_all(&BED_MAGIC)?;
+        for sample in header.samples() {
+            // family ID, individual ID, father, mother, sex (unknown), phenotype (missing)
+            writeln!(fam, "{}\t{}\t0\t0\t0\t-9", sample, sample)?;
+        }
+        Ok(PlinkWriter {
+            bed,
+            bim,
+            fam,
+            multiallelic,
+            n_samples: header.samples().len(),
+            skipped: vec![],
+        })
+    }
+
+    /// Writes a record, or one variant per ALT allele with `Multiallelic::Split`.
+    pub fn write<R: Record>(&mut self, record: &R) -> anyhow::Result<()> {
+        let chrom = record.try_chrom()?;
+        let alt_alleles = record.alt_alleles();
+        if alt_alleles.len() > 1 && self.multiallelic == Multiallelic::Skip {
+            self.skipped.push(format!(
+                "{}:{} skipped, {} ALT alleles",
+                chrom,
+                record.pos() + 1,
+                alt_alleles.len()
+            ));
+            return Ok(());
+        }
+        let genotypes = record.genotypes();
+        let ref_allele = String::from_utf8_lossy(&record.ref_allele()).into_owned();
+        let id = record.id();
+        // monomorphic sites have no ALT allele, which is "0" in PLINK
+        let alt_alleles = if alt_alleles.is_empty() {
+            vec![b"0".to_vec()]
+        } else {
+            alt_alleles
+        };
+        for (i, alt) in alt_alleles.iter().enumerate() {
+            let alt = String::from_utf8_lossy(alt);
+            let id = if id.is_empty() || id == b"." || alt_alleles.len() > 1 {
+                format!("{}:{}:{}:{}", chrom, record.pos() + 1, ref_allele, alt)
+            } else {
+                String::from_utf8_lossy(&id).into_owned()
+            };
+            writeln!(
+                self.bim,
+                "{}\t{}\t0\t{}\t{}\t{}",
+                chrom,
+                id,
+                record.pos() + 1,
+                alt,
+                ref_allele
+            )?;
+            let mut bytes = vec![0u8; self.n_samples.div_ceil(4)];
+            for sample in 0..self.n_samples {
+                let call = genotypes.get(sample).map_or(&[][..], Vec::as_slice);
+                let code = genotype_code(call, i as u32 + 1);
+                bytes[sample / 4] |= code << (2 * (sample % 4));
+            }
+            self.bed.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes all files and returns a message for each skipped record.
+    pub fn finish(mut self) -> anyhow::Result<Vec<String>> {
+        self.bed.flush()?;
+        self.bim.flush()?;
+        self.fam.flush()?;
+        Ok(self.skipped)
+    }
+}
+
+/// Returns the `.bed` code of a call w.r.t. the given ALT allele; all other alleles count as REF.
+fn genotype_code(call: &[GenotypeAllele], alt: u32) -> u8 {
+    let indices = call
+        .iter()
+        .map(|allele| allele.index())
+        .collect::<Option<Vec<_>>>();
+    let n_alt = match indices {
+        Some(indices) => indices.iter().filter(|&&index| index == alt).count(),
+        None => return MISSING,
+    };
+    match (call.len(), n_alt) {
+        (1, 0) | (2, 0) => HOM_A2,
+        (2, 1) => HET,
+        (1, 1) | (2, 2) => HOM_A1,
+        _ => MISSING,
+    }
+}
+
+/// Converts a BCF file into the PLINK 1.9 binary fileset `<prefix>.{bed,bim,fam}` and returns
+/// a message for each skipped record.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_bcf::plink::{bcf_to_plink, Multiallelic};
+///
+/// for skipped in bcf_to_plink("input.bcf", "output", Multiallelic::Split).unwrap() {
+///     eprintln!("{}", skipped);
+/// }
+/// ```
+pub fn bcf_to_plink<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    prefix: Q,
+    multiallelic: Multiallelic,
+) -> anyhow::Result<Vec<String>> {
+    let records = BcfRecords::from_path(input)?;
+    let mut writer = PlinkWriter::to_prefix(prefix, records.header(), multiallelic)?;
+    for record in records {
+        writer.write(&record)?;
+    }
+    writer.finish()
+}
@@ -1,57 +1,136 @@
 use std::mem::size_of;
 use std::ops::Range;
-use std::rc::Rc;
 
 use nom::multi::many_m_n;
 use nom::number::streaming::{le_f32, le_i16, le_i32, le_u24};
 use nom::IResult;
 
 use crate::parser::{raw_genotype_field, raw_info_pair, type_descriptor, typed_ints, typed_string};
+use crate::region::{Interval, Position};
 use crate::types::{
-    Header, HeaderValue, Text, TypeDescriptor, TypeKind, TypedVec, MISSING_FLOAT, NAN_FLOAT,
+    Header, HeaderContig, HeaderFilter, RawVec, Text, TypeDescriptor, TypeKind, TypedVec,
+    MISSING_FLOAT, MISSING_INTEGER, NAN_FLOAT, PASS_FILTER,
 };
 use itertools::Itertools;
 use nom::number::complete::le_u8;
-#[cfg(feature = "sync")]
 use std::sync::Arc;
 
 pub trait Record {
     fn id(&self) -> Text;
 
+    /// Returns the contig of this record, i.e. CHROM.
+    ///
+    /// # Panics
+    ///
+    /// `BcfRecord`s panic if their header has no contig with their contig index, which
+    /// `try_chrom` returns as error instead.
     fn chrom(&self) -> &str;
 
+    /// Returns the contig of this record, like `chrom`, or an error if it is not defined.
+    fn try_chrom(&self) -> anyhow::Result<&str> {
+        Ok(self.chrom())
+    }
+
     fn pos(&self) -> u32;
 
+    /// Returns POS as `Position`, see `Position::one_based` for VCF-style coordinates.
+    fn position(&self) -> Position {
+        Position::from_zero_based(self.pos())
+    }
+
+    /// Returns the interval covered by this record, i.e. from POS to INFO `END` (if given), or
+    /// else to the end of the reference allele; the interval contains at least POS.
+    fn interval(&self) -> Interval {
+        let start = self.pos();
+        // an END which cannot be decoded is ignored, like a missing one
+        let end = match self.try_info(b"END").ok().flatten() {
+            Some(TypedVec::Int32(end)) => end
+                .first()
+                .filter(|&&end| end != MISSING_INTEGER)
+                .map(|&end| end.max(0) as u32),
+            _ => None,
+        };
+        let end = end.unwrap_or(start.saturating_add(self.ref_allele().len() as u32));
+        Interval::from_zero_based(start, end.max(start.saturating_add(1)))
+    }
+
     fn ref_allele(&self) -> Text;
 
     fn alt_alleles(&self) -> Vec<Text>;
 
     fn qual(&self) -> Option<f32>;
 
+    /// Returns the filters of this record, i.e. FILTER.
+    ///
+    /// # Panics
+    ///
+    /// `BcfRecord`s panic if their header does not define one of their filter offsets, which
+    /// `try_filters` returns as error instead.
     fn filters(&self) -> Vec<&str>;
 
+    /// Returns the filters of this record, like `filters`, or an error if one is not defined.
+    fn try_filters(&self) -> anyhow::Result<Vec<&str>> {
+        Ok(self.filters())
+    }
+
+    fn filter_headers(&self) -> Vec<&HeaderFilter>;
+
+    /// Returns `true` if this record passed all filters, i.e. FILTER is exactly `PASS`.
+    ///
+    /// Note that a record whose filters have not been applied (`.` in VCF) is *not* passing,
+    /// and neither is a record with filters which are not defined in the header.
+    fn is_pass(&self) -> bool {
+        self.try_filters()
+            .is_ok_and(|filters| filters == [PASS_FILTER])
+    }
+
+    /// Returns `true` if FILTER contains the given filter ID.
+    fn has_filter(&self, filter: &str) -> bool {
+        self.try_filters()
+            .is_ok_and(|filters| filters.contains(&filter))
+    }
+
+    /// Returns the value of the given INFO tag, or `None` if the record does not have it.
+    ///
+    /// # Panics
+    ///
+    /// `VcfRecord`s panic if the value does not match the declared type, which `try_info`
+    /// returns as error instead.
     fn info(&self, tag: &[u8]) -> Option<TypedVec>;
 
+    /// Returns the value of the given INFO tag, like `info`, or an error if it cannot be decoded.
+    fn try_info(&self, tag: &[u8]) -> anyhow::Result<Option<TypedVec>> {
+        Ok(self.info(tag))
+    }
+
+    /// Returns the values of the given FORMAT tag for each sample, or `None` if the record does
+    /// not have it.
+    ///
+    /// # Panics
+    ///
+    /// `VcfRecord`s panic if a value does not match the declared type (or is not a valid
+    /// genotype, for GT), which `try_format` returns as error instead.
     fn format(&self, tag: &[u8]) -> Option<Vec<TypedVec>>;
 
+    /// Returns the values of the given FORMAT tag, like `format`, or an error if they cannot
+    /// be decoded.
+    fn try_format(&self, tag: &[u8]) -> anyhow::Result<Option<Vec<TypedVec>>> {
+        Ok(self.format(tag))
+    }
+
+    fn info_tags(&self) -> Vec<&str>;
+
+    fn format_tags(&self) -> Vec<&str>;
+
     fn genotypes(&self) -> Vec<Vec<GenotypeAllele>>;
 
     fn has_flag(&self, tag: &[u8]) -> bool;
 }
 
-#[cfg(feature = "sync")]
-unsafe impl Sync for BcfRecord {}
-
-#[cfg(feature = "sync")]
-unsafe impl Sync for Header {}
-
 #[derive(Debug, Clone)]
 pub struct BcfRecord {
     pub(crate) shared: Vec<u8>,
     pub(crate) format: Vec<u8>,
-    #[cfg(not(feature = "sync"))]
-    pub(crate) header: Rc<Header>,
-    #[cfg(feature = "sync")]
     pub(crate) header: Arc<Header>,
     id_start_bytepos: usize,
     allele_start_bytepos: usize,
@@ -62,26 +141,22 @@ const S_I32: usize = size_of::<i32>();
 const S_U32: usize = size_of::<u32>();
 const S_F32: usize = size_of::<f32>();
 
-const TYPE_DESCRIPTOR_LENGTH: usize = size_of::<u8>();
 const CHROM_BYTE_RANGE: Range<usize> = 0..S_I32;
 const POS_BYTE_RANGE: Range<usize> = S_I32..S_I32 * 2;
+const RLEN_BYTE_RANGE: Range<usize> = S_I32 * 2..S_I32 * 3;
 const QUAL_BYTE_RANGE: Range<usize> = S_I32 * 3..S_I32 * 3 + S_F32;
 
 impl BcfRecord {
-    pub(crate) fn new(
-        shared: Vec<u8>,
-        format: Vec<u8>,
-        #[cfg(not(feature = "sync"))] header: Rc<Header>,
-        #[cfg(feature = "sync")] header: Arc<Header>,
-    ) -> Self {
+    pub(crate) fn new(shared: Vec<u8>, format: Vec<u8>, header: Arc<Header>) -> Self {
         // The list of alleles starts right after ID
         let id_start_bytepos = S_I32 + S_I32 + S_I32 + S_F32 + S_I16 + S_I16 + S_U32;
         // however, ID is a "typed string" in bcf-speak, so we have to read the type descriptor (1 byte)
         // to know how long the ID is (and then skip those bytes)
-        let (_, TypeDescriptor { kind, num_elements }) =
+        let (remaining, TypeDescriptor { kind, num_elements }) =
             type_descriptor(&shared[id_start_bytepos..]).unwrap();
         assert_eq!(kind, TypeKind::String);
-        let allele_start_bytepos = id_start_bytepos + TYPE_DESCRIPTOR_LENGTH + num_elements;
+        // (the type descriptor may span several bytes, if the ID has 15 or more characters)
+        let allele_start_bytepos = shared.len() - remaining.len() + num_elements;
         Self {
             shared,
             format,
@@ -91,6 +166,33 @@ impl BcfRecord {
         }
     }
 
+    /// Returns the header this record was read with.
+    pub fn header(&self) -> &Arc<Header> {
+        &self.header
+    }
+
+    /// Returns the index of the contig of this record in the header, i.e. the encoded CHROM.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    ///
+    /// let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+    /// let record = records.next().unwrap();
+    /// assert_eq!(record.contig_index(), 0);
+    /// assert_eq!(record.contig().unwrap().length(), &Some(248956422));
+    /// ```
+    pub fn contig_index(&self) -> usize {
+        le_i32::<_, ()>(&self.shared[CHROM_BYTE_RANGE]).unwrap().1 as usize
+    }
+
+    /// Returns the header definition of the contig of this record, or `None` if the header
+    /// has no contig with this record's index.
+    pub fn contig(&self) -> Option<&HeaderContig> {
+        self.header.contig(self.contig_index())
+    }
+
     fn n_alleles(&self) -> usize {
         fn n_alleles_from_shared(shared: &[u8]) -> IResult<&[u8], i16> {
             let (remaining, v) =
@@ -118,6 +220,53 @@ impl BcfRecord {
         (n_fmt as usize, n_sample as usize)
     }
 
+    /// Returns the raw FILTER entries, i.e. offsets into the header dictionary.
+    fn filter_ids(&self) -> Vec<usize> {
+        // lazy access requires "reading" and discarding the alleles, since these have unknown size
+        let (_, byte_pos) = self.alleles();
+        let (_, filter_ids) = typed_ints(&self.shared[byte_pos..]).unwrap();
+        filter_ids
+    }
+
+    /// Returns all INFO entries as pairs of offset into the header dictionary and raw value.
+    fn raw_info_fields(&self) -> Vec<(usize, RawVec<'_>)> {
+        let (_, byte_pos) = self.alleles();
+        let (mut input, _) = typed_ints(&self.shared[byte_pos..]).unwrap();
+        (0..self.n_info())
+            .map(|_| {
+                let (i, info) = raw_info_pair(input).unwrap();
+                input = i;
+                info
+            })
+            .collect()
+    }
+
+    /// Returns all FORMAT entries as pairs of offset into the header dictionary and raw values,
+    /// one per sample.
+    fn raw_format_fields(&self) -> Vec<(usize, Vec<RawVec<'_>>)> {
+        if self.format.is_empty() {
+            return vec![];
+        }
+        let (n_fmt, n_sample) = self.n_fmt_n_sample();
+        let mut input = &self.format[..];
+        (0..n_fmt)
+            .map(|_| {
+                let (i, fmt) = raw_genotype_field(n_sample as u32, input).unwrap();
+                input = i;
+                fmt
+            })
+            .collect()
+    }
+
+    /// Returns the raw values of the given FORMAT field, one per sample.
+    pub(crate) fn raw_format_field(&self, tag: &str) -> Option<Vec<RawVec<'_>>> {
+        let offset = *self.header.format_tag_to_offset.get(tag)?;
+        self.raw_format_fields()
+            .into_iter()
+            .find(|(o, _)| *o == offset)
+            .map(|(_, values)| values)
+    }
+
     fn alleles(&self) -> (Vec<Text>, usize) {
         let n_allele = self.n_alleles();
         let start = self.allele_start_bytepos;
@@ -164,13 +313,36 @@ impl Record for BcfRecord {
     ///     assert_eq!(record.chrom(), "chr1")
     /// }
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the header has no contig with this record's index; use `try_chrom` (or
+    /// `BcfRecord::contig`) for records of untrusted files.
     fn chrom(&self) -> &str {
-        fn chrom_from_shared(shared: &[u8]) -> IResult<&[u8], i32> {
-            let (remaining, v) = le_i32(&shared[CHROM_BYTE_RANGE])?;
-            Ok((remaining, v))
+        self.try_chrom().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns the contig of this record, or an error if the header has no contig with this
+    /// record's index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    /// use rust_bcf::Record;
+    ///
+    /// let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+    /// let record = records.next().unwrap();
+    /// assert_eq!(record.try_chrom().unwrap(), "chr1");
+    /// ```
+    fn try_chrom(&self) -> anyhow::Result<&str> {
+        match self.contig() {
+            Some(contig) => Ok(contig.id()),
+            None => anyhow::bail!(
+                "contig index {} is not defined in the header",
+                self.contig_index()
+            ),
         }
-        let idx = chrom_from_shared(&self.shared).unwrap().1 as usize;
-        &self.header.contigs[idx].id
     }
 
     /// Returns the position of this record, i.e. POS, 0-based.
@@ -214,6 +386,27 @@ impl Record for BcfRecord {
         ref_allele
     }
 
+    /// Returns the interval covered by this record, as given by its encoded length (`rlen`),
+    /// i.e. from POS to INFO `END` (if given), or else to the end of the reference allele.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::region::Interval;
+    /// use rust_bcf::BcfRecords;
+    /// use rust_bcf::Record;
+    ///
+    /// let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+    /// let record = records.next().unwrap();
+    /// assert_eq!(record.interval(), Interval::from_zero_based(817185, 817186));
+    /// assert_eq!(record.position().one_based(), 817186);
+    /// ```
+    fn interval(&self) -> Interval {
+        let start = self.pos();
+        let rlen = le_i32::<_, ()>(&self.shared[RLEN_BYTE_RANGE]).unwrap().1;
+        Interval::from_zero_based(start, start.saturating_add(rlen.max(1) as u32))
+    }
+
     /// Returns the alternative alleles of this record, i.e. ALT.
     ///
     /// # Examples
@@ -272,6 +465,7 @@ impl Record for BcfRecord {
     }
 
     /// Returns the list of filters for this record, i.e. FILTER.
+    /// If not set (equivalent to `.` in VCF), return an empty list.
     ///
     /// # Examples
     ///
@@ -284,25 +478,51 @@ impl Record for BcfRecord {
     ///     assert_eq!(record.filters(), ["PASS"])
     /// }
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the header does not define one of the filter offsets; use `try_filters` for records
+    /// of untrusted files.
     fn filters(&self) -> Vec<&str> {
-        // lazy access requires "reading" and discarding the alleles, since these have unknown size
-        let (_, byte_pos) = self.alleles();
+        self.try_filters().unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        let (_, filter_ids) = typed_ints(&self.shared[byte_pos..]).unwrap();
-        let filters = self.header.meta.get_vec("FILTER").unwrap();
-        filter_ids
-            .iter()
-            .map(|&i| {
-                let value = &filters[i];
-                if let HeaderValue::Filter(f) = value {
-                    f.id.as_ref()
-                } else {
-                    unreachable!()
-                }
+    /// Returns the list of filters for this record, or an error if the header does not define
+    /// one of their offsets.
+    fn try_filters(&self) -> anyhow::Result<Vec<&str>> {
+        self.filter_ids()
+            .into_iter()
+            .map(|offset| match self.header.filter.get(&offset) {
+                Some(filter) => Ok(filter.id.as_ref()),
+                // PASS is always at offset 0 of the dictionary, even if the header omits it
+                None if offset == 0 => Ok(PASS_FILTER),
+                None => anyhow::bail!("FILTER offset {} is not defined in the header", offset),
             })
             .collect()
     }
 
+    /// Returns the header definitions of the filters for this record.
+    /// Filters without a definition in the header (e.g. an implicit PASS) are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    /// use rust_bcf::Record;
+    ///
+    /// let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+    /// for record in records {
+    ///     let filters = record.filter_headers();
+    ///     assert_eq!(filters[0].description(), "All filters passed")
+    /// }
+    /// ```
+    fn filter_headers(&self) -> Vec<&HeaderFilter> {
+        self.filter_ids()
+            .into_iter()
+            .filter_map(|offset| self.header.filter.get(&offset))
+            .collect()
+    }
+
     /// For a given INFO tag, return its contents.
     ///
     /// # Examples
@@ -363,33 +583,52 @@ impl Record for BcfRecord {
     /// }
     /// ```
     fn format(&self, tag: &[u8]) -> Option<Vec<TypedVec>> {
-        if self.format.is_empty() {
-            return None;
-        }
-        let (n_fmt, n_sample) = self.n_fmt_n_sample();
+        let tag = std::str::from_utf8(tag).unwrap();
+        // convert RawVec to TypedVec
+        self.raw_format_field(tag)
+            .map(|values| values.into_iter().map(Into::into).collect_vec())
+    }
 
-        let tag = std::str::from_utf8(tag).unwrap().to_owned();
-        let mut input = &self.format[..];
-        (0..n_fmt as usize)
-            .map(|_| {
-                // (note that raw_info_pair does not do type conversion between byteslice and
-                // requested type)
-                let (i, fmt) = raw_genotype_field(n_sample as u32, input).unwrap();
-                input = i;
-                fmt
-            })
-            .filter_map(|(offset, data)| {
-                // … and check if it corresponds to the tag we're looking for
-                self.header.format_tag_to_offset.get(&tag).and_then(|&idx| {
-                    if idx == offset {
-                        // convert RawVec to TypedVec
-                        Some(data.into_iter().map(Into::into).collect_vec())
-                    } else {
-                        None
-                    }
-                })
-            })
-            .next()
+    /// Returns the IDs of all INFO fields present in this record, in on-disk order.
+    /// Fields whose offset is not defined in the header are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    /// use rust_bcf::Record;
+    ///
+    /// let mut records = BcfRecords::from_path("resources/types.bcf").unwrap();
+    /// let record = records.next().unwrap();
+    /// assert_eq!(record.info_tags()[..3], ["FLAG", "INT", "FLOAT"])
+    /// ```
+    fn info_tags(&self) -> Vec<&str> {
+        self.raw_info_fields()
+            .into_iter()
+            .filter_map(|(offset, _)| self.header.info.get(&offset))
+            .map(|info| info.id.as_ref())
+            .collect()
+    }
+
+    /// Returns the IDs of all FORMAT fields present in this record, in on-disk order.
+    /// Fields whose offset is not defined in the header are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    /// use rust_bcf::Record;
+    ///
+    /// let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+    /// let record = records.next().unwrap();
+    /// assert_eq!(record.format_tags()[..3], ["GT", "DP", "ADALL"])
+    /// ```
+    fn format_tags(&self) -> Vec<&str> {
+        self.raw_format_fields()
+            .into_iter()
+            .filter_map(|(offset, _)| self.header.format.get(&offset))
+            .map(|format| format.id.as_ref())
+            .collect()
     }
 
     fn genotypes(&self) -> Vec<Vec<GenotypeAllele>> {
@@ -412,6 +651,7 @@ impl Record for BcfRecord {
 
 /// Phased or unphased alleles, represented as indices.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GenotypeAllele {
     Unphased(i32),
     Phased(i32),
@@ -432,6 +672,18 @@ impl From<i32> for GenotypeAllele {
     }
 }
 
+impl From<GenotypeAllele> for i32 {
+    /// Encode given allele according to BCF standard.
+    fn from(allele: GenotypeAllele) -> Self {
+        match allele {
+            GenotypeAllele::UnphasedMissing => 0,
+            GenotypeAllele::PhasedMissing => 1,
+            GenotypeAllele::Unphased(i) => (i + 1) << 1,
+            GenotypeAllele::Phased(i) => (i + 1) << 1 | 1,
+        }
+    }
+}
+
 impl GenotypeAllele {
     /// Get the index into the list of alleles.
     pub fn index(self) -> Option<u32> {
@@ -0,0 +1,279 @@
+//! Positions, intervals and regions on contigs, with explicit 0-based and 1-based conversions.
+//!
+//! BCF and this crate count positions from 0, whereas VCF and region strings such as
+//! `chr1:1,000-2,000` count from 1.
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use getset::Getters;
+
+/// A position on a contig.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::region::Position;
+///
+/// let position = Position::from_one_based(1000).unwrap();
+/// assert_eq!(position.zero_based(), 999);
+/// assert_eq!(position, Position::from_zero_based(999));
+/// assert!(Position::from_one_based(0).is_none());
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Position(u32);
+
+impl Position {
+    /// The largest position, e.g. the end of regions without end.
+    pub const MAX: Position = Position(u32::MAX);
+
+    /// Creates a position from a 0-based coordinate, as in BCF.
+    pub fn from_zero_based(pos: u32) -> Self {
+        Position(pos)
+    }
+
+    /// Creates a position from a 1-based coordinate, as in VCF; `None` for 0.
+    pub fn from_one_based(pos: u32) -> Option<Self> {
+        pos.checked_sub(1).map(Position)
+    }
+
+    /// Returns the 0-based coordinate, as in BCF.
+    pub fn zero_based(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the 1-based coordinate, as in VCF.
+    pub fn one_based(self) -> u32 {
+        self.0.saturating_add(1)
+    }
+}
+
+/// A half-open interval `[start, end)` of positions.
+///
+/// Intervals are ordered by start, then by end.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::region::{Interval, Position};
+///
+/// // VCF-style closed intervals, e.g. POS to END
+/// let interval = Interval::from_one_based(1000, 2000).unwrap();
+/// assert_eq!(interval, Interval::from_zero_based(999, 2000));
+/// assert_eq!(interval.len(), 1001);
+/// assert!(interval.contains(Position::from_one_based(2000).unwrap()));
+/// assert!(!interval.contains(Position::from_zero_based(2000)));
+///
+/// let other = Interval::from_zero_based(1500, 2500);
+/// assert!(interval.overlaps(&other));
+/// assert_eq!(
+///     interval.intersection(&other),
+///     Some(Interval::from_zero_based(1500, 2000))
+/// );
+/// assert!(!interval.overlaps(&Interval::from_zero_based(2000, 2001)));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Interval {
+    start: Position,
+    end: Position,
+}
+
+impl Interval {
+    /// Creates the interval `[start, end)`.
+    ///
+    /// # Panics
+    ///
+    /// If `end` is before `start`.
+    pub fn new(start: Position, end: Position) -> Self {
+        assert!(
+            start <= end,
+            "interval end {} is before start {}",
+            end.0,
+            start.0
+        );
+        Interval { start, end }
+    }
+
+    /// Creates an interval from 0-based, half-open coordinates, as in BCF and BED.
+    ///
+    /// # Panics
+    ///
+    /// If `end` is before `start`.
+    pub fn from_zero_based(start: u32, end: u32) -> Self {
+        Self::new(Position(start), Position(end))
+    }
+
+    /// Creates an interval from 1-based, closed coordinates, as in VCF and region strings;
+    /// `None` if `start` is 0 or `end` is before `start`.
+    pub fn from_one_based(start: u32, end: u32) -> Option<Self> {
+        if end < start {
+            return None;
+        }
+        Some(Interval {
+            start: Position::from_one_based(start)?,
+            end: Position(end),
+        })
+    }
+
+    /// Returns the first position of this interval.
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    /// Returns the position after the last position of this interval.
+    pub fn end(&self) -> Position {
+        self.end
+    }
+
+    /// Returns the number of positions in this interval.
+    pub fn len(&self) -> u32 {
+        self.end.0 - self.start.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Returns `true` if the given position lies within this interval.
+    pub fn contains(&self, position: Position) -> bool {
+        self.start <= position && position < self.end
+    }
+
+    /// Returns `true` if both intervals have at least one position in common.
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start.max(other.start) < self.end.min(other.end)
+    }
+
+    /// Returns the positions both intervals have in common, if any.
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        if self.overlaps(other) {
+            Some(Interval {
+                start: self.start.max(other.start),
+                end: self.end.min(other.end),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// An interval on a contig, as in `chr1:1,000-2,000`.
+///
+/// Region strings are parsed as by samtools and bcftools:
+/// - `chr1` is the whole contig,
+/// - `chr1:1,000-2,000` is the 1-based, closed interval from 1000 to 2000,
+/// - `chr1:5000-` and `chr1:5000` extend from 5000 to the end of the contig, and
+/// - `chr1:-2000` extends from the start of the contig to 2000.
+///
+/// If the part after the last `:` is not a range of numbers, the whole string is taken as
+/// contig name.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::region::{Interval, Position, Region};
+///
+/// let region: Region = "chr1:1,000-2,000".parse().unwrap();
+/// assert_eq!(region.contig(), "chr1");
+/// assert_eq!(*region.interval(), Interval::from_zero_based(999, 2000));
+/// assert_eq!(region.to_string(), "chr1:1000-2000");
+///
+/// let region: Region = "chr1:5000-".parse().unwrap();
+/// assert_eq!(region.interval().end(), Position::MAX);
+/// assert_eq!("chr1".parse::<Region>().unwrap(), Region::whole("chr1"));
+/// assert!("chr1:2000-1000".parse::<Region>().is_err());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Getters)]
+#[getset(get = "pub")]
+pub struct Region {
+    contig: String,
+    interval: Interval,
+}
+
+impl Region {
+    pub fn new(contig: &str, interval: Interval) -> Self {
+        Region {
+            contig: contig.into(),
+            interval,
+        }
+    }
+
+    /// Creates the region of the whole contig.
+    pub fn whole(contig: &str) -> Self {
+        Self::new(contig, Interval::new(Position(0), Position::MAX))
+    }
+
+    /// Returns `true` if both regions are on the same contig and overlap.
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.contig == other.contig && self.interval.overlaps(&other.interval)
+    }
+}
+
+impl FromStr for Region {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            bail!("empty region");
+        }
+        if let Some((contig, range)) = s.rsplit_once(':') {
+            if let Some(interval) = parse_range(range) {
+                if contig.is_empty() {
+                    bail!("region {} has no contig", s);
+                }
+                return Ok(Self::new(contig, interval?));
+            }
+        }
+        Ok(Self::whole(s))
+    }
+}
+
+/// Parses a 1-based, closed range such as `1,000-2,000`, `5000-`, `5000` or `-2000`;
+/// `None` if it is not a range at all, an error if it is not a valid one.
+fn parse_range(range: &str) -> Option<anyhow::Result<Interval>> {
+    let is_number = |s: &str| s.bytes().all(|b| b.is_ascii_digit() || b == b',');
+    // a single position extends to the end of the contig, as `5000-`
+    let (start, end) = range.split_once('-').unwrap_or((range, ""));
+    if !is_number(start) || !is_number(end) || (start.is_empty() && end.is_empty()) {
+        return None;
+    }
+    Some(range_interval(start, end))
+}
+
+fn range_interval(start: &str, end: &str) -> anyhow::Result<Interval> {
+    let number = |s: &str| -> anyhow::Result<Option<u32>> {
+        if s.is_empty() {
+            return Ok(None);
+        }
+        let n = s.replace(',', "");
+        Ok(Some(
+            n.parse().map_err(|_| anyhow!("invalid position {}", s))?,
+        ))
+    };
+    let start = number(start)?.unwrap_or(1);
+    match number(end)? {
+        None => Position::from_one_based(start)
+            .map(|start| Interval::new(start, Position::MAX))
+            .ok_or_else(|| anyhow!("region start must be at least 1")),
+        Some(end) => Interval::from_one_based(start, end).ok_or_else(|| {
+            anyhow!(
+                "invalid region {}-{}, start must be at least 1 and at most end",
+                start,
+                end
+            )
+        }),
+    }
+}
+
+/// Writes regions as `chr1:1000-2000` (1-based, closed), `chr1:5000-` or `chr1`.
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Interval { start, end } = self.interval;
+        match (start.0, end) {
+            (0, Position::MAX) => write!(f, "{}", self.contig),
+            (_, Position::MAX) => write!(f, "{}:{}-", self.contig, start.one_based()),
+            _ => write!(f, "{}:{}-{}", self.contig, start.one_based(), end.0),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! `serde` support (behind the `serde` feature).
+//!
+//! Text (IDs, alleles and string values) is represented as strings, and missing integer and float
+//! values as `null`, such that serialized records are straightforward to consume e.g. as JSON.
+//! Floats which JSON cannot represent, i.e. NaN and infinity, are represented as the strings
+//! `"NaN"`, `"Inf"` and `"-Inf"`, so that they remain distinct from missing values.
+use serde::de::Error;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::record::BcfRecord;
+use crate::types::{
+    Header, HeaderContig, HeaderFilter, HeaderFormat, HeaderInfo, HeaderLine, HeaderValue,
+    InfoNumber, MISSING_FLOAT, MISSING_INTEGER,
+};
+use crate::variant::VariantRecord;
+
+/// (De)serializes text as a string; invalid UTF-8 is replaced when serializing.
+pub(crate) mod text {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(text: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from_utf8_lossy(text))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        Ok(String::deserialize(deserializer)?.into_bytes())
+    }
+}
+
+/// (De)serializes a list of texts as a list of strings, see `text`.
+pub(crate) mod texts {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        texts: &[Vec<u8>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(texts.iter().map(|text| String::from_utf8_lossy(text)))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        let texts = Vec::<String>::deserialize(deserializer)?;
+        Ok(texts.into_iter().map(String::into_bytes).collect())
+    }
+}
+
+/// (De)serializes integers with `MISSING_INTEGER` as `null`.
+pub(crate) mod integers {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        values: &[i32],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            values
+                .iter()
+                .map(|&v| Some(v).filter(|&v| v != MISSING_INTEGER)),
+        )
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<i32>, D::Error> {
+        let values = Vec::<Option<i32>>::deserialize(deserializer)?;
+        Ok(values
+            .into_iter()
+            .map(|v| v.unwrap_or(MISSING_INTEGER))
+            .collect())
+    }
+}
+
+/// A float as number, or as string (`"NaN"`, `"Inf"` or `"-Inf"`, as in VCF) for values which
+/// formats such as JSON cannot represent, and which would otherwise become `null`, i.e. missing.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Float {
+    Number(f32),
+    Text(String),
+}
+
+impl Float {
+    /// Returns `None` for the missing value.
+    fn from_f32(value: f32) -> Option<Self> {
+        match value {
+            v if v.to_bits() == MISSING_FLOAT => None,
+            v if v.is_nan() => Some(Float::Text("NaN".into())),
+            f32::INFINITY => Some(Float::Text("Inf".into())),
+            f32::NEG_INFINITY => Some(Float::Text("-Inf".into())),
+            v => Some(Float::Number(v)),
+        }
+    }
+
+    fn to_f32<E: Error>(value: Option<Self>) -> Result<f32, E> {
+        match value {
+            None => Ok(f32::from_bits(MISSING_FLOAT)),
+            Some(Float::Number(v)) => Ok(v),
+            Some(Float::Text(text)) => match text.as_str() {
+                "NaN" => Ok(f32::NAN),
+                "Inf" => Ok(f32::INFINITY),
+                "-Inf" => Ok(f32::NEG_INFINITY),
+                _ => Err(E::custom(format!("invalid float {}", text))),
+            },
+        }
+    }
+}
+
+/// (De)serializes floats with the missing value as `null`, see `Float` for NaN and infinity.
+pub(crate) mod floats {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        values: &[f32],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|&v| Float::from_f32(v)))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<f32>, D::Error> {
+        let values = Vec::<Option<Float>>::deserialize(deserializer)?;
+        values.into_iter().map(Float::to_f32).collect()
+    }
+}
+
+/// (De)serializes an optional float such as QUAL with `None` as `null`, see `Float`.
+pub(crate) mod optional_float {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<f32>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.and_then(Float::from_f32).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<f32>, D::Error> {
+        match Option::<Float>::deserialize(deserializer)? {
+            None => Ok(None),
+            value => Float::to_f32(value).map(Some),
+        }
+    }
+}
+
+/// Numbers are represented as in the VCF header, i.e. `"1"`, `"A"`, `"R"`, `"G"` or `"."`.
+impl Serialize for InfoNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for InfoNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let number = String::deserialize(deserializer)?;
+        match number.as_str() {
+            "A" => Ok(InfoNumber::AlternateAlleles),
+            "R" => Ok(InfoNumber::Alleles),
+            "G" => Ok(InfoNumber::Genotypes),
+            "." => Ok(InfoNumber::Unknown),
+            n => n
+                .parse()
+                .map(InfoNumber::Count)
+                .map_err(|_| D::Error::custom(format!("invalid Number {}", n))),
+        }
+    }
+}
+
+/// The header is represented by its generic `##key=value` lines (as pairs, in order),
+/// its FILTER, INFO and FORMAT definitions, contigs and samples.
+impl Serialize for Header {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut occurrences = std::collections::HashMap::new();
+        let meta = self
+            .lines
+            .iter()
+            .filter_map(|line| match line {
+                HeaderLine::Meta(key) => {
+                    let n = occurrences.entry(key).or_insert(0);
+                    *n += 1;
+                    match self.meta.get_vec(key)?.get(*n - 1)? {
+                        HeaderValue::String(value) => Some((key, value)),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut state = serializer.serialize_struct("Header", 6)?;
+        state.serialize_field("meta", &meta)?;
+        state.serialize_field("filters", &self.filter.values().collect::<Vec<_>>())?;
+        state.serialize_field("info", &self.info.values().collect::<Vec<_>>())?;
+        state.serialize_field("format", &self.format.values().collect::<Vec<_>>())?;
+        state.serialize_field("contigs", &self.contigs)?;
+        state.serialize_field("samples", &self.samples)?;
+        state.end()
+    }
+}
+
+/// The serialized parts of a `Header`, see `impl Serialize for Header`.
+#[derive(Deserialize)]
+struct HeaderParts {
+    meta: Vec<(String, String)>,
+    filters: Vec<HeaderFilter>,
+    info: Vec<HeaderInfo>,
+    format: Vec<HeaderFormat>,
+    contigs: Vec<HeaderContig>,
+    samples: Vec<String>,
+}
+
+/// Headers are deserialized with the dictionary offsets (IDX) of their FILTER, INFO and FORMAT
+/// definitions, such that they can decode records encoded with the original header. Lines are
+/// ordered by kind, i.e. generic lines first, and structured lines are formatted as new ones,
+/// since the original order of lines and of their keys is not serialized.
+impl<'de> Deserialize<'de> for Header {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let parts = HeaderParts::deserialize(deserializer)?;
+        let mut header = Header::empty();
+        for (key, value) in parts.meta {
+            header.add_meta(&key, &value);
+        }
+        for filter in parts.filters {
+            header.insert_line(HeaderLine::Filter(filter.id.clone()));
+            header
+                .filter_tag_to_offset
+                .insert(filter.id.clone(), filter.idx);
+            header.filter.insert(filter.idx, filter);
+        }
+        for info in parts.info {
+            header.insert_line(HeaderLine::Info(info.id.clone()));
+            header.info_tag_to_offset.insert(info.id.clone(), info.idx);
+            header.info.insert(info.idx, info);
+        }
+        for format in parts.format {
+            header.insert_line(HeaderLine::Format(format.id.clone()));
+            header
+                .format_tag_to_offset
+                .insert(format.id.clone(), format.idx);
+            header.format.insert(format.idx, format);
+        }
+        for contig in parts.contigs {
+            header.add_contig(contig);
+        }
+        for sample in parts.samples {
+            header.add_sample(&sample).map_err(D::Error::custom)?;
+        }
+        Ok(header)
+    }
+}
+
+/// Records are serialized as their decoded `VariantRecord`.
+impl Serialize for BcfRecord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        VariantRecord::try_from_record(self)
+            .map_err(<S::Error as serde::ser::Error>::custom)?
+            .serialize(serializer)
+    }
+}
@@ -0,0 +1,403 @@
+//! Export of records into SQLite databases (behind the `sqlite` feature).
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+
+use crate::reader::BcfRecords;
+use crate::record::Record;
+use crate::types::{
+    Header, HeaderFormat, HeaderInfo, InfoNumber, InfoType, TypedVec, MISSING_FLOAT,
+    MISSING_INTEGER,
+};
+use crate::writer::{vcf_genotype, vcf_value};
+
+/// Which tables to create, and how many records to insert per transaction.
+#[derive(Debug, Clone)]
+pub struct SqliteOptions {
+    /// The FORMAT fields (besides `GT`) of the `genotypes` table, or `None` for no such table
+    pub genotypes: Option<Vec<String>>,
+    /// The number of records inserted per transaction
+    pub batch_size: usize,
+}
+
+impl Default for SqliteOptions {
+    fn default() -> Self {
+        SqliteOptions {
+            genotypes: None,
+            batch_size: 10_000,
+        }
+    }
+}
+
+/// Writes records into a SQLite database with the tables
+/// - `samples` with the columns `sample_id` and `name`,
+/// - `variants` with the columns `variant_id`, `CHROM`, `POS` (1-based, as in VCF), `ID`,
+///   `REF`, `ALT` (comma-separated), `QUAL` and `FILTER` (semicolon-separated), and one column
+///   `INFO_<ID>` per INFO field of the header; fields with `Number=1` are typed according to
+///   their definition, others are text as in VCF, and flags are 0 or 1,
+/// - optionally `genotypes` with the columns `variant_id`, `sample_id`, `GT` (e.g. `0|1`) and
+///   one column per selected FORMAT field, typed like INFO columns.
+///
+/// Missing values are `NULL`. Call `finish` when done, which creates an index on
+/// `(CHROM, POS)`.
+///
+/// # Examples
+///
+/// ```
+/// use rusqlite::Connection;
+/// use rust_bcf::sqlite::{SqliteOptions, SqliteWriter};
+/// use rust_bcf::BcfRecords;
+///
+/// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+/// let options = SqliteOptions {
+///     genotypes: Some(vec!["DP".into()]),
+///     ..Default::default()
+/// };
+/// let connection = Connection::open_in_memory().unwrap();
+/// let mut writer = SqliteWriter::new(connection, records.header(), options).unwrap();
+/// writer.write(records).unwrap();
+/// let connection = writer.finish().unwrap();
+/// let count = |table: &str| -> i64 {
+///     let query = format!("SELECT COUNT(*) FROM {}", table);
+///     connection.query_row(&query, [], |row| row.get(0)).unwrap()
+/// };
+/// assert_eq!(count("genotypes"), 3 * count("variants"));
+/// ```
+pub struct SqliteWriter {
+    connection: Connection,
+    info: Vec<HeaderInfo>,
+    /// The FORMAT fields of the `genotypes` table, or `None` without one
+    format: Option<Vec<HeaderFormat>>,
+    n_samples: usize,
+    batch_size: usize,
+    insert_variant: String,
+    insert_genotype: String,
+}
+
+impl SqliteWriter {
+    /// Creates a new database file, which must not exist yet.
+    pub fn to_path<P: AsRef<Path>>(
+        path: P,
+        header: &Header,
+        options: SqliteOptions,
+    ) -> anyhow::Result<Self> {
+        if path.as_ref().exists() {
+            bail!("{} already exists", path.as_ref().display());
+        }
+        Self::new(Connection::open(path)?, header, options)
+    }
+
+    /// Creates the tables and inserts all samples; fails if a selected FORMAT field is not
+    /// defined in the header, or if two columns would have the same (case-insensitive) name.
+    pub fn new(
+        connection: Connection,
+        header: &Header,
+        options: SqliteOptions,
+    ) -> anyhow::Result<Self> {
+        if options.batch_size == 0 {
+            bail!("batch size must be positive");
+        }
+        let info = header.info.values().cloned().collect::<Vec<_>>();
+        let format = match options.genotypes {
+            None => None,
+            Some(ids) => Some(
+                ids.iter()
+                    .filter(|id| id.as_str() != "GT")
+                    .map(|id| {
+                        header
+                            .format_tag_to_offset
+                            .get(id)
+                            .map(|offset| header.format[offset].clone())
+                            .ok_or_else(|| anyhow!("FORMAT {} is not defined in the header", id))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?,
+            ),
+        };
+
+        let mut columns = vec![
+            "variant_id INTEGER PRIMARY KEY".to_string(),
+            "CHROM TEXT NOT NULL".into(),
+            "POS INTEGER NOT NULL".into(),
+            "ID TEXT".into(),
+            "REF TEXT NOT NULL".into(),
+            "ALT TEXT".into(),
+            "QUAL REAL".into(),
+            "FILTER TEXT".into(),
+        ];
+        check_columns(
+            "variants",
+            &columns,
+            info.iter()
+                .map(|info| (format!("INFO_{}", info.id), format!("INFO {}", info.id))),
+        )?;
+        columns.extend(info.iter().map(|info| {
+            format!(
+                "{} {}",
+                quote(&format!("INFO_{}", info.id)),
+                column_type(info.kind(), info.number())
+            )
+        }));
+        let mut statements = vec![
+            "CREATE TABLE samples (sample_id INTEGER PRIMARY KEY, name TEXT NOT NULL)".to_string(),
+            format!("CREATE TABLE variants ({})", columns.join(", ")),
+        ];
+        let insert_variant = format!(
+            "INSERT INTO variants VALUES (?{})",
+            ", ?".repeat(columns.len() - 1)
+        );
+        let mut insert_genotype = String::new();
+        if let Some(format) = &format {
+            let mut columns = vec![
+                "variant_id INTEGER NOT NULL REFERENCES variants".to_string(),
+                "sample_id INTEGER NOT NULL REFERENCES samples".into(),
+                "GT TEXT".into(),
+            ];
+            check_columns(
+                "genotypes",
+                &columns,
+                format
+                    .iter()
+                    .map(|format| (format.id.clone(), format!("FORMAT {}", format.id))),
+            )?;
+            columns.extend(format.iter().map(|format| {
+                format!(
+                    "{} {}",
+                    quote(&format.id),
+                    column_type(format.kind(), format.number())
+                )
+            }));
+            statements.push(format!(
+                "CREATE TABLE genotypes ({}, PRIMARY KEY (variant_id, sample_id))",
+                columns.join(", ")
+            ));
+            insert_genotype = format!(
+                "INSERT INTO genotypes VALUES (?{})",
+                ", ?".repeat(columns.len() - 1)
+            );
+        }
+
+        let mut connection = connection;
+        let transaction = connection.transaction()?;
+        for statement in statements {
+            transaction.execute(&statement, [])?;
+        }
+        for (i, sample) in header.samples().iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO samples VALUES (?, ?)",
+                rusqlite::params![i as i64 + 1, sample],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(SqliteWriter {
+            connection,
+            info,
+            format,
+            n_samples: header.samples().len(),
+            batch_size: options.batch_size,
+            insert_variant,
+            insert_genotype,
+        })
+    }
+
+    /// Inserts all given records, `batch_size` records per transaction.
+    pub fn write<R: Record, I: IntoIterator<Item = R>>(
+        &mut self,
+        records: I,
+    ) -> anyhow::Result<()> {
+        let mut records = records.into_iter().peekable();
+        while records.peek().is_some() {
+            let transaction = self.connection.transaction()?;
+            {
+                let mut insert_variant = transaction.prepare_cached(&self.insert_variant)?;
+                let mut insert_genotype = match self.format {
+                    Some(_) => Some(transaction.prepare_cached(&self.insert_genotype)?),
+                    None => None,
+                };
+                for record in records.by_ref().take(self.batch_size) {
+                    let alt_alleles = record.alt_alleles();
+                    let filters = record.try_filters()?;
+                    let mut values = vec![
+                        Value::Null,
+                        Value::Text(record.try_chrom()?.into()),
+                        Value::Integer(record.pos() as i64 + 1),
+                        text(&record.id()),
+                        Value::Text(String::from_utf8_lossy(&record.ref_allele()).into()),
+                        if alt_alleles.is_empty() {
+                            Value::Null
+                        } else {
+                            Value::Text(
+                                alt_alleles
+                                    .iter()
+                                    .map(|alt| String::from_utf8_lossy(alt))
+                                    .collect::<Vec<_>>()
+                                    .join(","),
+                            )
+                        },
+                        record.qual().map_or(Value::Null, |q| Value::Real(q as f64)),
+                        if filters.is_empty() {
+                            Value::Null
+                        } else {
+                            Value::Text(filters.join(";"))
+                        },
+                    ];
+                    for info in &self.info {
+                        values.push(if *info.kind() == InfoType::Flag {
+                            Value::Integer(record.has_flag(info.id.as_bytes()) as i64)
+                        } else {
+                            value(
+                                info.kind(),
+                                info.number(),
+                                record.info(info.id.as_bytes()).as_ref(),
+                            )
+                        });
+                    }
+                    insert_variant.execute(params_from_iter(values))?;
+                    let variant_id = transaction.last_insert_rowid();
+
+                    if let (Some(format), Some(insert_genotype)) =
+                        (&self.format, &mut insert_genotype)
+                    {
+                        let genotypes = record.format(b"GT");
+                        let fields = format
+                            .iter()
+                            .map(|format| record.format(format.id.as_bytes()))
+                            .collect::<Vec<_>>();
+                        for sample in 0..self.n_samples {
+                            let mut values = vec![
+                                Value::Integer(variant_id),
+                                Value::Integer(sample as i64 + 1),
+                                genotypes
+                                    .as_ref()
+                                    .and_then(|gt| gt.get(sample))
+                                    .map_or(Value::Null, |gt| Value::Text(vcf_genotype(gt))),
+                            ];
+                            for (format, field) in format.iter().zip(&fields) {
+                                values.push(value(
+                                    format.kind(),
+                                    format.number(),
+                                    field.as_ref().and_then(|values| values.get(sample)),
+                                ));
+                            }
+                            insert_genotype.execute(params_from_iter(values))?;
+                        }
+                    }
+                }
+            }
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Creates the index on `(CHROM, POS)` and returns the connection.
+    pub fn finish(self) -> anyhow::Result<Connection> {
+        self.connection.execute(
+            "CREATE INDEX variants_position ON variants (CHROM, POS)",
+            [],
+        )?;
+        Ok(self.connection)
+    }
+}
+
+/// Checks that the columns of the given fields (pairs of column name and field, e.g.
+/// `INFO DP`) neither clash with each other nor with the fixed column definitions, since
+/// SQLite would reject the table with a "duplicate column" error. Like all SQLite identifiers,
+/// column names are case-insensitive.
+fn check_columns<I: IntoIterator<Item = (String, String)>>(
+    table: &str,
+    fixed: &[String],
+    fields: I,
+) -> anyhow::Result<()> {
+    let mut names = HashMap::new();
+    for definition in fixed {
+        let name = definition.split(' ').next().unwrap_or_default();
+        names.insert(name.to_lowercase(), (name.to_string(), None));
+    }
+    for (name, field) in fields {
+        match names.get(&name.to_lowercase()) {
+            Some((other, None)) => {
+                bail!("{} clashes with column {} of table {}", field, other, table)
+            }
+            Some((_, Some(other))) => bail!(
+                "{} and {} clash in column {} of table {}",
+                other,
+                field,
+                name,
+                table
+            ),
+            None => {
+                names.insert(name.to_lowercase(), (name, Some(field)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Quotes an identifier such as a column name.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn column_type(kind: &InfoType, number: &InfoNumber) -> &'static str {
+    match (kind, number) {
+        (InfoType::Flag, _) | (InfoType::Integer, InfoNumber::Count(1)) => "INTEGER",
+        (InfoType::Float, InfoNumber::Count(1)) => "REAL",
+        _ => "TEXT",
+    }
+}
+
+/// Returns the given text, or `NULL` if it is empty or `.`.
+fn text(text: &[u8]) -> Value {
+    if text.is_empty() || text == b"." {
+        Value::Null
+    } else {
+        Value::Text(String::from_utf8_lossy(text).into())
+    }
+}
+
+/// Converts an INFO or FORMAT value according to `column_type`.
+fn value(kind: &InfoType, number: &InfoNumber, value: Option<&TypedVec>) -> Value {
+    match (column_type(kind, number), value) {
+        (_, None) | (_, Some(TypedVec::Missing)) => Value::Null,
+        ("INTEGER", Some(TypedVec::Int32(v))) => match v.first() {
+            Some(&v) if v != MISSING_INTEGER => Value::Integer(v as i64),
+            _ => Value::Null,
+        },
+        ("REAL", Some(TypedVec::Float32(v))) => match v.first() {
+            Some(v) if v.to_bits() != MISSING_FLOAT => Value::Real(*v as f64),
+            _ => Value::Null,
+        },
+        (_, Some(TypedVec::UString(v))) => text(v),
+        (_, Some(v)) => match vcf_value(v) {
+            v if v == "." => Value::Null,
+            v => Value::Text(v),
+        },
+    }
+}
+
+/// Converts a BCF file into a new SQLite database.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_bcf::sqlite::{bcf_to_sqlite, SqliteOptions};
+///
+/// let options = SqliteOptions {
+///     genotypes: Some(vec!["DP".into(), "GQ".into()]),
+///     ..Default::default()
+/// };
+/// bcf_to_sqlite("input.bcf", "variants.db", options).unwrap();
+/// ```
+pub fn bcf_to_sqlite<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: SqliteOptions,
+) -> anyhow::Result<()> {
+    let records = BcfRecords::from_path(input)?;
+    let mut writer = SqliteWriter::to_path(output, records.header(), options)?;
+    writer.write(records)?;
+    writer.finish()?;
+    Ok(())
+}
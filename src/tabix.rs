@@ -0,0 +1,398 @@
+//! Tabix (`.tbi`) indices of BGZF compressed, position-sorted text files such as `.vcf.gz`.
+//!
+//! The index maps genomic regions to "chunks" of the compressed file (given as pairs of virtual
+//! file offsets, see `BgzfReader::virtual_offset`) using the binning scheme of the SAM/BAM
+//! specification, plus a linear index of 16kbp windows for skipping chunks which end before the
+//! queried region.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::bail;
+use getset::Getters;
+
+use crate::bgzf::{BgzfReader, BgzfWriter};
+
+const MAGIC: &[u8; 4] = b"TBI\x01";
+/// The size of the windows of the linear index, as a power of two
+const MIN_SHIFT: u32 = 14;
+/// The number of levels of the binning scheme (not counting the root bin)
+const DEPTH: u32 = 5;
+/// The pseudo-bin used by htslib for storing (unused) metadata
+const PSEUDO_BIN: u32 = 37450;
+/// The `format` of VCF files, see `TabixIndex::format`
+const FORMAT_VCF: i32 = 2;
+
+/// A range of the compressed file, given as virtual file offsets `[begin, end)`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Getters)]
+#[getset(get = "pub")]
+pub struct Chunk {
+    begin: u64,
+    end: u64,
+}
+
+/// The index of a single reference sequence (i.e. contig).
+#[derive(Debug, Clone, Default, Eq, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct ReferenceIndex {
+    /// The chunks of each bin
+    bins: BTreeMap<u32, Vec<Chunk>>,
+    /// The virtual file offset of the first record overlapping each 16kbp window
+    intervals: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Getters)]
+#[getset(get = "pub")]
+pub struct TabixIndex {
+    /// The file format (0: generic, 1: SAM, 2: VCF)
+    format: i32,
+    /// The 1-based columns of the sequence name, start and end position
+    col_seq: i32,
+    col_beg: i32,
+    col_end: i32,
+    /// Lines starting with this character are skipped
+    meta: u8,
+    /// The number of leading lines to skip
+    skip: i32,
+    /// The names of the indexed reference sequences
+    names: Vec<String>,
+    references: Vec<ReferenceIndex>,
+}
+
+/// Returns the bin which fully contains the 0-based, half-open interval `[begin, end)`.
+pub(crate) fn reg2bin(begin: u32, end: u32) -> u32 {
+    let end = end.max(begin + 1) - 1;
+    let mut level_size = (1 << (3 * DEPTH)) - 1;
+    for level in (1..=DEPTH).rev() {
+        let shift = MIN_SHIFT + 3 * (DEPTH - level);
+        if begin >> shift == end >> shift {
+            return level_size / 7 + (begin >> shift);
+        }
+        level_size >>= 3;
+    }
+    0
+}
+
+/// Returns all bins which may contain records overlapping `[begin, end)`.
+pub(crate) fn reg2bins(begin: u32, end: u32) -> Vec<u32> {
+    let end = end.max(begin + 1) - 1;
+    let mut bins = vec![0];
+    let mut offset = 1;
+    for level in 1..=DEPTH {
+        let shift = MIN_SHIFT + 3 * (DEPTH - level);
+        bins.extend(offset + (begin >> shift)..=offset + (end >> shift));
+        offset += 1 << (3 * level);
+    }
+    bins
+}
+
+fn read_i32<R: Read>(reader: &mut R) -> anyhow::Result<i32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> anyhow::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_count<R: Read>(reader: &mut R) -> anyhow::Result<usize> {
+    let n = read_i32(reader)?;
+    if n < 0 {
+        bail!("invalid tabix index: negative count {}", n);
+    }
+    Ok(n as usize)
+}
+
+/// Returns the 0-based, half-open interval covered by the given VCF line, i.e. starting at POS
+/// and ending at INFO `END` (if given) or after the reference allele.
+pub(crate) fn vcf_line_interval(line: &str) -> anyhow::Result<(u32, u32)> {
+    let columns = line.splitn(9, '\t').collect::<Vec<_>>();
+    if columns.len() < 8 {
+        bail!(
+            "VCF line has {} columns, expected at least 8",
+            columns.len()
+        );
+    }
+    let pos: u32 = columns[1].parse()?;
+    // telomeric records at POS 0 are indexed at the first position, as by htslib
+    let begin = pos.saturating_sub(1);
+    let end = columns[7]
+        .split(';')
+        .find_map(|field| field.strip_prefix("END="))
+        .and_then(|end| end.parse().ok())
+        .unwrap_or(begin + columns[3].len() as u32);
+    Ok((begin, end.max(begin + 1)))
+}
+
+impl TabixIndex {
+    /// Reads a (BGZF compressed) `.tbi` file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        Self::read(BgzfReader::new(BufReader::new(File::open(path)?)))
+    }
+
+    /// Reads an uncompressed tabix index.
+    pub fn read<R: Read>(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("not a tabix index");
+        }
+        let n_ref = read_count(&mut reader)?;
+        let format = read_i32(&mut reader)?;
+        let col_seq = read_i32(&mut reader)?;
+        let col_beg = read_i32(&mut reader)?;
+        let col_end = read_i32(&mut reader)?;
+        let meta = read_i32(&mut reader)? as u8;
+        let skip = read_i32(&mut reader)?;
+        let mut names = vec![0u8; read_count(&mut reader)?];
+        reader.read_exact(&mut names)?;
+        let names = names
+            .split(|&c| c == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8(name.to_vec()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if names.len() != n_ref {
+            bail!(
+                "invalid tabix index: {} names for {} references",
+                names.len(),
+                n_ref
+            );
+        }
+
+        let mut references = Vec::with_capacity(n_ref);
+        for _ in 0..n_ref {
+            let mut bins = BTreeMap::new();
+            for _ in 0..read_count(&mut reader)? {
+                let bin = read_i32(&mut reader)? as u32;
+                let chunks = (0..read_count(&mut reader)?)
+                    .map(|_| {
+                        Ok(Chunk {
+                            begin: read_u64(&mut reader)?,
+                            end: read_u64(&mut reader)?,
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                if bin != PSEUDO_BIN {
+                    bins.insert(bin, chunks);
+                }
+            }
+            let intervals = (0..read_count(&mut reader)?)
+                .map(|_| read_u64(&mut reader))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            references.push(ReferenceIndex { bins, intervals });
+        }
+        // an optional number of unplaced records may follow, which is ignored
+        Ok(TabixIndex {
+            format,
+            col_seq,
+            col_beg,
+            col_end,
+            meta,
+            skip,
+            names,
+            references,
+        })
+    }
+
+    /// Writes this index as (BGZF compressed) `.tbi` file.
+    pub fn to_path<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let mut writer = BgzfWriter::new(BufWriter::new(File::create(path)?));
+        self.write(&mut writer)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Writes this index uncompressed.
+    pub fn write<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        writer.write_all(MAGIC)?;
+        let names = self
+            .names
+            .iter()
+            .flat_map(|name| name.bytes().chain(std::iter::once(0)))
+            .collect::<Vec<_>>();
+        for value in &[
+            self.names.len() as i32,
+            self.format,
+            self.col_seq,
+            self.col_beg,
+            self.col_end,
+            self.meta as i32,
+            self.skip,
+            names.len() as i32,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&names)?;
+        for reference in &self.references {
+            writer.write_all(&(reference.bins.len() as i32).to_le_bytes())?;
+            for (bin, chunks) in &reference.bins {
+                writer.write_all(&bin.to_le_bytes())?;
+                writer.write_all(&(chunks.len() as i32).to_le_bytes())?;
+                for chunk in chunks {
+                    writer.write_all(&chunk.begin.to_le_bytes())?;
+                    writer.write_all(&chunk.end.to_le_bytes())?;
+                }
+            }
+            writer.write_all(&(reference.intervals.len() as i32).to_le_bytes())?;
+            for offset in &reference.intervals {
+                writer.write_all(&offset.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Indexes a BGZF compressed VCF file, which has to be sorted by position within each contig
+    /// (and must not contain interleaved contigs).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::tabix::TabixIndex;
+    /// use rust_bcf::writer::{Compression, VcfWriter};
+    /// use rust_bcf::BcfRecords;
+    ///
+    /// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+    /// let mut writer = VcfWriter::new(Vec::new(), records.header(), Compression::Bgzf).unwrap();
+    /// records.for_each(|record| writer.write(&record).unwrap());
+    /// let vcf = writer.finish().unwrap();
+    ///
+    /// let index = TabixIndex::build(&vcf[..]).unwrap();
+    /// assert_eq!(index.names(), &["chr1"]);
+    /// ```
+    pub fn build<R: Read>(reader: R) -> anyhow::Result<Self> {
+        let mut reader = BgzfReader::new(reader);
+        let mut names: Vec<String> = vec![];
+        let mut references: Vec<ReferenceIndex> = vec![];
+        let mut line = String::new();
+        // the bin of the current chunk and its start offset, i.e. of the first record in the chunk
+        let mut chunk: Option<(u32, u64)> = None;
+        let mut last_offset = 0;
+        let mut last_begin = 0;
+        loop {
+            let offset = reader.virtual_offset();
+            line.clear();
+            let n = reader.read_line(&mut line)?;
+            let new_contig = n > 0 && !line.starts_with('#') && {
+                let chrom = line.split('\t').next().unwrap();
+                names.last().map(String::as_str) != Some(chrom)
+            };
+            // a chunk ends with a change of bin or contig, or at the end of the file
+            let (begin, end) = if n > 0 && !line.starts_with('#') {
+                vcf_line_interval(line.trim_end_matches(&['\n', '\r'][..]))?
+            } else {
+                (0, 0)
+            };
+            let bin = reg2bin(begin, end);
+            if let Some((chunk_bin, chunk_begin)) = chunk {
+                if n == 0 || new_contig || chunk_bin != bin {
+                    references
+                        .last_mut()
+                        .unwrap()
+                        .bins
+                        .entry(chunk_bin)
+                        .or_default()
+                        .push(Chunk {
+                            begin: chunk_begin,
+                            end: last_offset,
+                        });
+                    chunk = None;
+                }
+            }
+            if n == 0 {
+                break;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            if new_contig {
+                let chrom = line.split('\t').next().unwrap();
+                if names.iter().any(|name| name == chrom) {
+                    bail!("records of contig {} are not contiguous", chrom);
+                }
+                names.push(chrom.into());
+                references.push(ReferenceIndex::default());
+                last_begin = 0;
+            }
+            if begin < last_begin {
+                bail!("records are not sorted by position at {}", line.trim_end());
+            }
+            last_begin = begin;
+            if chunk.is_none() {
+                chunk = Some((bin, offset));
+            }
+            last_offset = reader.virtual_offset();
+
+            let intervals = &mut references.last_mut().unwrap().intervals;
+            let (first, last) = (begin >> MIN_SHIFT, (end - 1) >> MIN_SHIFT);
+            if intervals.len() <= last as usize {
+                intervals.resize(last as usize + 1, u64::MAX);
+            }
+            for window in first..=last {
+                let interval = &mut intervals[window as usize];
+                if *interval == u64::MAX {
+                    *interval = offset;
+                }
+            }
+        }
+        // like htslib, windows without records get the offset of the preceding window
+        for reference in &mut references {
+            let mut previous = 0;
+            for interval in reference.intervals.iter_mut() {
+                if *interval == u64::MAX {
+                    *interval = previous;
+                } else {
+                    previous = *interval;
+                }
+            }
+        }
+        Ok(TabixIndex {
+            format: FORMAT_VCF,
+            col_seq: 1,
+            col_beg: 2,
+            col_end: 0,
+            meta: b'#',
+            skip: 0,
+            names,
+            references,
+        })
+    }
+
+    /// Returns the chunks of the compressed file which may contain records overlapping the
+    /// 0-based, half-open interval `[begin, end)` of the given contig, sorted and merged.
+    pub fn chunks(&self, contig: &str, begin: u32, end: u32) -> Vec<Chunk> {
+        // the bins cover positions up to 2^29, which open-ended regions would exceed
+        let end = end.min(1 << (MIN_SHIFT + 3 * DEPTH));
+        let begin = begin.min(end);
+        let reference = match self.names.iter().position(|name| name == contig) {
+            Some(tid) => &self.references[tid],
+            None => return vec![],
+        };
+        // records starting before this offset end before the queried region
+        let min_offset = reference
+            .intervals
+            .get((begin >> MIN_SHIFT) as usize)
+            .or_else(|| reference.intervals.last())
+            .copied()
+            .unwrap_or(0);
+        let mut chunks = reg2bins(begin, end)
+            .into_iter()
+            .filter_map(|bin| reference.bins.get(&bin))
+            .flatten()
+            .filter(|chunk| chunk.end > min_offset)
+            .copied()
+            .collect::<Vec<_>>();
+        chunks.sort();
+        let mut merged: Vec<Chunk> = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            match merged.last_mut() {
+                Some(last) if chunk.begin <= last.end => last.end = last.end.max(chunk.end),
+                _ => merged.push(chunk),
+            }
+        }
+        merged
+    }
+}
@@ -1,10 +1,12 @@
 use multimap::MultiMap;
 use nom::lib::std::collections::HashMap;
 use num_enum::TryFromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
-use strum::EnumString;
+use strum::{Display, EnumString};
 
-use crate::parser;
+use crate::{encoder, parser};
 use nom::combinator::map;
 use nom::multi::many0;
 use nom::number::complete::{le_f32, le_i16, le_i32, le_i8};
@@ -12,14 +14,21 @@ use nom::{IResult, InputTakeAtPosition};
 
 pub(crate) const NAN_FLOAT: u32 = 0x7FC00000;
 pub(crate) const MISSING_FLOAT: u32 = 0x7F800001;
-#[allow(dead_code)]
 pub(crate) const END_OF_VECTOR_FLOAT_32: u32 = 0x7F800002;
+pub(crate) const MISSING_INT_8: u8 = 0x80;
+pub(crate) const MISSING_INT_16: u16 = 0x8000;
+pub(crate) const MISSING_INT_32: u32 = 0x80000000;
 pub(crate) const END_OF_VECTOR_INT_8: u8 = 0x81;
-#[allow(dead_code)]
 pub(crate) const END_OF_VECTOR_INT_16: u16 = 0x8001;
-#[allow(dead_code)]
 pub(crate) const END_OF_VECTOR_INT_32: u32 = 0x80000001;
 
+/// The value used for missing integers (`.` in VCF) in `TypedVec::Int32`,
+/// regardless of the integer width used on disk.
+pub const MISSING_INTEGER: i32 = MISSING_INT_32 as i32;
+
+/// The ID of the filter which denotes that a record passed all filters.
+pub const PASS_FILTER: &str = "PASS";
+
 pub(crate) type Text = Vec<u8>;
 // pub(crate) type TextSlice<'a> = &'a [u8];
 
@@ -36,7 +45,7 @@ pub struct TypeDescriptor {
     pub(crate) num_elements: usize,
 }
 
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromPrimitive)]
 #[repr(u8)]
 pub enum TypeKind {
     Missing = 0,
@@ -53,12 +62,13 @@ pub enum TypeKind {
 pub type InfoKey = usize;
 pub type FormatKey = usize;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TypedVec {
     Missing,
-    Int32(Vec<i32>),
-    Float32(Vec<f32>),
-    UString(Vec<u8>),
+    Int32(#[cfg_attr(feature = "serde", serde(with = "crate::serialization::integers"))] Vec<i32>),
+    Float32(#[cfg_attr(feature = "serde", serde(with = "crate::serialization::floats"))] Vec<f32>),
+    UString(#[cfg_attr(feature = "serde", serde(with = "crate::serialization::text"))] Vec<u8>),
 }
 
 #[derive(Debug)]
@@ -81,7 +91,13 @@ impl<'a> From<RawVec<'a>> for TypedVec {
                     // trim off excess bytes (used for unequal length genotype fields)
                     let (_end_of_vector_bytes, input) =
                         input.split_at_position_complete(|b| b == END_OF_VECTOR_INT_8)?;
-                    let (input, data) = many0(map(le_i8, Into::into))(input)?;
+                    let (input, data) = many0(map(le_i8, |v| {
+                        if v as u8 == MISSING_INT_8 {
+                            MISSING_INTEGER
+                        } else {
+                            v.into()
+                        }
+                    }))(input)?;
                     Ok((input, data))
                 }
                 let (input, data) = parse(input).unwrap();
@@ -89,36 +105,57 @@ impl<'a> From<RawVec<'a>> for TypedVec {
                 TypedVec::Int32(data)
             }
             RawVec::Int16(input) => {
-                // TODO trim off END_OF_VECTOR_INT_16 values
-                fn parse(input: &[u8]) -> IResult<&[u8], Vec<i32>> {
-                    let (input, data) = many0(map(le_i16, Into::into))(input)?;
+                fn parse(input: &[u8]) -> IResult<&[u8], Vec<i16>> {
+                    let (input, data) = many0(le_i16)(input)?;
                     Ok((input, data))
                 }
                 let (input, data) = parse(input).unwrap();
                 assert!(input.is_empty());
-                TypedVec::Int32(data)
+                TypedVec::Int32(
+                    data.into_iter()
+                        .take_while(|&v| v as u16 != END_OF_VECTOR_INT_16)
+                        .map(|v| {
+                            if v as u16 == MISSING_INT_16 {
+                                MISSING_INTEGER
+                            } else {
+                                v.into()
+                            }
+                        })
+                        .collect(),
+                )
             }
             RawVec::Int32(input) => {
-                // TODO trim off END_OF_VECTOR_INT_32 values
                 fn parse(input: &[u8]) -> IResult<&[u8], Vec<i32>> {
                     let (input, data) = many0(le_i32)(input)?;
                     Ok((input, data))
                 }
-                let (input, data) = parse(input).unwrap();
+                let (input, mut data) = parse(input).unwrap();
                 assert!(input.is_empty());
+                if let Some(end) = data.iter().position(|&v| v as u32 == END_OF_VECTOR_INT_32) {
+                    data.truncate(end);
+                }
                 TypedVec::Int32(data)
             }
             RawVec::Float32(input) => {
-                // TODO trim off END_OF_VECTOR_FLOAT values
                 fn parse(input: &[u8]) -> IResult<&[u8], Vec<f32>> {
                     let (input, data) = many0(le_f32)(input)?;
                     Ok((input, data))
                 }
-                let (input, data) = parse(input).unwrap();
+                let (input, mut data) = parse(input).unwrap();
                 assert!(input.is_empty());
+                if let Some(end) = data
+                    .iter()
+                    .position(|v| v.to_bits() == END_OF_VECTOR_FLOAT_32)
+                {
+                    data.truncate(end);
+                }
                 TypedVec::Float32(data)
             }
-            RawVec::UString(input) => TypedVec::UString(input.into()),
+            // per-sample strings are padded with NUL bytes to a common length
+            RawVec::UString(input) => {
+                let end = input.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+                TypedVec::UString(input[..end].into())
+            }
         }
     }
 }
@@ -158,7 +195,8 @@ impl TypedVec {
         }
     }
 }
-use getset::Getters;
+use crate::alias::ContigAliases;
+use getset::{Getters, Setters};
 use indexmap::IndexMap;
 
 #[derive(Debug, Clone, Getters)]
@@ -169,13 +207,370 @@ pub struct Header {
     pub(crate) info_tag_to_offset: HashMap<String, usize>,
     pub(crate) format: IndexMap<usize, HeaderFormat>,
     pub(crate) format_tag_to_offset: HashMap<String, usize>,
+    pub(crate) filter: IndexMap<usize, HeaderFilter>,
+    pub(crate) filter_tag_to_offset: HashMap<String, usize>,
     pub(crate) contigs: Vec<HeaderContig>,
     pub(crate) samples: Vec<Sample>,
+    /// The aliases of contig names used for lookups, see `resolve_contig`
+    pub(crate) aliases: ContigAliases,
+    /// The order of lines, see `to_vcf_text`
+    #[getset(skip)]
+    pub(crate) lines: Vec<HeaderLine>,
+}
+
+/// A line of the text header. Generic lines refer to the values of `Header::meta` by key,
+/// in order of occurrence; all other lines refer to their definition by ID.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum HeaderLine {
+    Meta(String),
+    Filter(String),
+    Info(String),
+    Format(String),
+    Contig(String),
+}
+
+impl HeaderLine {
+    /// The usual position of lines of this kind relative to each other, used for placing new lines.
+    fn rank(&self) -> u8 {
+        match self {
+            HeaderLine::Meta(_) => 0,
+            HeaderLine::Filter(_) => 1,
+            HeaderLine::Info(_) => 2,
+            HeaderLine::Format(_) => 3,
+            HeaderLine::Contig(_) => 4,
+        }
+    }
+}
+
+/// The keys of a structured header line (such as `##INFO=<...>`) in their original order,
+/// each with whether its value was quoted.
+pub(crate) type Layout = Vec<(String, bool)>;
+
+impl Header {
+    /// Creates a header without any lines, not even `##fileformat`.
+    pub(crate) fn empty() -> Self {
+        Header {
+            meta: MultiMap::new(),
+            info: IndexMap::new(),
+            info_tag_to_offset: Default::default(),
+            format: IndexMap::new(),
+            format_tag_to_offset: Default::default(),
+            filter: IndexMap::new(),
+            filter_tag_to_offset: Default::default(),
+            contigs: vec![],
+            samples: vec![],
+            aliases: Default::default(),
+            lines: vec![],
+        }
+    }
+
+    /// Returns the text representation of this header, i.e. all `##` lines followed by the
+    /// `#CHROM ... FORMAT samples` line.
+    ///
+    /// Lines are written in their original order, with structured lines keeping the order of
+    /// their keys and the quoting of their values, such that the text of a parsed header is
+    /// reproduced exactly. Lines added later on are placed after the last line of the same kind.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    ///
+    /// let records = BcfRecords::from_path("resources/types.bcf").unwrap();
+    /// let text = records.header().to_vcf_text();
+    /// assert!(text.starts_with("##fileformat=VCFv4.1\n##FILTER=<ID=PASS,"));
+    /// assert!(text.ends_with("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSample\n"));
+    /// ```
+    pub fn to_vcf_text(&self) -> String {
+        encoder::vcf_header_text(self)
+    }
+
+    /// Returns the FILTER definition with the given ID, if the header declares one.
+    pub fn filter_by_id(&self, id: &str) -> Option<&HeaderFilter> {
+        self.filter_tag_to_offset
+            .get(id)
+            .and_then(|offset| self.filter.get(offset))
+    }
+
+    /// Returns the offset into the dictionary of strings shared by FILTER, INFO and FORMAT
+    /// for the given ID. IDs which are not yet part of the dictionary get the next free offset;
+    /// offset 0 is reserved for `PASS`.
+    fn dictionary_offset(&self, id: &str) -> usize {
+        if id == PASS_FILTER {
+            return 0;
+        }
+        self.filter_tag_to_offset
+            .get(id)
+            .or_else(|| self.info_tag_to_offset.get(id))
+            .or_else(|| self.format_tag_to_offset.get(id))
+            .copied()
+            .unwrap_or_else(|| {
+                self.filter
+                    .keys()
+                    .chain(self.info.keys())
+                    .chain(self.format.keys())
+                    .max()
+                    .map_or(1, |max| max + 1)
+            })
+    }
+
+    /// Records a new line after the last line of the same (or a preceding) kind.
+    /// `##fileformat` always comes first.
+    pub(crate) fn insert_line(&mut self, line: HeaderLine) {
+        if !matches!(line, HeaderLine::Meta(_)) && self.lines.contains(&line) {
+            return;
+        }
+        let position = match &line {
+            HeaderLine::Meta(key) if key == "fileformat" => 0,
+            _ => self
+                .lines
+                .iter()
+                .rposition(|l| l.rank() <= line.rank())
+                .map_or(0, |i| i + 1),
+        };
+        self.lines.insert(position, line);
+    }
+
+    /// Adds an INFO definition, replacing (and returning) any previous definition with the same ID.
+    /// Its IDX is assigned automatically, keeping the offsets of all other definitions intact.
+    pub fn add_info(&mut self, mut info: HeaderInfo) -> Option<HeaderInfo> {
+        info.idx = self.dictionary_offset(&info.id);
+        self.insert_line(HeaderLine::Info(info.id.clone()));
+        self.info_tag_to_offset.insert(info.id.clone(), info.idx);
+        self.info.insert(info.idx, info)
+    }
+
+    /// Removes the INFO definition with the given ID.
+    /// The offsets of all other definitions stay the same.
+    pub fn remove_info(&mut self, id: &str) -> Option<HeaderInfo> {
+        let offset = self.info_tag_to_offset.remove(id)?;
+        self.lines.retain(|l| *l != HeaderLine::Info(id.into()));
+        self.info.shift_remove(&offset)
+    }
+
+    /// Adds a FORMAT definition, see `add_info`.
+    pub fn add_format(&mut self, mut format: HeaderFormat) -> Option<HeaderFormat> {
+        format.idx = self.dictionary_offset(&format.id);
+        self.insert_line(HeaderLine::Format(format.id.clone()));
+        self.format_tag_to_offset
+            .insert(format.id.clone(), format.idx);
+        self.format.insert(format.idx, format)
+    }
+
+    /// Removes the FORMAT definition with the given ID, see `remove_info`.
+    pub fn remove_format(&mut self, id: &str) -> Option<HeaderFormat> {
+        let offset = self.format_tag_to_offset.remove(id)?;
+        self.lines.retain(|l| *l != HeaderLine::Format(id.into()));
+        self.format.shift_remove(&offset)
+    }
+
+    /// Adds a FILTER definition, see `add_info`.
+    pub fn add_filter(&mut self, mut filter: HeaderFilter) -> Option<HeaderFilter> {
+        filter.idx = self.dictionary_offset(&filter.id);
+        self.insert_line(HeaderLine::Filter(filter.id.clone()));
+        self.filter_tag_to_offset
+            .insert(filter.id.clone(), filter.idx);
+        self.filter.insert(filter.idx, filter)
+    }
+
+    /// Removes the FILTER definition with the given ID, see `remove_info`.
+    pub fn remove_filter(&mut self, id: &str) -> Option<HeaderFilter> {
+        let offset = self.filter_tag_to_offset.remove(id)?;
+        self.lines.retain(|l| *l != HeaderLine::Filter(id.into()));
+        self.filter.shift_remove(&offset)
+    }
+
+    /// Returns the index of the contig with the given ID, by which records refer to it.
+    pub fn contig_index(&self, id: &str) -> Option<usize> {
+        self.contigs.iter().position(|contig| contig.id == id)
+    }
+
+    /// Returns the contig with the given index, if there is one.
+    pub fn contig(&self, idx: usize) -> Option<&HeaderContig> {
+        self.contigs.get(idx)
+    }
+
+    /// Sets the aliases of contig names used by `resolve_contig` and `same_contigs`;
+    /// by default, these are `ContigAliases::default()`.
+    pub fn set_aliases(&mut self, aliases: ContigAliases) {
+        self.aliases = aliases;
+    }
+
+    /// Returns the index of the contig with the given ID or else of the first contig which is
+    /// an alias of it, e.g. of `chr1` for `1`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    ///
+    /// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+    /// let header = records.header();
+    /// assert_eq!(header.resolve_contig("1"), header.contig_index("chr1"));
+    /// assert_eq!(header.resolve_contig("NC_000002.12"), header.contig_index("chr2"));
+    /// ```
+    pub fn resolve_contig(&self, id: &str) -> Option<usize> {
+        let id = self
+            .aliases
+            .find(id, self.contigs.iter().map(|contig| contig.id.as_str()))?;
+        self.contig_index(id)
+    }
+
+    /// Returns `true` if both headers declare the same contigs in the same order, up to
+    /// aliases, such that records of one header refer to the same contigs with the other.
+    /// Lengths have to match if both headers declare them.
+    pub fn same_contigs(&self, other: &Header) -> bool {
+        self.contigs.len() == other.contigs.len()
+            && self.contigs.iter().zip(&other.contigs).all(|(a, b)| {
+                self.aliases.is_alias(&a.id, &b.id)
+                    && match (a.length, b.length) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => true,
+                    }
+            })
+    }
+
+    /// Renames a contig; returns an error if there is no such contig or the new ID is taken.
+    ///
+    /// Records refer to contigs by index, hence encoded records remain valid and get the new
+    /// name when read with this header.
+    pub fn rename_contig(&mut self, id: &str, new_id: &str) -> anyhow::Result<()> {
+        if self.contig_index(new_id).is_some() {
+            anyhow::bail!("contig {} already exists", new_id);
+        }
+        let i = self
+            .contig_index(id)
+            .ok_or_else(|| anyhow::anyhow!("contig {} does not exist", id))?;
+        self.contigs[i].id = new_id.into();
+        for line in &mut self.lines {
+            if *line == HeaderLine::Contig(id.into()) {
+                *line = HeaderLine::Contig(new_id.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Renames all contigs for which `rename` returns a new ID, see `rename_contig`.
+    /// Returns an error if the new IDs are not unique, in which case no contig is renamed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::BcfRecords;
+    ///
+    /// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+    /// let mut header = records.header().clone();
+    /// header
+    ///     .rename_contigs(|id| id.strip_prefix("chr").map(String::from))
+    ///     .unwrap();
+    /// assert_eq!(header.contigs()[0].id(), "1");
+    /// assert!(header.same_contigs(records.header()));
+    /// ```
+    pub fn rename_contigs<F: FnMut(&str) -> Option<String>>(
+        &mut self,
+        mut rename: F,
+    ) -> anyhow::Result<()> {
+        let renames = self
+            .contigs
+            .iter()
+            .filter_map(|contig| Some((contig.id.clone(), rename(&contig.id)?)))
+            .collect::<HashMap<_, _>>();
+        let mut ids = self
+            .contigs
+            .iter()
+            .map(|contig| renames.get(&contig.id).unwrap_or(&contig.id))
+            .collect::<Vec<_>>();
+        ids.sort();
+        if let Some(ids) = ids.windows(2).find(|ids| ids[0] == ids[1]) {
+            anyhow::bail!("contig {} would not be unique", ids[0]);
+        }
+        for contig in &mut self.contigs {
+            if let Some(new_id) = renames.get(&contig.id) {
+                contig.id = new_id.clone();
+            }
+        }
+        for line in &mut self.lines {
+            if let HeaderLine::Contig(id) = line {
+                if let Some(new_id) = renames.get(id) {
+                    *id = new_id.clone();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a contig, replacing (and returning) any previous contig with the same ID.
+    /// New contigs are appended, i.e. the indices of existing contigs stay the same.
+    pub fn add_contig(&mut self, contig: HeaderContig) -> Option<HeaderContig> {
+        self.insert_line(HeaderLine::Contig(contig.id.clone()));
+        match self.contig_index(&contig.id) {
+            Some(i) => Some(std::mem::replace(&mut self.contigs[i], contig)),
+            None => {
+                self.contigs.push(contig);
+                None
+            }
+        }
+    }
+
+    /// Removes the contig with the given ID.
+    ///
+    /// Note that records refer to contigs by index, hence removing a contig invalidates
+    /// encoded records on all subsequent contigs.
+    pub fn remove_contig(&mut self, id: &str) -> Option<HeaderContig> {
+        let i = self.contig_index(id)?;
+        self.lines.retain(|l| *l != HeaderLine::Contig(id.into()));
+        Some(self.contigs.remove(i))
+    }
+
+    /// Adds a generic `##key=value` line.
+    pub fn add_meta(&mut self, key: &str, value: &str) {
+        self.insert_line(HeaderLine::Meta(key.into()));
+        self.meta
+            .insert(key.into(), HeaderValue::String(value.into()));
+    }
+
+    /// Removes all generic lines with the given key.
+    pub fn remove_meta(&mut self, key: &str) -> Option<Vec<HeaderValue>> {
+        self.lines.retain(|l| *l != HeaderLine::Meta(key.into()));
+        self.meta.remove(key)
+    }
+
+    /// Appends a sample; returns an error if a sample with the same name already exists.
+    pub fn add_sample(&mut self, name: &str) -> anyhow::Result<()> {
+        if self.samples.iter().any(|s| s == name) {
+            anyhow::bail!("sample {} already exists", name);
+        }
+        self.samples.push(name.into());
+        Ok(())
+    }
+
+    /// Renames a sample; returns an error if there is no such sample or the new name is taken.
+    pub fn rename_sample(&mut self, name: &str, new_name: &str) -> anyhow::Result<()> {
+        if self.samples.iter().any(|s| s == new_name) {
+            anyhow::bail!("sample {} already exists", new_name);
+        }
+        let sample = self
+            .samples
+            .iter_mut()
+            .find(|s| *s == name)
+            .ok_or_else(|| anyhow::anyhow!("sample {} does not exist", name))?;
+        *sample = new_name.into();
+        Ok(())
+    }
+
+    /// Removes a sample and returns its former index, which can be used to drop the sample's
+    /// values from records via `VariantRecord::select_samples`.
+    pub fn remove_sample(&mut self, name: &str) -> Option<usize> {
+        let i = self.samples.iter().position(|s| s == name)?;
+        self.samples.remove(i);
+        Some(i)
+    }
 }
 
 pub type HeaderKey<'a> = &'a str;
 
-#[derive(Debug, Clone, Eq, PartialEq, EnumString)]
+#[derive(Debug, Clone, Eq, PartialEq, EnumString, Display)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum InfoType {
     Integer,
     Float,
@@ -184,6 +579,22 @@ pub enum InfoType {
     String,
 }
 
+impl InfoType {
+    /// Returns `true` if the given value may be stored in a field of this type.
+    /// Missing values are accepted for every type.
+    pub fn accepts(&self, value: &TypedVec) -> bool {
+        matches!(
+            (self, value),
+            (_, TypedVec::Missing)
+                | (InfoType::Integer, TypedVec::Int32(_))
+                | (InfoType::Flag, TypedVec::Int32(_))
+                | (InfoType::Float, TypedVec::Float32(_))
+                | (InfoType::Character, TypedVec::UString(_))
+                | (InfoType::String, TypedVec::UString(_))
+        )
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum InfoNumber {
     Count(usize),
@@ -193,6 +604,33 @@ pub enum InfoNumber {
     Unknown,
 }
 
+impl std::fmt::Display for InfoNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InfoNumber::Count(n) => write!(f, "{}", n),
+            InfoNumber::Alleles => write!(f, "R"),
+            InfoNumber::AlternateAlleles => write!(f, "A"),
+            InfoNumber::Genotypes => write!(f, "G"),
+            InfoNumber::Unknown => write!(f, "."),
+        }
+    }
+}
+
+impl InfoNumber {
+    /// Returns the number of values expected for a record with `n_alleles` alleles (REF and ALT)
+    /// and the given ploidy, or `None` if the number of values is not fixed.
+    pub fn count(&self, n_alleles: usize, ploidy: usize) -> Option<usize> {
+        match *self {
+            InfoNumber::Count(n) => Some(n),
+            InfoNumber::Alleles => Some(n_alleles),
+            InfoNumber::AlternateAlleles => Some(n_alleles.saturating_sub(1)),
+            // the number of unordered combinations of `ploidy` alleles (with repetition)
+            InfoNumber::Genotypes => Some((1..=ploidy).fold(1, |n, k| n * (n_alleles + k - 1) / k)),
+            InfoNumber::Unknown => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum HeaderValue {
     String(String),
@@ -203,6 +641,7 @@ pub enum HeaderValue {
 }
 
 #[derive(Debug, Getters, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[getset(get = "pub")]
 pub struct HeaderInfo {
     pub(crate) id: String,
@@ -215,10 +654,30 @@ pub struct HeaderInfo {
     version: String,
     pub(crate) idx: usize,
     additional: HashMap<String, String>,
+    #[getset(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) layout: Layout,
 }
 
 pub type Sample = String;
 
+impl HeaderInfo {
+    /// Creates a new INFO definition; its IDX is assigned when adding it to a `Header`.
+    pub fn new(id: &str, number: InfoNumber, kind: InfoType, description: &str) -> Self {
+        HeaderInfo {
+            id: id.into(),
+            number,
+            kind,
+            description: description.into(),
+            source: String::new(),
+            version: String::new(),
+            idx: 0,
+            additional: Default::default(),
+            layout: vec![],
+        }
+    }
+}
+
 impl<'a> From<Vec<(&'a str, &'a str)>> for HeaderInfo {
     fn from(data: Vec<(&'a str, &'a str)>) -> Self {
         let mut h: HashMap<_, _> = data.into_iter().collect();
@@ -236,25 +695,47 @@ impl<'a> From<Vec<(&'a str, &'a str)>> for HeaderInfo {
             version: h.remove("Version").unwrap_or(&"").into(),
             idx: str::parse(h.remove("IDX").unwrap_or(&"0")).unwrap(),
             additional: Default::default(),
+            layout: vec![],
         };
         header_info.additional = h.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
         header_info
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Getters, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[getset(get = "pub")]
 pub struct HeaderFormat {
     pub(crate) id: String,
     number: InfoNumber,
     kind: InfoType,
     description: String,
     pub(crate) idx: usize,
+    additional: HashMap<String, String>,
+    #[getset(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) layout: Layout,
+}
+
+impl HeaderFormat {
+    /// Creates a new FORMAT definition; its IDX is assigned when adding it to a `Header`.
+    pub fn new(id: &str, number: InfoNumber, kind: InfoType, description: &str) -> Self {
+        HeaderFormat {
+            id: id.into(),
+            number,
+            kind,
+            description: description.into(),
+            idx: 0,
+            additional: Default::default(),
+            layout: vec![],
+        }
+    }
 }
 
 impl<'a> From<Vec<(&'a str, &'a str)>> for HeaderFormat {
     fn from(data: Vec<(&'a str, &'a str)>) -> Self {
         let mut h: HashMap<_, _> = data.into_iter().collect();
-        HeaderFormat {
+        let mut header_format = HeaderFormat {
             id: h.remove("ID").expect("ID is mandatory").into(),
             number: parser::info_number(h.remove("Number").expect("Number is mandatory"))
                 .unwrap()
@@ -265,15 +746,59 @@ impl<'a> From<Vec<(&'a str, &'a str)>> for HeaderFormat {
                 .expect("Description is mandatory")
                 .into(),
             idx: str::parse(h.remove("IDX").unwrap_or(&"0")).unwrap(),
-        }
+            additional: Default::default(),
+            layout: vec![],
+        };
+        header_format.additional = h.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        header_format
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Getters, Setters, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[getset(get = "pub")]
 pub struct HeaderContig {
     pub(crate) id: String,
+    /// The length in bases, if declared
+    #[getset(get = "pub", set = "pub")]
     length: Option<usize>,
+    /// All other attributes, such as `assembly` or `md5`
     additional: HashMap<String, String>,
+    #[getset(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) layout: Layout,
+}
+
+impl HeaderContig {
+    pub fn new(id: &str, length: Option<usize>) -> Self {
+        HeaderContig {
+            id: id.into(),
+            length,
+            additional: Default::default(),
+            layout: vec![],
+        }
+    }
+
+    /// Returns the `assembly` attribute, if declared.
+    pub fn assembly(&self) -> Option<&str> {
+        self.attribute("assembly")
+    }
+
+    /// Sets the `assembly` attribute.
+    pub fn set_assembly(&mut self, assembly: &str) -> &mut Self {
+        self.set_attribute("assembly", assembly)
+    }
+
+    /// Returns the attribute with the given key, other than `ID` and `length`.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.additional.get(key).map(String::as_str)
+    }
+
+    /// Sets an attribute other than `ID` and `length`, such as `md5` or `species`.
+    pub fn set_attribute(&mut self, key: &str, value: &str) -> &mut Self {
+        self.additional.insert(key.into(), value.into());
+        self
+    }
 }
 
 impl<'a> From<Vec<(&'a str, &'a str)>> for HeaderContig {
@@ -283,27 +808,53 @@ impl<'a> From<Vec<(&'a str, &'a str)>> for HeaderContig {
             id: h.remove("ID").expect("ID is mandatory").into(),
             length: h.remove("length").map(|s| s.parse().ok()).flatten(),
             additional: Default::default(),
+            layout: vec![],
         };
         header_info.additional = h.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
         header_info
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Getters, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[getset(get = "pub")]
 pub struct HeaderFilter {
     pub(crate) id: String,
     description: String,
+    pub(crate) idx: usize,
+    additional: HashMap<String, String>,
+    #[getset(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) layout: Layout,
+}
+
+impl HeaderFilter {
+    /// Creates a new FILTER definition; its IDX is assigned when adding it to a `Header`.
+    pub fn new(id: &str, description: &str) -> Self {
+        HeaderFilter {
+            id: id.into(),
+            description: description.into(),
+            idx: 0,
+            additional: Default::default(),
+            layout: vec![],
+        }
+    }
 }
 
 impl<'a> From<Vec<(&'a str, &'a str)>> for HeaderFilter {
     fn from(data: Vec<(&'a str, &'a str)>) -> Self {
         let mut h: HashMap<_, _> = data.into_iter().collect();
-        HeaderFilter {
+        let mut header_filter = HeaderFilter {
             id: h.remove("ID").expect("ID is mandatory").into(),
             description: h
                 .remove("Description")
                 .expect("Description is mandatory")
                 .into(),
-        }
+            idx: str::parse(h.remove("IDX").unwrap_or("0")).unwrap(),
+            additional: Default::default(),
+            layout: vec![],
+        };
+        header_filter.additional = h.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        header_filter
     }
 }
@@ -0,0 +1,376 @@
+use anyhow::{anyhow, bail};
+use getset::{Getters, MutGetters, Setters};
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+use crate::encoder;
+use crate::record::{BcfRecord, GenotypeAllele, Record};
+use crate::types::{
+    Header, InfoNumber, InfoType, Text, TypedVec, MISSING_FLOAT, MISSING_INTEGER, PASS_FILTER,
+};
+
+/// An owned, mutable variant record, i.e. a decoded copy of all fields of a `Record`.
+///
+/// In contrast to `BcfRecord`, which is an immutable view over the encoded bytes, fields of a
+/// `VariantRecord` can be changed freely; use `to_bcf_record` to encode it again.
+#[derive(Debug, Clone, PartialEq, Getters, MutGetters, Setters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[getset(get = "pub", get_mut = "pub", set = "pub")]
+pub struct VariantRecord {
+    chrom: String,
+    /// 0-based, see `Record::pos`
+    pos: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::text"))]
+    id: Text,
+    /// The reference allele, followed by the alternative alleles
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::texts"))]
+    alleles: Vec<Text>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::optional_float")
+    )]
+    qual: Option<f32>,
+    filters: Vec<String>,
+    info: IndexMap<String, TypedVec>,
+    /// FORMAT values by tag, with one entry per sample
+    format: IndexMap<String, Vec<TypedVec>>,
+}
+
+impl VariantRecord {
+    /// Creates a record without ID, QUAL, FILTER, INFO and FORMAT values.
+    pub fn new(chrom: &str, pos: u32, alleles: Vec<Text>) -> Self {
+        VariantRecord {
+            chrom: chrom.into(),
+            pos,
+            id: vec![],
+            alleles,
+            qual: None,
+            filters: vec![],
+            info: IndexMap::new(),
+            format: IndexMap::new(),
+        }
+    }
+
+    /// Adds a filter to FILTER. Since `PASS` must not be combined with other filters,
+    /// it is removed (or replaces all other filters, if `filter` is `PASS`).
+    pub fn add_filter(&mut self, filter: &str) {
+        if filter == PASS_FILTER {
+            self.filters.clear();
+        } else {
+            self.filters.retain(|f| f != PASS_FILTER);
+        }
+        if !self.filters.iter().any(|f| f == filter) {
+            self.filters.push(filter.into());
+        }
+    }
+
+    /// Keeps only the FORMAT values of the samples at the given indices, in the given order.
+    /// Returns an error (and keeps all values) if an index is out of range.
+    pub fn select_samples(&mut self, samples: &[usize]) -> anyhow::Result<()> {
+        for (tag, values) in &self.format {
+            if let Some(i) = samples.iter().find(|&&i| i >= values.len()) {
+                bail!(
+                    "sample index {} is out of range, FORMAT {} has {} samples",
+                    i,
+                    tag,
+                    values.len()
+                );
+            }
+        }
+        for values in self.format.values_mut() {
+            *values = samples.iter().map(|&i| values[i].clone()).collect();
+        }
+        Ok(())
+    }
+
+    /// Decodes the GT FORMAT field, see `Record::genotypes`.
+    pub fn genotypes(&self) -> Vec<Vec<GenotypeAllele>> {
+        self.format
+            .get("GT")
+            .map(|gts| {
+                gts.iter()
+                    .map(|gt| match gt {
+                        TypedVec::Int32(gt) => {
+                            gt.iter().cloned().map(GenotypeAllele::from).collect()
+                        }
+                        _ => vec![],
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sets the GT FORMAT field (which is always the first FORMAT field) from the given genotypes.
+    pub fn set_genotypes(&mut self, genotypes: &[Vec<GenotypeAllele>]) {
+        let gts = genotypes
+            .iter()
+            .map(|gt| TypedVec::Int32(gt.iter().cloned().map(i32::from).collect()))
+            .collect();
+        self.format.insert("GT".into(), gts);
+        let index = self.format.get_index_of("GT").unwrap();
+        self.format.move_index(index, 0);
+    }
+
+    /// Checks that CHROM, FILTER, INFO and FORMAT are defined in the given header, that
+    /// POS and END lie within the declared length of the contig and that INFO and FORMAT values
+    /// match the declared Type and Number.
+    pub fn validate(&self, header: &Header) -> anyhow::Result<()> {
+        let contig = header
+            .contig_index(&self.chrom)
+            .and_then(|idx| header.contig(idx))
+            .ok_or_else(|| anyhow!("contig {} is not defined in the header", self.chrom))?;
+        if self.alleles.is_empty() {
+            bail!("a record must have at least a reference allele");
+        }
+        if let Some(length) = *contig.length() {
+            // 1-based, as in VCF; position length + 1 denotes the telomere
+            let pos = self.pos as usize + 1;
+            let end = match self.info.get("END") {
+                Some(TypedVec::Int32(end)) if !end.is_empty() => end[0].max(0) as usize,
+                _ => self.pos as usize + self.alleles[0].len(),
+            };
+            if pos > length + 1 {
+                bail!(
+                    "POS {} exceeds the length {} of contig {}",
+                    pos,
+                    length,
+                    self.chrom
+                );
+            }
+            if pos <= length && end > length {
+                bail!(
+                    "END {} exceeds the length {} of contig {}",
+                    end,
+                    length,
+                    self.chrom
+                );
+            }
+        }
+        for filter in &self.filters {
+            if filter != PASS_FILTER && header.filter_by_id(filter).is_none() {
+                bail!("FILTER {} is not defined in the header", filter);
+            }
+        }
+        let n_alleles = self.alleles.len();
+        for (tag, value) in &self.info {
+            let definition = header
+                .info_tag_to_offset
+                .get(tag)
+                .map(|offset| &header.info[offset])
+                .ok_or_else(|| anyhow!("INFO {} is not defined in the header", tag))?;
+            check_value(
+                "INFO",
+                tag,
+                definition.kind(),
+                definition.number(),
+                value,
+                n_alleles,
+                2,
+            )?;
+        }
+        let genotypes = self.genotypes();
+        for (tag, values) in &self.format {
+            let definition = header
+                .format_tag_to_offset
+                .get(tag)
+                .map(|offset| &header.format[offset])
+                .ok_or_else(|| anyhow!("FORMAT {} is not defined in the header", tag))?;
+            if values.len() != header.samples.len() {
+                bail!(
+                    "FORMAT {} has {} values, but the header declares {} samples",
+                    tag,
+                    values.len(),
+                    header.samples.len()
+                );
+            }
+            // GT has a dedicated encoding, regardless of its declared type
+            if tag == "GT" {
+                continue;
+            }
+            for (sample, value) in values.iter().enumerate() {
+                let ploidy = genotypes.get(sample).map_or(2, |gt| gt.len());
+                check_value(
+                    "FORMAT",
+                    tag,
+                    definition.kind(),
+                    definition.number(),
+                    value,
+                    n_alleles,
+                    ploidy,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes this record with respect to the given header, i.e. resolves CHROM, FILTER,
+    /// INFO and FORMAT IDs to their offsets in the header dictionary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::{BcfRecords, Record, VariantRecord};
+    ///
+    /// let mut records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+    /// let record = records.next().unwrap();
+    /// let mut variant = VariantRecord::from(&record);
+    /// variant.set_pos(1000);
+    /// variant.add_filter("GQlessthan70");
+    /// let encoded = variant.to_bcf_record(record.header().clone()).unwrap();
+    /// assert_eq!(encoded.pos(), 1000);
+    /// assert_eq!(encoded.filters(), ["GQlessthan70"]);
+    /// ```
+    pub fn to_bcf_record(&self, header: Arc<Header>) -> anyhow::Result<BcfRecord> {
+        let (shared, indiv) = self.encode(&header)?;
+        Ok(BcfRecord::new(shared, indiv, header))
+    }
+
+    /// Returns the encoded `shared` (CHROM to INFO) and `indiv` (FORMAT) parts of this record.
+    pub(crate) fn encode(&self, header: &Header) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        let chrom = header
+            .contig_index(&self.chrom)
+            .ok_or_else(|| anyhow!("contig {} is not defined in the header", self.chrom))?;
+        if self.alleles.is_empty() {
+            bail!("a record must have at least a reference allele");
+        }
+        let n_sample = header.samples.len();
+        let end = match self.info.get("END") {
+            Some(TypedVec::Int32(end)) => end.first().filter(|&&end| end != MISSING_INTEGER),
+            _ => None,
+        };
+        // END is 1-based and inclusive, i.e. the length is END - (0-based pos)
+        let rlen = match end.map(|&end| i64::from(end) - i64::from(self.pos)) {
+            Some(rlen) if rlen > 0 => rlen as i32,
+            _ => self.alleles[0].len() as i32,
+        };
+
+        let mut shared = Vec::new();
+        shared.extend_from_slice(&(chrom as i32).to_le_bytes());
+        shared.extend_from_slice(&(self.pos as i32).to_le_bytes());
+        shared.extend_from_slice(&rlen.to_le_bytes());
+        let qual = self.qual.unwrap_or_else(|| f32::from_bits(MISSING_FLOAT));
+        shared.extend_from_slice(&qual.to_le_bytes());
+        shared.extend_from_slice(&(self.info.len() as i16).to_le_bytes());
+        shared.extend_from_slice(&(self.alleles.len() as i16).to_le_bytes());
+        let n_fmt_n_sample = (self.format.len() as u32) << 24 | n_sample as u32;
+        shared.extend_from_slice(&n_fmt_n_sample.to_le_bytes());
+        encoder::typed_string(&self.id, &mut shared);
+        for allele in &self.alleles {
+            encoder::typed_string(allele, &mut shared);
+        }
+        let filters = self
+            .filters
+            .iter()
+            .map(|filter| match header.filter_tag_to_offset.get(filter) {
+                Some(&offset) => Ok(offset as i32),
+                None if filter == PASS_FILTER => Ok(0),
+                None => Err(anyhow!("FILTER {} is not defined in the header", filter)),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        encoder::typed_ints(&filters, &mut shared);
+        for (tag, value) in &self.info {
+            let offset = header
+                .info_tag_to_offset
+                .get(tag)
+                .ok_or_else(|| anyhow!("INFO {} is not defined in the header", tag))?;
+            encoder::info_pair(*offset, value, &mut shared);
+        }
+
+        let mut indiv = Vec::new();
+        // BCF requires GT to be the first FORMAT field, see `set_genotypes`
+        let gt = self.format.get_key_value("GT");
+        let others = self.format.iter().filter(|(tag, _)| *tag != "GT");
+        for (tag, values) in gt.into_iter().chain(others) {
+            let offset = header
+                .format_tag_to_offset
+                .get(tag)
+                .ok_or_else(|| anyhow!("FORMAT {} is not defined in the header", tag))?;
+            if values.len() != n_sample {
+                bail!(
+                    "FORMAT {} has {} values, but the header declares {} samples",
+                    tag,
+                    values.len(),
+                    n_sample
+                );
+            }
+            encoder::genotype_field(*offset, values, &mut indiv)?;
+        }
+        Ok((shared, indiv))
+    }
+}
+
+impl VariantRecord {
+    /// Decodes all fields of the given record, or returns an error naming the first field
+    /// which cannot be decoded, see `Record::try_info` and `Record::try_format`.
+    pub fn try_from_record<R: Record>(record: &R) -> anyhow::Result<Self> {
+        let mut alleles = vec![record.ref_allele()];
+        alleles.extend(record.alt_alleles());
+        Ok(VariantRecord {
+            chrom: record.try_chrom()?.into(),
+            pos: record.pos(),
+            id: record.id(),
+            alleles,
+            qual: record.qual(),
+            filters: record.try_filters()?.into_iter().map(Into::into).collect(),
+            info: record
+                .info_tags()
+                .into_iter()
+                .map(|tag| Ok((tag.into(), record.try_info(tag.as_bytes())?.unwrap())))
+                .collect::<anyhow::Result<_>>()?,
+            format: record
+                .format_tags()
+                .into_iter()
+                .map(|tag| Ok((tag.into(), record.try_format(tag.as_bytes())?.unwrap())))
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+impl<R: Record> From<&R> for VariantRecord {
+    /// Decodes all fields of the given record.
+    ///
+    /// # Panics
+    ///
+    /// If a field cannot be decoded, see `VariantRecord::try_from_record`.
+    fn from(record: &R) -> Self {
+        VariantRecord::try_from_record(record).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+/// Checks a single INFO or FORMAT value against its header definition.
+fn check_value(
+    field: &str,
+    tag: &str,
+    kind: &InfoType,
+    number: &InfoNumber,
+    value: &TypedVec,
+    n_alleles: usize,
+    ploidy: usize,
+) -> anyhow::Result<()> {
+    if !kind.accepts(value) {
+        bail!(
+            "{} {} is declared as {:?}, got {:?}",
+            field,
+            tag,
+            kind,
+            value
+        );
+    }
+    let len = match value {
+        TypedVec::Int32(v) if *kind != InfoType::Flag => v.len(),
+        TypedVec::Float32(v) => v.len(),
+        // neither flags nor strings can be checked for the number of values
+        _ => return Ok(()),
+    };
+    match number.count(n_alleles, ploidy) {
+        Some(expected) if expected != len => bail!(
+            "{} {} requires {} values (Number={:?}), got {}",
+            field,
+            tag,
+            expected,
+            number,
+            len
+        ),
+        _ => Ok(()),
+    }
+}
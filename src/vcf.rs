@@ -0,0 +1,646 @@
+//! Reading VCF text files, i.e. the text counterpart to `BcfRecords`/`BcfRecord`.
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::bgzf::BgzfReader;
+use crate::parser;
+use crate::record::{GenotypeAllele, Record};
+use crate::region::{Interval, Position, Region};
+use crate::tabix::{vcf_line_interval, Chunk, TabixIndex};
+use crate::types::{
+    Header, HeaderFilter, InfoType, Text, TypedVec, MISSING_FLOAT, MISSING_INTEGER,
+};
+
+const CHROM: usize = 0;
+const POS: usize = 1;
+const ID: usize = 2;
+const REF: usize = 3;
+const ALT: usize = 4;
+const QUAL: usize = 5;
+const FILTER: usize = 6;
+const INFO: usize = 7;
+const FORMAT: usize = 8;
+
+/// Reads records from VCF text, either uncompressed or (BGZF) gzip compressed.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::writer::{Compression, VcfWriter};
+/// use rust_bcf::{BcfRecords, Record, VcfRecords};
+///
+/// let records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+/// let mut writer = VcfWriter::new(Vec::new(), records.header(), Compression::Bgzf).unwrap();
+/// records.for_each(|record| writer.write(&record).unwrap());
+/// let vcf = writer.finish().unwrap();
+///
+/// let mut records = VcfRecords::new(&vcf[..]).unwrap();
+/// assert_eq!(records.header().samples(), &["HG001", "INTEGRATION", "HG003"]);
+/// let record = records.next().unwrap();
+/// assert_eq!(record.id(), b"TestId123");
+/// assert_eq!(record.info(b"platforms").unwrap().integer(), [3]);
+/// ```
+pub struct VcfRecords<R: BufRead> {
+    header: Arc<Header>,
+    line_buf: String,
+    inner: R,
+    index: Option<TabixIndex>,
+    /// The error which ended iteration, if any
+    error: Option<anyhow::Error>,
+    /// The number of lines read so far, including the header
+    line_number: usize,
+}
+
+impl<R: BufRead> VcfRecords<R> {
+    pub fn header(&self) -> &Header {
+        self.header.as_ref()
+    }
+
+    /// Returns (and clears) the error which ended iteration early, e.g. an IO error, invalid
+    /// UTF-8 or a line with fewer than 8 columns. Iteration ends without error at the end of
+    /// the input only, so a `None` after iterating means that all records have been read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::VcfRecords;
+    ///
+    /// let text = "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+    ///             chr1\t10\t.\tA\t.\t.\t.\t.\n\
+    ///             chr1\t20\t.\tA\n";
+    /// let mut records = VcfRecords::new(text.as_bytes()).unwrap();
+    /// assert_eq!(records.by_ref().count(), 1);
+    /// assert!(records.take_error().is_some());
+    /// ```
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        self.error.take()
+    }
+
+    /// Returns the 1-based line number of the record returned last, for error messages.
+    /// After seeking, e.g. in `query`, line numbers are relative to the seek target.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+}
+
+impl VcfRecords<BufReader<Box<dyn Read>>> {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let (reader, _format) = niffler::from_path(path)?;
+        Self::from_text(BufReader::new(reader))
+    }
+}
+
+impl<'a> VcfRecords<BufReader<Box<dyn Read + 'a>>> {
+    /// Creates a new reader, reading the header from `reader`.
+    /// Compressed input is detected and decompressed automatically.
+    pub fn new<R: Read + 'a>(reader: R) -> anyhow::Result<Self> {
+        let (reader, _format) = niffler::get_reader(Box::new(reader))?;
+        Self::from_text(BufReader::new(reader))
+    }
+}
+
+impl<R: BufRead> VcfRecords<R> {
+    /// Creates a new reader from uncompressed VCF text.
+    pub fn from_text(mut reader: R) -> anyhow::Result<Self> {
+        // the header is parsed with the same grammar as the header of a BCF file,
+        // which is terminated by a NUL byte
+        let mut text = String::new();
+        let mut line_number = 0;
+        loop {
+            let n = reader.read_line(&mut text)?;
+            line_number += 1;
+            if n == 0 {
+                anyhow::bail!("VCF header is missing the #CHROM line");
+            }
+            let line = &text[text.len() - n..];
+            if !line.starts_with("##") {
+                if !line.starts_with("#CHROM") {
+                    anyhow::bail!("VCF header is missing the #CHROM line");
+                }
+                if !line.ends_with('\n') {
+                    text.push('\n');
+                }
+                break;
+            }
+        }
+        let mut text = text.into_bytes();
+        text.push(0);
+        let (input, header) = parser::header(text.len() as u32, &text)
+            .map_err(|e| anyhow::anyhow!("Failed to parse VCF header: {:?}", e))?;
+        assert!(input.is_empty());
+
+        Ok(Self {
+            header: Arc::new(header),
+            line_buf: String::new(),
+            inner: reader,
+            index: None,
+            error: None,
+            line_number,
+        })
+    }
+}
+
+impl VcfRecords<BgzfReader<BufReader<File>>> {
+    /// Opens a BGZF compressed VCF file along with its tabix index `<path>.tbi`,
+    /// which allows for region queries via `query`.
+    pub fn from_indexed_path<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let mut index_path = path.as_ref().as_os_str().to_owned();
+        index_path.push(".tbi");
+        let index = TabixIndex::from_path(index_path)?;
+        let reader = BgzfReader::new(BufReader::new(File::open(path)?));
+        Ok(Self::from_text(reader)?.with_index(index))
+    }
+}
+
+impl<R: Read + Seek> VcfRecords<BgzfReader<R>> {
+    /// Sets the index used for region queries.
+    pub fn with_index(mut self, index: TabixIndex) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Returns all records of the given contig which overlap the 0-based, half-open interval
+    /// `[start, end)`. A record spans from its position to its INFO `END` (if given), or else
+    /// to the end of its reference allele. The contig may also be given by an alias of its
+    /// name, as with `Header::resolve_contig`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use rust_bcf::bgzf::BgzfReader;
+    /// use rust_bcf::tabix::TabixIndex;
+    /// use rust_bcf::writer::{Compression, VcfWriter};
+    /// use rust_bcf::{BcfRecords, Record, VcfRecords};
+    ///
+    /// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+    /// let mut writer = VcfWriter::new(Vec::new(), records.header(), Compression::Bgzf).unwrap();
+    /// records.for_each(|record| writer.write(&record).unwrap());
+    /// let vcf = writer.finish().unwrap();
+    ///
+    /// let index = TabixIndex::build(&vcf[..]).unwrap();
+    /// let reader = BgzfReader::new(Cursor::new(vcf));
+    /// let mut records = VcfRecords::from_text(reader).unwrap().with_index(index);
+    /// let positions = records
+    ///     .query("chr1", 817185, 817340)
+    ///     .unwrap()
+    ///     .map(|record| record.pos())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(positions, [817185]);
+    /// ```
+    pub fn query(&mut self, contig: &str, start: u32, end: u32) -> anyhow::Result<Query<'_, R>> {
+        let index = self
+            .index
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("region queries require a tabix index"))?;
+        if end < start {
+            anyhow::bail!("region end {} is before start {}", end, start);
+        }
+        let contig = self
+            .header
+            .aliases()
+            .find(contig, index.names().iter().map(String::as_str))
+            .unwrap_or(contig)
+            .to_string();
+        let chunks = index.chunks(&contig, start, end);
+        Ok(Query {
+            records: self,
+            chunks: chunks.into_iter(),
+            chunk_end: 0,
+            contig,
+            interval: Interval::from_zero_based(start, end),
+        })
+    }
+
+    /// Returns all records which overlap the given region, see `query`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use rust_bcf::bgzf::BgzfReader;
+    /// use rust_bcf::region::Region;
+    /// use rust_bcf::tabix::TabixIndex;
+    /// use rust_bcf::writer::{Compression, VcfWriter};
+    /// use rust_bcf::{BcfRecords, Record, VcfRecords};
+    ///
+    /// let records = BcfRecords::from_path("resources/example.uncompressed.bcf").unwrap();
+    /// let mut writer = VcfWriter::new(Vec::new(), records.header(), Compression::Bgzf).unwrap();
+    /// records.for_each(|record| writer.write(&record).unwrap());
+    /// let vcf = writer.finish().unwrap();
+    ///
+    /// let index = TabixIndex::build(&vcf[..]).unwrap();
+    /// let reader = BgzfReader::new(Cursor::new(vcf));
+    /// let mut records = VcfRecords::from_text(reader).unwrap().with_index(index);
+    /// let region = "chr1:817,186-817,340".parse::<Region>().unwrap();
+    /// let positions = records
+    ///     .query_region(&region)
+    ///     .unwrap()
+    ///     .map(|record| record.position().one_based())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(positions, [817186]);
+    /// ```
+    pub fn query_region(&mut self, region: &Region) -> anyhow::Result<Query<'_, R>> {
+        let interval = region.interval();
+        self.query(
+            region.contig(),
+            interval.start().zero_based(),
+            interval.end().zero_based(),
+        )
+    }
+}
+
+/// An iterator over the records overlapping a region, see `VcfRecords::query`. Errors end
+/// iteration and are returned by `VcfRecords::take_error`.
+pub struct Query<'a, R: Read + Seek> {
+    records: &'a mut VcfRecords<BgzfReader<R>>,
+    chunks: std::vec::IntoIter<Chunk>,
+    /// The end of the current chunk
+    chunk_end: u64,
+    contig: String,
+    interval: Interval,
+}
+
+impl<'a, R: Read + Seek> Iterator for Query<'a, R> {
+    type Item = VcfRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.records.inner.virtual_offset() >= self.chunk_end {
+                let chunk = self.chunks.next()?;
+                if let Err(e) = self.records.inner.seek(*chunk.begin()) {
+                    self.records.error = Some(e.into());
+                    return None;
+                }
+                self.chunk_end = *chunk.end();
+            }
+            let record = self.records.next()?;
+            if record.chrom() != self.contig {
+                continue;
+            }
+            let (start, end) = match vcf_line_interval(record.line()) {
+                Ok(interval) => interval,
+                Err(e) => {
+                    self.records.error = Some(e);
+                    return None;
+                }
+            };
+            let span = Interval::from_zero_based(start, end);
+            // records are sorted by position, hence all remaining records start after the region
+            if span.start() >= self.interval.end() {
+                self.chunks = Vec::new().into_iter();
+                self.chunk_end = 0;
+                return None;
+            }
+            if span.overlaps(&self.interval) {
+                return Some(record);
+            }
+        }
+    }
+}
+
+/// Iterates over the records until the end of the input or the first error, see `take_error`.
+impl<R: BufRead> Iterator for VcfRecords<R> {
+    type Item = VcfRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        loop {
+            self.line_buf.clear();
+            match self.inner.read_line(&mut self.line_buf) {
+                Ok(0) => return None,
+                Ok(_) => self.line_number += 1,
+                Err(e) => {
+                    self.error = Some(e.into());
+                    return None;
+                }
+            }
+            let line = self.line_buf.trim_end_matches(&['\n', '\r'][..]);
+            if !line.is_empty() {
+                return match VcfRecord::new(line.into(), self.header.clone()) {
+                    Ok(record) => Some(record),
+                    Err(e) => {
+                        self.error = Some(e);
+                        None
+                    }
+                };
+            }
+        }
+    }
+}
+
+/// A single line of a VCF file. Only the column boundaries are determined upfront,
+/// all values are parsed on access.
+#[derive(Debug, Clone)]
+pub struct VcfRecord {
+    line: String,
+    columns: Vec<Range<usize>>,
+    header: Arc<Header>,
+    /// 0-based, see `pos`
+    pos: u32,
+    qual: Option<f32>,
+}
+
+impl VcfRecord {
+    pub(crate) fn new(line: String, header: Arc<Header>) -> anyhow::Result<Self> {
+        let mut columns = Vec::with_capacity(FORMAT + 1 + header.samples.len());
+        let mut start = 0;
+        for (i, _) in line.match_indices('\t') {
+            columns.push(start..i);
+            start = i + 1;
+        }
+        columns.push(start..line.len());
+        if columns.len() <= INFO {
+            anyhow::bail!(
+                "VCF line has {} columns, expected at least {}: {}",
+                columns.len(),
+                INFO + 1,
+                line
+            );
+        }
+        // Telomeric records at POS 0 are at the first position of the contig, i.e. also at 0.
+        let pos = &line[columns[POS].clone()];
+        let pos = pos
+            .parse()
+            .map(|pos| Position::from_one_based(pos).map_or(0, Position::zero_based))
+            .map_err(|_| anyhow::anyhow!("invalid POS {}: {}", pos, line))?;
+        let qual = match &line[columns[QUAL].clone()] {
+            "." => None,
+            qual => Some(
+                qual.parse()
+                    .map_err(|_| anyhow::anyhow!("invalid QUAL {}: {}", qual, line))?,
+            ),
+        };
+        Ok(Self {
+            line,
+            columns,
+            header,
+            pos,
+            qual,
+        })
+    }
+
+    /// Returns the header this record was read with.
+    pub fn header(&self) -> &Arc<Header> {
+        &self.header
+    }
+
+    /// Returns the raw text of this record, without line break.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    fn column(&self, index: usize) -> &str {
+        self.columns
+            .get(index)
+            .map_or("", |range| &self.line[range.clone()])
+    }
+
+    /// Returns the INFO entries as `(tag, value)` pairs; flags have no value.
+    pub(crate) fn info_fields(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        let info = self.column(INFO);
+        info.split(';')
+            .filter(move |_| info != ".")
+            .map(|field| match field.split_once('=') {
+                Some((tag, value)) => (tag, Some(value)),
+                None => (field, None),
+            })
+    }
+
+    pub(crate) fn samples(&self) -> impl Iterator<Item = &str> {
+        (FORMAT + 1..self.columns.len()).map(move |i| self.column(i))
+    }
+}
+
+/// Parses a comma separated VCF value according to its declared type (strings, if undeclared).
+/// Like in BCF files written by htslib, missing strings are kept as `.`.
+/// `field` and `tag` (e.g. `INFO` and `DP`) are only used for error messages.
+fn typed_value(
+    field: &str,
+    tag: &str,
+    kind: Option<&InfoType>,
+    value: &str,
+) -> anyhow::Result<TypedVec> {
+    Ok(match kind {
+        Some(InfoType::Integer) => TypedVec::Int32(
+            value
+                .split(',')
+                .map(|v| match v {
+                    "." => Ok(MISSING_INTEGER),
+                    v => v.parse().map_err(|_| {
+                        anyhow::anyhow!("invalid Integer value {} of {} {}", v, field, tag)
+                    }),
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Some(InfoType::Float) => TypedVec::Float32(
+            value
+                .split(',')
+                .map(|v| match v {
+                    "." => Ok(f32::from_bits(MISSING_FLOAT)),
+                    v => v.parse().map_err(|_| {
+                        anyhow::anyhow!("invalid Float value {} of {} {}", v, field, tag)
+                    }),
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Some(InfoType::Flag) => TypedVec::Missing,
+        _ => TypedVec::UString(value.as_bytes().to_vec()),
+    })
+}
+
+/// Parses a genotype such as `0/1` or `1|0` into its BCF encoding, see `GenotypeAllele`.
+fn encoded_genotype(value: &str) -> anyhow::Result<TypedVec> {
+    let mut alleles = vec![];
+    let mut phased = false;
+    let mut start = 0;
+    for (i, c) in value
+        .char_indices()
+        .chain(std::iter::once((value.len(), '/')))
+    {
+        if c == '/' || c == '|' {
+            let allele = match &value[start..i] {
+                "." if phased => GenotypeAllele::PhasedMissing,
+                "." => GenotypeAllele::UnphasedMissing,
+                index => {
+                    let index = index
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("invalid genotype {} of FORMAT GT", value))?;
+                    if phased {
+                        GenotypeAllele::Phased(index)
+                    } else {
+                        GenotypeAllele::Unphased(index)
+                    }
+                }
+            };
+            alleles.push(i32::from(allele));
+            phased = c == '|';
+            start = i + 1;
+        }
+    }
+    Ok(TypedVec::Int32(alleles))
+}
+
+impl Record for VcfRecord {
+    fn id(&self) -> Text {
+        match self.column(ID) {
+            "." => vec![],
+            id => id.as_bytes().to_vec(),
+        }
+    }
+
+    fn chrom(&self) -> &str {
+        self.column(CHROM)
+    }
+
+    /// Returns the 0-based position, like `BcfRecord::pos`.
+    /// Telomeric records at POS 0 are at the first position of the contig, i.e. also at 0.
+    fn pos(&self) -> u32 {
+        self.pos
+    }
+
+    fn ref_allele(&self) -> Text {
+        self.column(REF).as_bytes().to_vec()
+    }
+
+    fn alt_alleles(&self) -> Vec<Text> {
+        match self.column(ALT) {
+            "." => vec![],
+            alt => alt.split(',').map(|a| a.as_bytes().to_vec()).collect(),
+        }
+    }
+
+    fn qual(&self) -> Option<f32> {
+        self.qual
+    }
+
+    fn filters(&self) -> Vec<&str> {
+        match self.column(FILTER) {
+            "." => vec![],
+            filters => filters.split(';').collect(),
+        }
+    }
+
+    /// Returns the header definitions of the filters for this record.
+    /// Filters without a definition in the header are skipped.
+    fn filter_headers(&self) -> Vec<&HeaderFilter> {
+        self.filters()
+            .into_iter()
+            .filter_map(|filter| self.header.filter_by_id(filter))
+            .collect()
+    }
+
+    /// For a given INFO tag, return its contents, parsed according to its declared type.
+    /// Values of undeclared tags are returned as strings. Like htslib does in BCF, flags are
+    /// represented by a missing value.
+    ///
+    /// # Panics
+    ///
+    /// If the value does not match the declared type, see `try_info`.
+    fn info(&self, tag: &[u8]) -> Option<TypedVec> {
+        self.try_info(tag).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// For a given INFO tag, return its contents like `info`, or an error if the value does not
+    /// match the declared type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_bcf::{Record, VcfRecords};
+    ///
+    /// let text = "##fileformat=VCFv4.2\n##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth\">\n\
+    ///             #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+    ///             chr1\t10\t.\tA\t.\t.\t.\tDP=abc\n";
+    /// let record = VcfRecords::new(text.as_bytes()).unwrap().next().unwrap();
+    /// let error = record.try_info(b"DP").unwrap_err();
+    /// assert_eq!(error.to_string(), "invalid Integer value abc of INFO DP");
+    /// ```
+    fn try_info(&self, tag: &[u8]) -> anyhow::Result<Option<TypedVec>> {
+        let tag = std::str::from_utf8(tag)?;
+        let value = match self.info_fields().find(|(t, _)| *t == tag) {
+            Some((_, value)) => value,
+            None => return Ok(None),
+        };
+        let kind = self
+            .header
+            .info_tag_to_offset
+            .get(tag)
+            .map(|offset| self.header.info[offset].kind());
+        Ok(Some(match value {
+            Some(value) => typed_value("INFO", tag, kind, value)?,
+            None => TypedVec::Missing,
+        }))
+    }
+
+    /// For a given FORMAT tag, return its contents for each sample. Fields dropped from the
+    /// end of a sample column are returned as missing values.
+    ///
+    /// # Panics
+    ///
+    /// If a value does not match the declared type, see `try_format`.
+    fn format(&self, tag: &[u8]) -> Option<Vec<TypedVec>> {
+        self.try_format(tag).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// For a given FORMAT tag, return its contents like `format`, or an error if a value does
+    /// not match the declared type or, for GT, is not a valid genotype.
+    fn try_format(&self, tag: &[u8]) -> anyhow::Result<Option<Vec<TypedVec>>> {
+        let tag = std::str::from_utf8(tag)?;
+        let index = match self.format_tags().iter().position(|t| *t == tag) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let kind = self
+            .header
+            .format_tag_to_offset
+            .get(tag)
+            .map(|offset| self.header.format[offset].kind());
+        self.samples()
+            .map(|sample| {
+                let value = sample.split(':').nth(index).unwrap_or(".");
+                if tag == "GT" {
+                    encoded_genotype(value)
+                } else {
+                    typed_value("FORMAT", tag, kind, value)
+                }
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Some)
+    }
+
+    fn info_tags(&self) -> Vec<&str> {
+        self.info_fields().map(|(tag, _)| tag).collect()
+    }
+
+    fn format_tags(&self) -> Vec<&str> {
+        match self.column(FORMAT) {
+            "" => vec![],
+            format => format.split(':').collect(),
+        }
+    }
+
+    fn genotypes(&self) -> Vec<Vec<GenotypeAllele>> {
+        let gts = self.format(b"GT").unwrap_or_default();
+        gts.iter()
+            .map(|gt| {
+                gt.integer()
+                    .iter()
+                    .cloned()
+                    .map(GenotypeAllele::from)
+                    .collect_vec()
+            })
+            .collect()
+    }
+
+    fn has_flag(&self, tag: &[u8]) -> bool {
+        self.info_fields().any(|(t, _)| t.as_bytes() == tag)
+    }
+}
@@ -0,0 +1,348 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::bgzf::BgzfWriter;
+use crate::encoder;
+use crate::record::{BcfRecord, GenotypeAllele, Record};
+use crate::types::{Header, InfoType, TypedVec, MISSING_FLOAT, MISSING_INTEGER};
+use crate::variant::VariantRecord;
+
+const BCF_MAGIC: &[u8] = b"BCF";
+const BCF_MAJOR_VERSION: u8 = 2;
+const BCF_MINOR_VERSION: u8 = 2;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Compression {
+    Uncompressed,
+    Bgzf,
+}
+
+/// The underlying output, either written as-is or BGZF compressed.
+pub(crate) enum Output<W: Write> {
+    Uncompressed(W),
+    Bgzf(BgzfWriter<W>),
+}
+
+impl<W: Write> Output<W> {
+    pub(crate) fn new(inner: W, compression: Compression) -> Self {
+        match compression {
+            Compression::Uncompressed => Output::Uncompressed(inner),
+            Compression::Bgzf => Output::Bgzf(BgzfWriter::new(inner)),
+        }
+    }
+
+    pub(crate) fn finish(self) -> io::Result<W> {
+        match self {
+            Output::Uncompressed(mut inner) => {
+                inner.flush()?;
+                Ok(inner)
+            }
+            Output::Bgzf(inner) => inner.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Uncompressed(inner) => inner.write(buf),
+            Output::Bgzf(inner) => inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Uncompressed(inner) => inner.flush(),
+            Output::Bgzf(inner) => inner.flush(),
+        }
+    }
+}
+
+/// Writes records in BCF format, either uncompressed or BGZF compressed.
+///
+/// Call `finish` when done, which (for BGZF) writes the last block and the EOF marker.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::writer::{BcfWriter, Compression};
+/// use rust_bcf::BcfRecords;
+///
+/// let records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+/// let mut writer = BcfWriter::new(Vec::new(), records.header(), Compression::Uncompressed).unwrap();
+/// for record in records {
+///     writer.write(&record).unwrap();
+/// }
+/// let bcf = writer.finish().unwrap();
+/// assert_eq!(BcfRecords::new(&bcf[..]).unwrap().count(), 1);
+/// ```
+pub struct BcfWriter<W: Write> {
+    header: Header,
+    inner: Output<W>,
+}
+
+impl BcfWriter<BufWriter<File>> {
+    pub fn to_path<P: AsRef<Path>>(
+        path: P,
+        header: &Header,
+        compression: Compression,
+    ) -> anyhow::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Self::new(file, header, compression)
+    }
+}
+
+impl<W: Write> BcfWriter<W> {
+    /// Creates a new writer and writes the BCF magic and the given header.
+    pub fn new(inner: W, header: &Header, compression: Compression) -> anyhow::Result<Self> {
+        let mut inner = Output::new(inner, compression);
+        inner.write_all(BCF_MAGIC)?;
+        inner.write_all(&[BCF_MAJOR_VERSION, BCF_MINOR_VERSION])?;
+        let mut text = encoder::bcf_header_text(header).into_bytes();
+        text.push(b'\0');
+        inner.write_all(&(text.len() as u32).to_le_bytes())?;
+        inner.write_all(&text)?;
+        Ok(BcfWriter {
+            header: header.clone(),
+            inner,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Writes an encoded record. The record must have been encoded with respect to the header
+    /// of this writer (or one with the same dictionary, e.g. the header of the input file).
+    pub fn write(&mut self, record: &BcfRecord) -> anyhow::Result<()> {
+        self.write_raw(&record.shared, &record.format)
+    }
+
+    /// Encodes the given record with respect to the header of this writer and writes it.
+    pub fn write_variant(&mut self, record: &VariantRecord) -> anyhow::Result<()> {
+        let (shared, indiv) = record.encode(&self.header)?;
+        self.write_raw(&shared, &indiv)
+    }
+
+    fn write_raw(&mut self, shared: &[u8], indiv: &[u8]) -> anyhow::Result<()> {
+        self.inner.write_all(&(shared.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&(indiv.len() as u32).to_le_bytes())?;
+        self.inner.write_all(shared)?;
+        self.inner.write_all(indiv)?;
+        Ok(())
+    }
+
+    /// Flushes all remaining data and returns the underlying writer.
+    pub fn finish(self) -> anyhow::Result<W> {
+        Ok(self.inner.finish()?)
+    }
+}
+
+/// Writes records as VCF text lines, either uncompressed or BGZF compressed (i.e. `.vcf.gz`).
+///
+/// Call `finish` when done, which (for BGZF) writes the last block and the EOF marker.
+///
+/// # Examples
+///
+/// ```
+/// use rust_bcf::writer::{Compression, VcfWriter};
+/// use rust_bcf::BcfRecords;
+///
+/// let records = BcfRecords::from_path("resources/example.id.bcf").unwrap();
+/// let mut writer = VcfWriter::new(Vec::new(), records.header(), Compression::Uncompressed).unwrap();
+/// for record in records {
+///     writer.write(&record).unwrap();
+/// }
+/// let vcf = String::from_utf8(writer.finish().unwrap()).unwrap();
+/// let line = vcf.lines().last().unwrap();
+/// assert!(line.starts_with("chr1\t817186\tTestId123\tG\tA\t50\tPASS\t"));
+/// ```
+pub struct VcfWriter<W: Write> {
+    header: Header,
+    inner: Output<W>,
+}
+
+impl VcfWriter<BufWriter<File>> {
+    pub fn to_path<P: AsRef<Path>>(
+        path: P,
+        header: &Header,
+        compression: Compression,
+    ) -> anyhow::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Self::new(file, header, compression)
+    }
+}
+
+impl<W: Write> VcfWriter<W> {
+    /// Creates a new writer and writes the given header.
+    pub fn new(inner: W, header: &Header, compression: Compression) -> anyhow::Result<Self> {
+        let mut inner = Output::new(inner, compression);
+        inner.write_all(header.to_vcf_text().as_bytes())?;
+        Ok(VcfWriter {
+            header: header.clone(),
+            inner,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Writes a record as a single VCF line. INFO values are formatted according to their
+    /// declared type in the header of this writer, e.g. flags are written without value.
+    pub fn write<R: Record>(&mut self, record: &R) -> anyhow::Result<()> {
+        let mut line = vcf_line(record, &self.header)?;
+        line.push('\n');
+        self.inner.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Flushes all remaining data and returns the underlying writer.
+    pub fn finish(self) -> anyhow::Result<W> {
+        Ok(self.inner.finish()?)
+    }
+}
+
+/// Returns the given text, or `.` if it is empty.
+fn text_or_missing(text: &[u8]) -> String {
+    if text.is_empty() {
+        ".".into()
+    } else {
+        String::from_utf8_lossy(text).into()
+    }
+}
+
+/// Formats an INFO or FORMAT value as in VCF, i.e. comma-separated with `.` for missing values.
+pub(crate) fn vcf_value(value: &TypedVec) -> String {
+    match value {
+        TypedVec::Missing => ".".into(),
+        TypedVec::Int32(v) if v.is_empty() => ".".into(),
+        TypedVec::Int32(v) => v
+            .iter()
+            .map(|&v| {
+                if v == MISSING_INTEGER {
+                    ".".into()
+                } else {
+                    v.to_string()
+                }
+            })
+            .join(","),
+        TypedVec::Float32(v) if v.is_empty() => ".".into(),
+        TypedVec::Float32(v) => v
+            .iter()
+            .map(|v| {
+                if v.to_bits() == MISSING_FLOAT {
+                    ".".into()
+                } else {
+                    v.to_string()
+                }
+            })
+            .join(","),
+        TypedVec::UString(v) => text_or_missing(v),
+    }
+}
+
+/// Formats an encoded GT value, e.g. `0/1` or `1|0`.
+pub(crate) fn vcf_genotype(value: &TypedVec) -> String {
+    match value {
+        TypedVec::Int32(alleles) if !alleles.is_empty() && alleles[0] != MISSING_INTEGER => {
+            let mut gt = String::new();
+            for (i, &allele) in alleles.iter().enumerate() {
+                let allele = GenotypeAllele::from(allele);
+                if i > 0 {
+                    // the phasing of the first allele is irrelevant
+                    gt.push(match allele {
+                        GenotypeAllele::Phased(_) | GenotypeAllele::PhasedMissing => '|',
+                        _ => '/',
+                    });
+                }
+                match allele.index() {
+                    Some(index) => gt.push_str(&index.to_string()),
+                    None => gt.push('.'),
+                }
+            }
+            gt
+        }
+        other => vcf_value(other),
+    }
+}
+
+/// Formats the given record as a VCF line (without line break).
+fn vcf_line<R: Record>(record: &R, header: &Header) -> anyhow::Result<String> {
+    let alt_alleles = record.alt_alleles();
+    let filters = record.try_filters()?;
+    let info = record
+        .info_tags()
+        .into_iter()
+        .map(|tag| {
+            let kind = header
+                .info_tag_to_offset
+                .get(tag)
+                .map(|offset| header.info[offset].kind());
+            Ok(match (kind, record.try_info(tag.as_bytes())?.unwrap()) {
+                (Some(InfoType::Flag), _) => tag.to_string(),
+                (_, value) => format!("{}={}", tag, vcf_value(&value)),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut columns = vec![
+        record.try_chrom()?.to_string(),
+        (record.pos() + 1).to_string(),
+        text_or_missing(&record.id()),
+        text_or_missing(&record.ref_allele()),
+        if alt_alleles.is_empty() {
+            ".".into()
+        } else {
+            alt_alleles
+                .iter()
+                .map(|a| String::from_utf8_lossy(a))
+                .join(",")
+        },
+        record.qual().map_or_else(|| ".".into(), |q| q.to_string()),
+        if filters.is_empty() {
+            ".".into()
+        } else {
+            filters.join(";")
+        },
+        if info.is_empty() {
+            ".".into()
+        } else {
+            info.join(";")
+        },
+    ];
+
+    let tags = record.format_tags();
+    if !header.samples.is_empty() && !tags.is_empty() {
+        let values = tags
+            .iter()
+            .map(|tag| {
+                Ok(record
+                    .try_format(tag.as_bytes())?
+                    .unwrap()
+                    .iter()
+                    .map(|value| match *tag {
+                        "GT" => vcf_genotype(value),
+                        _ => vcf_value(value),
+                    })
+                    .collect_vec())
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        columns.push(tags.join(":"));
+        for sample in 0..header.samples.len() {
+            // samples without a value (e.g. of a `VariantRecord` with fewer samples) are missing
+            let mut fields = values
+                .iter()
+                .map(|v| v.get(sample).map_or(".", String::as_str))
+                .collect_vec();
+            // trailing missing fields may be dropped, but at least one field has to remain
+            while fields.len() > 1 && fields[fields.len() - 1].split(',').all(|v| v == ".") {
+                fields.pop();
+            }
+            columns.push(fields.join(":"));
+        }
+    }
+    Ok(columns.join("\t"))
+}
@@ -0,0 +1,755 @@
+//! Export of BCF files into the VCF Zarr layout read by sgkit (behind the `zarr` feature).
+//!
+//! The output is a Zarr v2 directory store with zlib-compressed chunks and consolidated metadata.
+//! Following the VCF Zarr conventions, missing integers are `-1` and missing floats use the BCF
+//! missing value, whereas values which do not exist (such as the third allele of a biallelic
+//! record) are filled with `-2` and the BCF end-of-vector value, respectively.
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use flate2::write::ZlibEncoder;
+use serde_json::{json, Value};
+
+use crate::reader::BcfRecords;
+use crate::record::Record;
+use crate::types::{
+    Header, InfoNumber, InfoType, TypedVec, END_OF_VECTOR_FLOAT_32, MISSING_FLOAT, MISSING_INTEGER,
+};
+
+const INT_MISSING: i32 = -1;
+const INT_FILL: i32 = -2;
+const STRING_MISSING: &str = ".";
+const STRING_FILL: &str = "";
+
+/// Chunk sizes and compression of a Zarr store, see `bcf_to_zarr`.
+#[derive(Debug, Clone)]
+pub struct ZarrOptions {
+    /// The number of variants per chunk
+    pub variants_chunk_size: usize,
+    /// The number of samples per chunk
+    pub samples_chunk_size: usize,
+    /// The zlib compression level, from 0 to 9
+    pub compression_level: u32,
+}
+
+impl Default for ZarrOptions {
+    fn default() -> Self {
+        ZarrOptions {
+            variants_chunk_size: 10_000,
+            samples_chunk_size: 1_000,
+            compression_level: 5,
+        }
+    }
+}
+
+/// The values of a chunk (or of a part of an array which is not yet written).
+#[derive(Debug)]
+enum Buffer {
+    Bool(Vec<bool>),
+    Int8(Vec<i8>),
+    Int32(Vec<i32>),
+    Float32(Vec<f32>),
+    Str(Vec<String>),
+}
+
+impl Buffer {
+    fn len(&self) -> usize {
+        match self {
+            Buffer::Bool(v) => v.len(),
+            Buffer::Int8(v) => v.len(),
+            Buffer::Int32(v) => v.len(),
+            Buffer::Float32(v) => v.len(),
+            Buffer::Str(v) => v.len(),
+        }
+    }
+
+    /// Removes the first `n` values.
+    fn drain(&mut self, n: usize) {
+        match self {
+            Buffer::Bool(v) => drop(v.drain(..n)),
+            Buffer::Int8(v) => drop(v.drain(..n)),
+            Buffer::Int32(v) => drop(v.drain(..n)),
+            Buffer::Float32(v) => drop(v.drain(..n)),
+            Buffer::Str(v) => drop(v.drain(..n)),
+        }
+    }
+
+    fn dtype(&self) -> &'static str {
+        match self {
+            Buffer::Bool(_) => "|b1",
+            Buffer::Int8(_) => "|i1",
+            Buffer::Int32(_) => "<i4",
+            Buffer::Float32(_) => "<f4",
+            Buffer::Str(_) => "|O",
+        }
+    }
+
+    fn fill_value(&self) -> Value {
+        match self {
+            Buffer::Bool(_) => json!(false),
+            Buffer::Int8(_) | Buffer::Int32(_) => json!(INT_FILL),
+            Buffer::Float32(_) => json!("NaN"),
+            Buffer::Str(_) => Value::Null,
+        }
+    }
+
+    /// Returns the encoded values of a block starting at value `skip`, padded with fill values,
+    /// see `block`.
+    fn encode_block(
+        &self,
+        skip: usize,
+        rows: usize,
+        inner: &[usize],
+        samples: Option<(usize, usize)>,
+    ) -> Vec<u8> {
+        match self {
+            Buffer::Bool(v) => block(&v[skip..], rows, inner, samples, false)
+                .into_iter()
+                .map(u8::from)
+                .collect(),
+            Buffer::Int8(v) => block(&v[skip..], rows, inner, samples, INT_FILL as i8)
+                .into_iter()
+                .map(|v| v as u8)
+                .collect(),
+            Buffer::Int32(v) => block(&v[skip..], rows, inner, samples, INT_FILL)
+                .into_iter()
+                .flat_map(i32::to_le_bytes)
+                .collect(),
+            Buffer::Float32(v) => block(
+                &v[skip..],
+                rows,
+                inner,
+                samples,
+                f32::from_bits(END_OF_VECTOR_FLOAT_32),
+            )
+            .into_iter()
+            .flat_map(|v| v.to_bits().to_le_bytes())
+            .collect(),
+            // the vlen-utf8 encoding of numcodecs
+            Buffer::Str(v) => {
+                let values = block(&v[skip..], rows, inner, samples, STRING_FILL.to_string());
+                let mut bytes = (values.len() as u32).to_le_bytes().to_vec();
+                for value in values {
+                    bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(value.as_bytes());
+                }
+                bytes
+            }
+        }
+    }
+}
+
+/// Extracts a block of `rows` rows (of the given inner shape) from `values`, i.e. either whole rows
+/// or, if `samples` is `(start, length)`, the given range of the second dimension.
+/// Rows and samples beyond the end of `values` are filled with `fill`.
+fn block<T: Clone>(
+    values: &[T],
+    rows: usize,
+    inner: &[usize],
+    samples: Option<(usize, usize)>,
+    fill: T,
+) -> Vec<T> {
+    let row_size = inner.iter().product::<usize>();
+    let n_rows = values.len() / row_size.max(1);
+    let mut block = vec![];
+    for row in 0..rows {
+        let values = &values[(row * row_size).min(values.len())..];
+        match samples {
+            Some((start, length)) => {
+                let rest = inner[1..].iter().product::<usize>();
+                for sample in start..start + length {
+                    if row < n_rows && sample < inner[0] {
+                        block.extend_from_slice(&values[sample * rest..(sample + 1) * rest]);
+                    } else {
+                        block.extend(std::iter::repeat_n(fill.clone(), rest));
+                    }
+                }
+            }
+            None if row < n_rows => block.extend_from_slice(&values[..row_size]),
+            None => block.extend(std::iter::repeat_n(fill.clone(), row_size)),
+        }
+    }
+    block
+}
+
+/// An array which is written chunk by chunk along its first dimension.
+#[derive(Debug)]
+struct ZarrArray {
+    name: String,
+    dimensions: Vec<String>,
+    /// The sizes of all dimensions but the first
+    inner: Vec<usize>,
+    /// The chunk size of the first dimension
+    chunk_rows: usize,
+    /// The chunk size of the second dimension, if it is chunked (i.e. the samples dimension)
+    chunk_samples: Option<usize>,
+    buffer: Buffer,
+    /// The number of rows so far, including buffered rows
+    rows: usize,
+}
+
+impl ZarrArray {
+    fn new(name: &str, dimensions: &[&str], inner: Vec<usize>, buffer: Buffer) -> Self {
+        ZarrArray {
+            name: name.into(),
+            dimensions: dimensions.iter().map(|d| d.to_string()).collect(),
+            inner,
+            chunk_rows: 1,
+            chunk_samples: None,
+            buffer,
+            rows: 0,
+        }
+    }
+
+    fn chunks(mut self, options: &ZarrOptions) -> Self {
+        self.chunk_rows = match self.dimensions[0].as_str() {
+            "variants" => options.variants_chunk_size,
+            "samples" => options.samples_chunk_size,
+            _ => usize::MAX,
+        };
+        if self.dimensions.get(1).map(String::as_str) == Some("samples") {
+            self.chunk_samples = Some(options.samples_chunk_size);
+        }
+        self
+    }
+
+    fn row_size(&self) -> usize {
+        self.inner.iter().product()
+    }
+
+    /// The chunk size of the first dimension; dimensions other than variants and samples are
+    /// not chunked.
+    fn chunk_rows(&self) -> usize {
+        if self.chunk_rows == usize::MAX {
+            self.rows.max(1)
+        } else {
+            self.chunk_rows
+        }
+    }
+
+    /// Writes all complete chunks of buffered rows, and (if `last`) the remaining rows.
+    fn flush(&mut self, store: &Path, level: u32, last: bool) -> anyhow::Result<()> {
+        let row_size = self.row_size();
+        if row_size == 0 {
+            // e.g. per-sample arrays without samples, which consist of empty chunks only
+            return Ok(());
+        }
+        let chunk_rows = self.chunk_rows();
+        let buffered = self.buffer.len() / row_size;
+        let first_chunk = (self.rows - buffered) / chunk_rows;
+        let directory = store.join(&self.name);
+        let trailing = ".0".repeat(self.inner.len() - usize::from(self.chunk_samples.is_some()));
+        let mut offset = 0;
+        while offset + chunk_rows <= buffered || (last && offset < buffered) {
+            fs::create_dir_all(&directory)?;
+            let index = first_chunk + offset / chunk_rows;
+            let blocks = match self.chunk_samples {
+                Some(chunk) => (0..self.inner[0].div_ceil(chunk))
+                    .map(|j| {
+                        (
+                            format!("{}.{}{}", index, j, trailing),
+                            Some((j * chunk, chunk)),
+                        )
+                    })
+                    .collect(),
+                None => vec![(format!("{}{}", index, trailing), None)],
+            };
+            for (key, samples) in blocks {
+                let bytes =
+                    self.buffer
+                        .encode_block(offset * row_size, chunk_rows, &self.inner, samples);
+                let mut encoder = ZlibEncoder::new(vec![], flate2::Compression::new(level));
+                encoder.write_all(&bytes)?;
+                fs::write(directory.join(key), encoder.finish()?)?;
+            }
+            offset += chunk_rows;
+        }
+        self.buffer.drain(offset.min(buffered) * row_size);
+        Ok(())
+    }
+
+    /// The `.zarray` and `.zattrs` metadata.
+    fn metadata(&self, level: u32) -> (Value, Value) {
+        let mut shape = vec![self.rows];
+        shape.extend(&self.inner);
+        let mut chunks = vec![self.chunk_rows()];
+        chunks.extend(self.inner.iter().map(|&n| n.max(1)));
+        if let Some(chunk) = self.chunk_samples {
+            chunks[1] = chunk;
+        }
+        let filters = match self.buffer {
+            Buffer::Str(_) => json!([{"id": "vlen-utf8"}]),
+            _ => Value::Null,
+        };
+        let zarray = json!({
+            "zarr_format": 2,
+            "shape": shape,
+            "chunks": chunks,
+            "dtype": self.buffer.dtype(),
+            "compressor": {"id": "zlib", "level": level},
+            "fill_value": self.buffer.fill_value(),
+            "order": "C",
+            "filters": filters,
+            "dimension_separator": ".",
+        });
+        let zattrs = json!({ "_ARRAY_DIMENSIONS": self.dimensions });
+        (zarray, zattrs)
+    }
+}
+
+/// An INFO or FORMAT field and its array.
+#[derive(Debug)]
+struct FieldArray {
+    tag: String,
+    format: bool,
+    /// The number of values, or `None` for a single value
+    count: Option<usize>,
+    array: ZarrArray,
+}
+
+/// The maximum dimensions observed in the first pass over the records.
+#[derive(Debug, Default)]
+struct Dimensions {
+    alleles: usize,
+    ploidy: usize,
+    /// The maximum number of values of each INFO (by tag) and FORMAT (by `FORMAT/` tag) field
+    counts: std::collections::HashMap<String, usize>,
+}
+
+fn value_count(value: &TypedVec) -> usize {
+    match value {
+        TypedVec::Missing => 0,
+        TypedVec::Int32(v) => v.len(),
+        TypedVec::Float32(v) => v.len(),
+        TypedVec::UString(v) => v.split(|&c| c == b',').count(),
+    }
+}
+
+impl Dimensions {
+    fn observe<R: Record>(&mut self, record: &R, header: &Header) {
+        self.alleles = self.alleles.max(1 + record.alt_alleles().len());
+        for info in header.info.values() {
+            if let Some(value) = record.info(info.id.as_bytes()) {
+                let count = self.counts.entry(info.id.clone()).or_default();
+                *count = (*count).max(value_count(&value));
+            }
+        }
+        for format in header.format.values() {
+            if let Some(values) = record.format(format.id.as_bytes()) {
+                let max = values.iter().map(value_count).max().unwrap_or(0);
+                if format.id == "GT" {
+                    self.ploidy = self.ploidy.max(max);
+                } else {
+                    let count = self
+                        .counts
+                        .entry(format!("FORMAT/{}", format.id))
+                        .or_default();
+                    *count = (*count).max(max);
+                }
+            }
+        }
+    }
+}
+
+/// Pushes exactly `n` values, padded with `fill`.
+fn push_padded<T: Clone, I: Iterator<Item = T>>(buffer: &mut Vec<T>, values: I, n: usize, fill: T) {
+    let start = buffer.len();
+    buffer.extend(values.take(n));
+    buffer.resize(start + n, fill);
+}
+
+/// Pushes the values of an INFO or FORMAT field (`None` if it is absent).
+fn push_value(buffer: &mut Buffer, value: Option<&TypedVec>, n: usize) {
+    match buffer {
+        Buffer::Bool(v) => v.push(value.is_some()),
+        Buffer::Int32(v) => {
+            let values = match value {
+                Some(TypedVec::Int32(values)) => values.as_slice(),
+                _ => &[MISSING_INTEGER],
+            };
+            let values = values
+                .iter()
+                .map(|&v| if v == MISSING_INTEGER { INT_MISSING } else { v });
+            push_padded(v, values, n, INT_FILL)
+        }
+        Buffer::Float32(v) => {
+            let missing = [f32::from_bits(MISSING_FLOAT)];
+            let values = match value {
+                Some(TypedVec::Float32(values)) => values.as_slice(),
+                _ => &missing,
+            };
+            let fill = f32::from_bits(END_OF_VECTOR_FLOAT_32);
+            push_padded(v, values.iter().copied(), n, fill)
+        }
+        Buffer::Str(v) => {
+            let text = match value {
+                Some(TypedVec::UString(text)) if !text.is_empty() => {
+                    String::from_utf8_lossy(text).into_owned()
+                }
+                _ => STRING_MISSING.into(),
+            };
+            if n == 1 {
+                v.push(text)
+            } else {
+                let values = text.split(',').map(String::from);
+                push_padded(v, values, n, STRING_FILL.into())
+            }
+        }
+        Buffer::Int8(_) => unreachable!(),
+    }
+}
+
+/// Converts a BCF file into a Zarr v2 directory store in the VCF Zarr layout, readable e.g. by
+/// `sgkit.load_dataset`; the directory must not exist yet.
+///
+/// The store contains `contig_id`, `filter_id` and `sample_id`, the per-variant arrays
+/// `variant_contig`, `variant_position` (1-based), `variant_id`, `variant_id_mask`,
+/// `variant_allele`, `variant_quality` and `variant_filter`, the per-call arrays
+/// `call_genotype`, `call_genotype_phased` and `call_genotype_mask`, and one array
+/// `variant_<ID>` per INFO and `call_<ID>` per FORMAT field. Fields whose array would replace
+/// one of the fixed arrays (e.g. an INFO field `id`) are an error. The input is read twice,
+/// once for determining the maximum number of alleles, ploidy and values per field.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rust_bcf::zarr::{bcf_to_zarr, ZarrOptions};
+///
+/// bcf_to_zarr("input.bcf", "output.vcz", ZarrOptions::default()).unwrap();
+/// ```
+pub fn bcf_to_zarr<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: ZarrOptions,
+) -> anyhow::Result<()> {
+    let store = output.as_ref().to_path_buf();
+    if store.exists() {
+        bail!("{} already exists", store.display());
+    }
+    if options.variants_chunk_size == 0 || options.samples_chunk_size == 0 {
+        bail!("chunk sizes must be positive");
+    }
+    let records = BcfRecords::from_path(&input)?;
+    let header = records.header().clone();
+    let mut dimensions = Dimensions::default();
+    records.for_each(|record| dimensions.observe(&record, &header));
+    let n_samples = header.samples.len();
+    let alleles = dimensions.alleles.max(1);
+    let ploidy = dimensions.ploidy.max(1);
+
+    let fields = field_arrays(&header, &dimensions, n_samples, &options);
+    let mut writer = StoreWriter {
+        store,
+        level: options.compression_level,
+        arrays: vec![],
+        fields,
+    };
+    let filters = header
+        .filter
+        .values()
+        .map(|f| f.id.clone())
+        .collect::<Vec<_>>();
+    let contigs = header
+        .contigs
+        .iter()
+        .map(|c| c.id.clone())
+        .collect::<Vec<_>>();
+    for (name, dimension, ids) in [
+        ("contig_id", "contigs", &contigs),
+        ("filter_id", "filters", &filters),
+        ("sample_id", "samples", &header.samples),
+    ] {
+        let mut array =
+            ZarrArray::new(name, &[dimension], vec![], Buffer::Str(ids.clone())).chunks(&options);
+        array.rows = ids.len();
+        writer.arrays.push(array);
+    }
+
+    let variant_arrays = vec![
+        ZarrArray::new(
+            "variant_contig",
+            &["variants"],
+            vec![],
+            Buffer::Int32(vec![]),
+        ),
+        ZarrArray::new(
+            "variant_position",
+            &["variants"],
+            vec![],
+            Buffer::Int32(vec![]),
+        ),
+        ZarrArray::new("variant_id", &["variants"], vec![], Buffer::Str(vec![])),
+        ZarrArray::new(
+            "variant_id_mask",
+            &["variants"],
+            vec![],
+            Buffer::Bool(vec![]),
+        ),
+        ZarrArray::new(
+            "variant_allele",
+            &["variants", "alleles"],
+            vec![alleles],
+            Buffer::Str(vec![]),
+        ),
+        ZarrArray::new(
+            "variant_quality",
+            &["variants"],
+            vec![],
+            Buffer::Float32(vec![]),
+        ),
+        ZarrArray::new(
+            "variant_filter",
+            &["variants", "filters"],
+            vec![filters.len()],
+            Buffer::Bool(vec![]),
+        ),
+        ZarrArray::new(
+            "call_genotype",
+            &["variants", "samples", "ploidy"],
+            vec![n_samples, ploidy],
+            Buffer::Int8(vec![]),
+        ),
+        ZarrArray::new(
+            "call_genotype_phased",
+            &["variants", "samples"],
+            vec![n_samples],
+            Buffer::Bool(vec![]),
+        ),
+        ZarrArray::new(
+            "call_genotype_mask",
+            &["variants", "samples", "ploidy"],
+            vec![n_samples, ploidy],
+            Buffer::Bool(vec![]),
+        ),
+    ];
+    if let Some((field, array)) = writer.fields.iter().find_map(|field| {
+        variant_arrays
+            .iter()
+            .find(|array| array.name == field.array.name)
+            .map(|array| (field, array))
+    }) {
+        bail!(
+            "{} {} clashes with the array {}",
+            if field.format { "FORMAT" } else { "INFO" },
+            field.tag,
+            array.name
+        );
+    }
+    let first_variant_array = writer.arrays.len();
+    writer
+        .arrays
+        .extend(variant_arrays.into_iter().map(|a| a.chunks(&options)));
+
+    for record in BcfRecords::from_path(&input)? {
+        let arrays = &mut writer.arrays[first_variant_array..];
+        let chrom = record.try_chrom()?;
+        let contig = contigs.iter().position(|c| c == chrom);
+        let id = record.id();
+        let mut alleles_of_record = vec![record.ref_allele()];
+        alleles_of_record.extend(record.alt_alleles());
+        let record_filters = record.try_filters()?;
+        let genotypes = record.format(b"GT");
+        for array in arrays.iter_mut() {
+            array.rows += 1;
+            match (array.name.as_str(), &mut array.buffer) {
+                ("variant_contig", Buffer::Int32(v)) => {
+                    v.push(contig.map_or(INT_MISSING, |i| i as i32))
+                }
+                ("variant_position", Buffer::Int32(v)) => v.push(record.pos() as i32 + 1),
+                ("variant_id", Buffer::Str(v)) => v.push(if id.is_empty() {
+                    STRING_MISSING.into()
+                } else {
+                    String::from_utf8_lossy(&id).into_owned()
+                }),
+                ("variant_id_mask", Buffer::Bool(v)) => v.push(id.is_empty() || id == b"."),
+                ("variant_allele", Buffer::Str(v)) => push_padded(
+                    v,
+                    alleles_of_record
+                        .iter()
+                        .map(|a| String::from_utf8_lossy(a).into_owned()),
+                    alleles,
+                    STRING_FILL.into(),
+                ),
+                ("variant_quality", Buffer::Float32(v)) => v.push(
+                    record
+                        .qual()
+                        .unwrap_or_else(|| f32::from_bits(MISSING_FLOAT)),
+                ),
+                ("variant_filter", Buffer::Bool(v)) => {
+                    v.extend(filters.iter().map(|f| record_filters.contains(&f.as_str())))
+                }
+                ("call_genotype", Buffer::Int8(v)) => {
+                    for sample in 0..n_samples {
+                        let alleles = match genotypes.as_ref().and_then(|gt| gt.get(sample)) {
+                            Some(TypedVec::Int32(alleles)) => alleles.as_slice(),
+                            _ => &[],
+                        };
+                        // 0 and 1 denote missing alleles, see `GenotypeAllele`
+                        let alleles = alleles.iter().map(|&a| ((a >> 1) - 1) as i8);
+                        push_padded(v, alleles, ploidy, INT_FILL as i8);
+                    }
+                }
+                ("call_genotype_phased", Buffer::Bool(v)) => {
+                    for sample in 0..n_samples {
+                        v.push(match genotypes.as_ref().and_then(|gt| gt.get(sample)) {
+                            Some(TypedVec::Int32(alleles)) => {
+                                alleles.len() > 1 && alleles[1..].iter().all(|a| a & 1 == 1)
+                            }
+                            _ => false,
+                        });
+                    }
+                }
+                ("call_genotype_mask", Buffer::Bool(v)) => {
+                    for sample in 0..n_samples {
+                        let alleles = match genotypes.as_ref().and_then(|gt| gt.get(sample)) {
+                            Some(TypedVec::Int32(alleles)) => alleles.as_slice(),
+                            _ => &[],
+                        };
+                        let missing = alleles.iter().map(|&a| (a >> 1) == 0);
+                        push_padded(v, missing, ploidy, true);
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        for field in writer.fields.iter_mut() {
+            field.array.rows += 1;
+            let n = field.count.unwrap_or(1);
+            if field.format {
+                let values = record.format(field.tag.as_bytes());
+                for sample in 0..n_samples {
+                    let value = values.as_ref().and_then(|v| v.get(sample));
+                    push_value(&mut field.array.buffer, value, n);
+                }
+            } else {
+                let value = record.info(field.tag.as_bytes());
+                push_value(&mut field.array.buffer, value.as_ref(), n);
+            }
+        }
+        writer.flush(false)?;
+    }
+    writer.flush(true)?;
+    writer.write_metadata(&header)
+}
+
+/// The arrays of all INFO and FORMAT fields (except GT).
+fn field_arrays(
+    header: &Header,
+    dimensions: &Dimensions,
+    n_samples: usize,
+    options: &ZarrOptions,
+) -> Vec<FieldArray> {
+    let alleles = dimensions.alleles.max(1);
+    let definitions = header
+        .info
+        .values()
+        .map(|info| (false, &info.id, info.number(), info.kind()))
+        .chain(
+            header
+                .format
+                .values()
+                .filter(|format| format.id != "GT")
+                .map(|format| (true, &format.id, format.number(), format.kind())),
+        );
+    definitions
+        .map(|(format, tag, number, kind)| {
+            let name = format!("{}_{}", if format { "call" } else { "variant" }, tag);
+            let key = if format {
+                format!("FORMAT/{}", tag)
+            } else {
+                tag.clone()
+            };
+            let observed = dimensions.counts.get(&key).copied().unwrap_or(0).max(1);
+            let dimension = format!("{}_dim", name);
+            let (count, dimension) = match (kind, number) {
+                (InfoType::Flag, _) | (_, InfoNumber::Count(1)) => (None, None),
+                (_, InfoNumber::Alleles) => (Some(alleles), Some("alleles")),
+                (_, InfoNumber::AlternateAlleles) => {
+                    (Some((alleles - 1).max(1)), Some("alt_alleles"))
+                }
+                _ => (Some(observed), Some(dimension.as_str())),
+            };
+            let mut dimensions = vec!["variants"];
+            let mut inner = vec![];
+            if format {
+                dimensions.push("samples");
+                inner.push(n_samples);
+            }
+            if let (Some(count), Some(dimension)) = (count, dimension) {
+                dimensions.push(dimension);
+                inner.push(count);
+            }
+            let buffer = match kind {
+                InfoType::Flag => Buffer::Bool(vec![]),
+                InfoType::Integer => Buffer::Int32(vec![]),
+                InfoType::Float => Buffer::Float32(vec![]),
+                InfoType::Character | InfoType::String => Buffer::Str(vec![]),
+            };
+            FieldArray {
+                tag: tag.clone(),
+                format,
+                count,
+                array: ZarrArray::new(&name, &dimensions, inner, buffer).chunks(options),
+            }
+        })
+        .collect()
+}
+
+struct StoreWriter {
+    store: PathBuf,
+    level: u32,
+    arrays: Vec<ZarrArray>,
+    fields: Vec<FieldArray>,
+}
+
+impl StoreWriter {
+    fn flush(&mut self, last: bool) -> anyhow::Result<()> {
+        let arrays = self
+            .arrays
+            .iter_mut()
+            .chain(self.fields.iter_mut().map(|f| &mut f.array));
+        for array in arrays {
+            array.flush(&self.store, self.level, last)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the metadata of the group and all arrays, including consolidated metadata.
+    fn write_metadata(&self, header: &Header) -> anyhow::Result<()> {
+        let mut consolidated = serde_json::Map::new();
+        let zgroup = json!({ "zarr_format": 2 });
+        let zattrs = json!({
+            "source": format!("rust-bcf {}", env!("CARGO_PKG_VERSION")),
+            "vcf_header": header.to_vcf_text(),
+        });
+        fs::create_dir_all(&self.store)?;
+        for (key, value) in [(".zgroup", zgroup), (".zattrs", zattrs)] {
+            fs::write(self.store.join(key), serde_json::to_vec_pretty(&value)?)?;
+            consolidated.insert(key.into(), value);
+        }
+        let arrays = self
+            .arrays
+            .iter()
+            .chain(self.fields.iter().map(|f| &f.array));
+        for array in arrays {
+            let directory = self.store.join(&array.name);
+            fs::create_dir_all(&directory)?;
+            let (zarray, zattrs) = array.metadata(self.level);
+            for (key, value) in [(".zarray", zarray), (".zattrs", zattrs)] {
+                fs::write(directory.join(key), serde_json::to_vec_pretty(&value)?)?;
+                consolidated.insert(format!("{}/{}", array.name, key), value);
+            }
+        }
+        let zmetadata = json!({
+            "zarr_consolidated_format": 1,
+            "metadata": consolidated,
+        });
+        fs::write(
+            self.store.join(".zmetadata"),
+            serde_json::to_vec_pretty(&zmetadata)?,
+        )?;
+        Ok(())
+    }
+}
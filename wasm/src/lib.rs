@@ -0,0 +1,127 @@
+//! WebAssembly bindings of the `rust-bcf` BCF parser, for previewing BCF files in the browser.
+//!
+//! Files are fed in chunks, e.g. slices of a `Blob`, and only the complete records among them
+//! are decoded, so a preview does not need the whole file.
+use std::convert::TryInto;
+use std::io::{Cursor, Read};
+
+use anyhow::bail;
+use bcf::BcfRecords;
+use flate2::read::MultiGzDecoder;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// The magic bytes of BCF 2.2 files.
+const BCF_MAGIC: &[u8] = b"BCF\x02\x02";
+
+/// The magic bytes of gzip (and thus BGZF) files.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// A preview of a BCF file, compressed or not, read from its start in chunks.
+///
+/// ```js
+/// const preview = new BcfPreview();
+/// const chunkSize = 1 << 16;
+/// for (let offset = 0; offset < Math.min(file.size, 1 << 20); offset += chunkSize) {
+///     const chunk = await file.slice(offset, offset + chunkSize).arrayBuffer();
+///     preview.push(new Uint8Array(chunk));
+/// }
+/// const header = preview.header();
+/// const records = preview.records(10);
+/// ```
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct BcfPreview {
+    /// The chunks pushed so far
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl BcfPreview {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the next chunk of the file.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.data.extend_from_slice(chunk);
+    }
+
+    /// Returns the header with the samples, contigs, filters, and INFO and FORMAT definitions;
+    /// fails if the chunks pushed so far do not contain the whole header.
+    pub fn header(&self) -> Result<JsValue, JsError> {
+        let records = self.bcf_records().map_err(js_error)?;
+        Ok(to_js(records.header())?)
+    }
+
+    /// Returns the first `n` records, or all complete records pushed so far if there are fewer.
+    pub fn records(&self, n: usize) -> Result<JsValue, JsError> {
+        let records = self.bcf_records().map_err(js_error)?;
+        let records = records.take(n).collect::<Vec<_>>();
+        Ok(to_js(&records)?)
+    }
+}
+
+impl BcfPreview {
+    /// Returns the records of the complete prefix of the chunks pushed so far.
+    fn bcf_records(&self) -> anyhow::Result<BcfRecords<Cursor<Vec<u8>>>> {
+        let mut data = if self.data.starts_with(GZIP_MAGIC) {
+            decompress(&self.data)
+        } else {
+            self.data.clone()
+        };
+        data.truncate(complete_length(&data)?);
+        BcfRecords::new(Cursor::new(data))
+    }
+}
+
+/// Decompresses as much of the given (possibly truncated) gzip data as possible.
+fn decompress(data: &[u8]) -> Vec<u8> {
+    let mut decoder = MultiGzDecoder::new(data);
+    let mut decompressed = vec![];
+    let mut buffer = [0u8; 1 << 16];
+    // a truncated block ends decompression with an error, but keeps everything before it
+    while let Ok(n @ 1..) = decoder.read(&mut buffer) {
+        decompressed.extend_from_slice(&buffer[..n]);
+    }
+    decompressed
+}
+
+/// Returns the length of the header and all complete records at the start of the given
+/// (uncompressed) BCF data.
+fn complete_length(data: &[u8]) -> anyhow::Result<usize> {
+    let u32_at = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    if data.len() < BCF_MAGIC.len() {
+        bail!("not enough data for the BCF header");
+    }
+    if !data.starts_with(BCF_MAGIC) {
+        bail!("not a BCF 2.2 file");
+    }
+    let mut length = match u32_at(BCF_MAGIC.len()) {
+        Some(header_length) if BCF_MAGIC.len() + 4 + header_length <= data.len() => {
+            BCF_MAGIC.len() + 4 + header_length
+        }
+        _ => bail!("not enough data for the BCF header"),
+    };
+    while let (Some(l_shared), Some(l_indiv)) = (u32_at(length), u32_at(length + 4)) {
+        let end = length + 8 + l_shared + l_indiv;
+        if end > data.len() {
+            break;
+        }
+        length = end;
+    }
+    Ok(length)
+}
+
+fn js_error(error: anyhow::Error) -> JsError {
+    JsError::new(&format!("{:#}", error))
+}
+
+/// Converts into plain JS objects, i.e. with maps as objects rather than `Map`s.
+fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, serde_wasm_bindgen::Error> {
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+}
@@ -0,0 +1,70 @@
+//! Run with `wasm-pack test --node` or `cargo test --target wasm32-unknown-unknown` with
+//! `wasm-bindgen-test-runner` as runner.
+#![cfg(target_arch = "wasm32")]
+
+use rust_bcf_wasm::BcfPreview;
+use serde_json::Value;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const UNCOMPRESSED: &[u8] = include_bytes!("../../resources/example.uncompressed.bcf");
+const COMPRESSED: &[u8] = include_bytes!("../../resources/example.compressed.bcf");
+
+fn json(value: JsValue) -> Value {
+    serde_wasm_bindgen::from_value(value).unwrap()
+}
+
+fn preview(data: &[u8], chunk_size: usize) -> BcfPreview {
+    let mut preview = BcfPreview::new();
+    for chunk in data.chunks(chunk_size) {
+        preview.push(chunk);
+    }
+    preview
+}
+
+#[wasm_bindgen_test]
+fn header() {
+    let header = json(preview(UNCOMPRESSED, 1000).header().unwrap());
+    assert_eq!(
+        header["samples"],
+        serde_json::json!(["HG001", "INTEGRATION", "HG003"])
+    );
+    assert_eq!(header["contigs"][0]["id"], "chr1");
+    assert!(BcfPreview::new().header().is_err());
+    assert!(preview(&UNCOMPRESSED[..100], 100).header().is_err());
+    assert!(preview(b"##fileformat=VCFv4.2\n", 100).header().is_err());
+}
+
+#[wasm_bindgen_test]
+fn records() {
+    let records = json(preview(UNCOMPRESSED, 1000).records(3).unwrap());
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["chrom"], "chr1");
+    assert!(records[0]["pos"].is_u64());
+    assert!(records[0]["alleles"].as_array().unwrap().len() >= 2);
+    assert!(records[0]["format"]["GT"].is_array());
+
+    // compressed input gives the same records
+    assert_eq!(
+        json(preview(COMPRESSED, 1000).records(3).unwrap()),
+        Value::Array(records.clone())
+    );
+}
+
+#[wasm_bindgen_test]
+fn truncated() {
+    let all = json(preview(UNCOMPRESSED, 1 << 16).records(usize::MAX).unwrap());
+    let all = all.as_array().unwrap();
+    for data in [UNCOMPRESSED, COMPRESSED] {
+        // only the complete records of a truncated file are returned
+        let partial = json(
+            preview(&data[..data.len() / 2], 1 << 16)
+                .records(usize::MAX)
+                .unwrap(),
+        );
+        let partial = partial.as_array().unwrap();
+        assert!(partial.len() < all.len());
+        assert_eq!(partial[..], all[..partial.len()]);
+    }
+}